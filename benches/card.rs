@@ -0,0 +1,175 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compares the packed `Card` representation against plain index math.
+//!
+//! `Card` packs the four ternary features of a card into the bytes of a
+//! `u32`, so that sets can be checked and completed with a handful of
+//! integer operations. The examples skip `Card` entirely and work with
+//! indices instead. This benchmark times both approaches over every
+//! pair of cards, and over a batch of boards when finding sets, so
+//! changes to the packing can be judged by the numbers.
+//!
+//! Run it with `cargo bench --features bench`.
+
+extern crate core;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use core::card::{Card, CompleteSet, ToSet};
+use core::deck::{cards, DECK_SIZE};
+use core::find::FindSets;
+
+/// Passes over every pair of cards for each measurement.
+const ROUNDS: u32 = 200;
+
+/// Boards searched for sets, and the cards on each one.
+const BOARDS: usize = 1000;
+const BOARD_SIZE: usize = 12;
+
+/// Returns the four ternary features of a card index.
+fn trits(index: usize) -> [usize; 4] {
+    [index / 27, index / 9 % 3, index / 3 % 3, index % 3]
+}
+
+/// The inverse of `trits`.
+fn untrits(trits: [usize; 4]) -> usize {
+    trits.iter().fold(0, |index, &trit| index * 3 + trit)
+}
+
+/// Sums a card's features, with the count in [1,3] like `Card::count`.
+fn feature_sum(card: Card) -> usize {
+    usize::from(card.count()) + card.shape() as usize + card.color() as usize + card.shading() as usize
+}
+
+/// Index-based equivalent of `feature_sum`.
+fn feature_sum_index(index: usize) -> usize {
+    let [count, shape, color, shading] = trits(index);
+    count + 1 + shape + color + shading
+}
+
+/// Index-based equivalent of `CompleteSet::complete_set`.
+fn complete_index(a: usize, b: usize) -> usize {
+    let (a, b) = (trits(a), trits(b));
+    (0..4).fold(0, |index, i| index * 3 + (6 - a[i] - b[i]) % 3)
+}
+
+/// Index-based equivalent of `ToSet::to_set`.
+fn is_set_index(a: usize, b: usize, c: usize) -> bool {
+    let (a, b, c) = (trits(a), trits(b), trits(c));
+    (0..4).all(|i| (a[i] + b[i] + c[i]).is_multiple_of(3))
+}
+
+/// Index-based equivalent of `FindSets::count_sets`.
+fn count_sets_index(board: &[usize]) -> usize {
+    let mut count = 0;
+    for i in 0..board.len() {
+        for j in i + 1..board.len() {
+            for k in j + 1..board.len() {
+                count += is_set_index(board[i], board[j], board[k]) as usize;
+            }
+        }
+    }
+    count
+}
+
+/// Spreads each board's distinct cards across the deck, so the boards
+/// hold a mix of set counts without needing a random number generator.
+fn boards() -> Vec<Vec<usize>> {
+    (0..BOARDS)
+        .map(|n| (0..BOARD_SIZE).map(|i| (n * 7 + i * (i + 1)) % DECK_SIZE).collect())
+        .collect()
+}
+
+/// Runs `f` over every pair of cards `ROUNDS` times, returning the
+/// average time per pair.
+fn measure<F>(f: F) -> Duration
+where
+    F: Fn(usize, usize) -> usize,
+{
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for a in 0..DECK_SIZE {
+            for b in 0..DECK_SIZE {
+                black_box(f(black_box(a), black_box(b)));
+            }
+        }
+    }
+    start.elapsed() / (ROUNDS * (DECK_SIZE * DECK_SIZE) as u32)
+}
+
+/// Runs `f` over every board, returning the average time per board.
+fn measure_boards<T, F>(boards: &[T], f: F) -> Duration
+where
+    F: Fn(&T) -> usize,
+{
+    let start = Instant::now();
+    for board in boards {
+        black_box(f(black_box(board)));
+    }
+    start.elapsed() / boards.len() as u32
+}
+
+fn report(name: &str, packed: Duration, index: Duration) {
+    let ratio = packed.as_secs_f64() / index.as_secs_f64();
+    println!(
+        "{:<20} {:>10.2?} {:>10.2?} {:>8.2}x",
+        name, packed, index, ratio
+    );
+}
+
+fn main() {
+    let deck = cards();
+
+    // make sure both approaches agree before timing them
+    for (a, &x) in deck.iter().enumerate() {
+        for (b, &y) in deck.iter().enumerate() {
+            let c = complete_index(a, b);
+            assert_eq!((x, y).complete_set().index(), c);
+            assert_eq!((x, y, deck[c]).to_set().is_some(), is_set_index(a, b, c));
+        }
+        assert_eq!(Card::new(a).index(), untrits(trits(a)));
+        assert_eq!(feature_sum(x), feature_sum_index(a));
+    }
+
+    let boards = boards();
+    let card_boards: Vec<Vec<Card>> = boards.iter().map(|board| board.iter().map(|&i| deck[i]).collect()).collect();
+    for (board, cards) in boards.iter().zip(&card_boards) {
+        assert_eq!(cards.count_sets(), count_sets_index(board));
+    }
+
+    println!("{:<20} {:>10} {:>10} {:>9}", "operation", "packed", "index", "ratio");
+
+    let packed = measure(|a, b| Card::new(a).index() + Card::new(b).index());
+    let index = measure(|a, b| untrits(trits(a)) + untrits(trits(b)));
+    report("conversion", packed, index);
+
+    let packed = measure(|a, b| feature_sum(deck[a]) + feature_sum(deck[b]));
+    let index = measure(|a, b| feature_sum_index(a) + feature_sum_index(b));
+    report("features", packed, index);
+
+    let packed = measure(|a, b| (deck[a], deck[b]).complete_set().index());
+    let index = measure(complete_index);
+    report("complete_set", packed, index);
+
+    let packed = measure(|a, b| (deck[a], deck[b], deck[(a + b) % DECK_SIZE]).to_set().is_some() as usize);
+    let index = measure(|a, b| is_set_index(a, b, (a + b) % DECK_SIZE) as usize);
+    report("to_set", packed, index);
+
+    let packed = measure_boards(&card_boards, |cards| cards.count_sets());
+    let index = measure_boards(&boards, |board| count_sets_index(board));
+    report("find_sets", packed, index);
+}
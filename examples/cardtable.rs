@@ -0,0 +1,102 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+extern crate clap;
+extern crate core;
+
+use clap::{Parser, ValueEnum};
+
+use core::card::Card;
+use core::deck::all_cards;
+use core::graphics::ColorScheme;
+
+const SCHEMES: [(&str, ColorScheme); 2] = [
+    ("cmyk", ColorScheme::CMYK),
+    ("classic", ColorScheme::Classic),
+];
+
+#[derive(Parser)]
+#[command(version)]
+#[command(about = "Print the features and colors of every Marmoset card.")]
+struct Cli {
+    /// Set the output format
+    #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// The features of `card`, in column order.
+fn features(card: Card) -> (usize, u8, &'static str, String, &'static str) {
+    (
+        card.index(),
+        card.count(),
+        card.shape().name(),
+        format!("{:?}", card.color()),
+        card.shading().name(),
+    )
+}
+
+fn print_csv() {
+    let mut header = String::from("index,count,shape,color,shading");
+    for (scheme, _) in SCHEMES.iter() {
+        header += &format!(",{0}_name,{0}_r,{0}_g,{0}_b", scheme);
+    }
+    println!("{}", header);
+
+    for card in all_cards() {
+        let (index, count, shape, color, shading) = features(card);
+        let mut row = format!("{},{},{},{},{}", index, count, shape, color, shading);
+
+        for &(_, scheme) in SCHEMES.iter() {
+            let (r, g, b) = scheme.card_rgb(card);
+            row += &format!(",{},{},{},{}", scheme.color_name(card.color()), r, g, b);
+        }
+        println!("{}", row);
+    }
+}
+
+fn print_json() {
+    let rows: Vec<_> = all_cards()
+        .map(|card| {
+            let (index, count, shape, color, shading) = features(card);
+            let colors: Vec<_> = SCHEMES.iter()
+                .map(|&(name, scheme)| {
+                    let (r, g, b) = scheme.card_rgb(card);
+                    format!(r#""{}": {{"name": "{}", "rgb": [{}, {}, {}]}}"#,
+                            name, scheme.color_name(card.color()), r, g, b)
+                })
+                .collect();
+
+            format!(r#"  {{"index": {}, "count": {}, "shape": "{}", "color": "{}", "shading": "{}", {}}}"#,
+                    index, count, shape, color, shading, colors.join(", "))
+        })
+        .collect();
+
+    println!("[\n{}\n]", rows.join(",\n"));
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    match cli.format {
+        Format::Csv => print_csv(),
+        Format::Json => print_json(),
+    }
+}
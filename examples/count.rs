@@ -38,7 +38,7 @@
 //!
 //! As it is, this program runs in about 3 minutes on my machine. It makes use of the fact that
 //! there is an isomorphism between a `core::Card` and its index. It only uses `core::Card`
-//! objects directly when initializing the `SETS` lookup table, and otherwise just works with
+//! objects directly when initializing the `SetLookup` table, and otherwise just works with
 //! the cards by index. It recursively builds up a hand of cards, and abandons branches of the
 //! search tree as soon as the hand contains a SuperSet.
 //!
@@ -61,17 +61,13 @@ use std::cmp;
 use std::ops::Range;
 use std::time::{Duration, Instant};
 
-use core::card::*;
-use core::deck::cards;
-use core::pair_iter::PairIter;
 use core::utils::pretty_print;
+use core::SetLookup;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// The number of cards composing a SuperSet.
 const SUPERSET_SIZE: usize = 4;
 
-type Lookup = [[u8; 81]; 81];
-
 struct Combination {
     /// Current combination.
     hand: Vec<usize>,
@@ -89,7 +85,7 @@ struct Count {
 }
 
 fn count_null_supersets(deal_size: usize) -> Count {
-    let table = build_lookup();
+    let table = SetLookup::new();
     let start_time = Instant::now();
     let sum = (deal_size - 1..81)
         .into_par_iter()
@@ -103,7 +99,7 @@ fn count_null_supersets(deal_size: usize) -> Count {
     }
 }
 
-fn deal_hands(start: usize, deal_size: usize, table: &Lookup) -> u64 {
+fn deal_hands(start: usize, deal_size: usize, table: &SetLookup) -> u64 {
     let mut data = Combination {
         hand: Vec::with_capacity(deal_size),
         null_count: 0,
@@ -115,7 +111,7 @@ fn deal_hands(start: usize, deal_size: usize, table: &Lookup) -> u64 {
     data.null_count
 }
 
-fn deal_another_card(data: &mut Combination, range: Range<usize>, table: &Lookup) {
+fn deal_another_card(data: &mut Combination, range: Range<usize>, table: &SetLookup) {
     let depth = range.start;
 
     for y in range {
@@ -196,28 +192,16 @@ fn choose(n: u64, k: u64) -> u64 {
     (1..m).fold(1, |product, i| product * (n + 1 - i) / i)
 }
 
-fn build_lookup() -> Lookup {
-    let cards = cards();
-    let mut table = [[0; 81]; 81];
-
-    for (&a, &b) in (0..81).collect::<Vec<_>>().pairs() {
-        let c = (cards[a], cards[b]).complete_set().index() as u8;
-        table[a][b] = c;
-        // `complete_set()` is commutative
-        table[b][a] = c;
-    }
-
-    table
-}
-
-fn is_superset(a: usize, b: usize, c: usize, d: usize, table: &Lookup) -> bool {
-    table[a][b] == table[c][d] || table[a][c] == table[b][d] || table[a][d] == table[b][c]
+fn is_superset(a: usize, b: usize, c: usize, d: usize, table: &SetLookup) -> bool {
+    table.complete(a, b) == table.complete(c, d)
+        || table.complete(a, c) == table.complete(b, d)
+        || table.complete(a, d) == table.complete(b, c)
 }
 
 /// This function assumes that `hand` does not already contain a
 /// SuperSet. It only tests combinations that include `extra`.
 #[allow(clippy::needless_range_loop)]
-fn contains_superset(hand: &[usize], extra: usize, table: &Lookup) -> bool {
+fn contains_superset(hand: &[usize], extra: usize, table: &SetLookup) -> bool {
     for a in 2..hand.len() {
         for b in 1..a {
             for c in 0..b {
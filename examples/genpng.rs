@@ -20,13 +20,15 @@ extern crate clap;
 extern crate core;
 
 use cairo::{Context, Format, ImageSurface, Operator, Rectangle};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::f64::consts::FRAC_PI_2;
 use std::fs::File;
 use std::mem;
 
+use core::card::Card;
 use core::deck::cards;
 use core::graphics::*;
+use core::log_error;
 use core::utils::clamp;
 
 const CARD_ASPECT_RATIO: f64 = 3.5 / 2.25;
@@ -54,14 +56,72 @@ struct Cli {
     /// Use classic SET colors
     #[arg(short, long)]
     classic: bool,
+
+    /// Show each card in both color schemes (CMYK left, classic right)
+    #[arg(long, conflicts_with = "classic")]
+    compare: bool,
+
+    /// Preview the cards as seen with a color vision deficiency
+    #[arg(short, long, value_enum)]
+    simulate: Option<Simulation>,
+
+    /// Only generate the card with this index, 0 to 80 (repeatable)
+    #[arg(short = 'k', long = "card", value_name = "INDEX")]
+    cards: Vec<Card>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Simulation {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl From<Simulation> for Deficiency {
+    fn from(simulation: Simulation) -> Deficiency {
+        match simulation {
+            Simulation::Protanopia => Deficiency::Protanopia,
+            Simulation::Deuteranopia => Deficiency::Deuteranopia,
+            Simulation::Tritanopia => Deficiency::Tritanopia,
+        }
+    }
+}
+
+/// Passes every pixel of the surface through the deficiency simulation.
+fn simulate_surface(
+    surface: &mut ImageSurface,
+    kind: Deficiency,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    surface.flush();
+    let mut data = surface.data()?;
+
+    // ARgb32 pixels are native endian with premultiplied alpha
+    for pixel in data.chunks_exact_mut(4) {
+        let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        let alpha = (argb >> 24) as f64 / 255.;
+        if alpha == 0. {
+            continue;
+        }
+
+        let channel = |shift: u32| ((argb >> shift) & 0xff) as f64 / 255. / alpha;
+        let (r, g, b) = simulate_cvd((channel(16), channel(8), channel(0)), kind);
+        let pack = |c: f64, shift: u32| ((c * alpha * 255.).round() as u32) << shift;
+
+        let argb = (argb & 0xff00_0000) | pack(r, 16) | pack(g, 8) | pack(b, 0);
+        pixel.copy_from_slice(&argb.to_ne_bytes());
+    }
+
+    Ok(())
 }
 
 fn generate_card_images(
     path: &str,
+    cards: &[Card],
     card_width: i32,
     border: i32,
     vertical: bool,
-    scheme: ColorScheme,
+    schemes: &[ColorScheme],
+    simulation: Option<Deficiency>,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let card_height = (card_width as f64 / CARD_ASPECT_RATIO).ceil() as i32;
     // offset by (border, border)
@@ -79,17 +139,16 @@ fn generate_card_images(
         mem::swap(&mut ctx_width, &mut ctx_height);
     }
 
-    // create the surface and context
-    let surface = ImageSurface::create(Format::ARgb32, ctx_width, ctx_height)
+    // create the surface, with a panel for each scheme
+    let panels = schemes.len() as i32;
+    let mut surface = ImageSurface::create(Format::ARgb32, ctx_width * panels, ctx_height)
         .expect("Could not create surface.");
-    let ctx = Context::new(&surface)?;
-    if vertical {
-        // adjust the transform to account for the vertical orientation
-        ctx.rotate(FRAC_PI_2);
-        ctx.translate(0.0, -ctx_width as f64);
-    }
 
-    for card in cards() {
+    for &card in cards {
+        // the context is recreated for each card since the surface
+        // data can only be accessed once the context is dropped
+        let ctx = Context::new(&surface)?;
+
         // completely clear the context to avoid accumulating color on
         // any edge that antialiases over the transparent background
         // (e.g. rounded card corners)
@@ -98,22 +157,38 @@ fn generate_card_images(
         ctx.paint()?;
         ctx.restore()?;
 
-        if border > 0 {
-            ctx.rounded_rect(card_rect, card_corner_radius(card_rect));
-            ctx.set_source_gray(0.0);
-            // half the stroke will be covered by the card
-            ctx.set_line_width(border as f64 * 2.);
-            ctx.stroke()?;
+        for (panel, &scheme) in schemes.iter().enumerate() {
+            ctx.save()?;
+            ctx.translate((ctx_width * panel as i32) as f64, 0.0);
+            if vertical {
+                // adjust the transform to account for the vertical orientation
+                ctx.rotate(FRAC_PI_2);
+                ctx.translate(0.0, -ctx_width as f64);
+            }
+
+            if border > 0 {
+                ctx.rounded_rect(card_rect, card_corner_radius(card_rect));
+                ctx.set_source_gray(0.0);
+                // half the stroke will be covered by the card
+                ctx.set_line_width(border as f64 * 2.);
+                ctx.stroke()?;
+            }
+
+            ctx.draw_card(card, card_rect, None, scheme)?;
+            ctx.restore()?;
         }
+        drop(ctx);
 
-        ctx.draw_card(card, card_rect, None, scheme)?;
+        if let Some(kind) = simulation {
+            simulate_surface(&mut surface, kind)?;
+        }
 
         let filename = format!("{}/{}.png", path, card.index());
         let mut image = File::create(&filename)?;
 
         surface
             .write_to_png(&mut image)
-            .unwrap_or_else(|_| println!("Error writing {}", filename));
+            .unwrap_or_else(|err| log_error!("Error writing {}: {}", filename, err));
     }
 
     Ok(())
@@ -137,6 +212,15 @@ fn main() {
         ColorScheme::CMYK
     };
 
-    generate_card_images(path, width, border, render_vertically, scheme)
-        .unwrap_or_else(|e| println!("{}", e));
+    let schemes = if cli.compare {
+        vec![ColorScheme::CMYK, ColorScheme::Classic]
+    } else {
+        vec![scheme]
+    };
+
+    let simulation = cli.simulate.map(Deficiency::from);
+    let cards = if cli.cards.is_empty() { cards() } else { cli.cards };
+
+    generate_card_images(path, &cards, width, border, render_vertically, &schemes, simulation)
+        .unwrap_or_else(|e| log_error!("{}", e));
 }
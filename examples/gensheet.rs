@@ -0,0 +1,154 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(clippy::cast_lossless)]
+
+extern crate cairo;
+extern crate clap;
+extern crate core;
+
+use cairo::{Context, PdfSurface, Rectangle};
+use clap::{Parser, ValueEnum};
+
+use core::deck::cards;
+use core::graphics::*;
+use core::log_error;
+use core::utils::clamp;
+
+const CARD_ASPECT_RATIO: f64 = 3.5 / 2.25;
+/// space around the edge of each page, in points
+const MARGIN: f64 = 36.;
+/// space between neighboring cards, in points
+const GUTTER: f64 = 9.;
+
+#[derive(Parser)]
+#[command(version)]
+#[command(about = "Lay out the Marmoset deck on printable PDF pages.")]
+struct Cli {
+    /// The PDF file to write
+    output: String,
+
+    /// Set the number of cards across each page
+    #[arg(long, default_value_t = 2)]
+    columns: usize,
+
+    /// Set the number of cards down each page
+    #[arg(long, default_value_t = 4)]
+    rows: usize,
+
+    /// Set the paper size
+    #[arg(short, long, value_enum, default_value_t = Paper::Letter)]
+    paper: Paper,
+
+    /// Use classic SET colors
+    #[arg(short, long)]
+    classic: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Paper {
+    Letter,
+    A4,
+}
+
+impl Paper {
+    /// Portrait page size in points.
+    fn size(self) -> (f64, f64) {
+        match self {
+            Paper::Letter => (612., 792.),
+            Paper::A4 => (595.28, 841.89),
+        }
+    }
+}
+
+/// The largest card that lets a `columns` by `rows` grid fit within
+/// the margins of a page of the given size.
+fn card_size((page_width, page_height): (f64, f64), columns: usize, rows: usize) -> (f64, f64) {
+    let across = (page_width - 2. * MARGIN - GUTTER * (columns - 1) as f64) / columns as f64;
+    let down = (page_height - 2. * MARGIN - GUTTER * (rows - 1) as f64) / rows as f64;
+    let width = across.min(down * CARD_ASPECT_RATIO);
+    (width, width / CARD_ASPECT_RATIO)
+}
+
+/// Writes the deck to `path`, filling each page before starting the
+/// next. Returns the number of pages.
+fn generate_sheet(
+    path: &str,
+    paper: Paper,
+    columns: usize,
+    rows: usize,
+    scheme: ColorScheme,
+) -> std::result::Result<usize, Box<dyn std::error::Error>> {
+    let (page_width, page_height) = paper.size();
+    let (card_width, card_height) = card_size(paper.size(), columns, rows);
+
+    // center the grid, so every page has the same margins regardless
+    // of how the cards divide the space
+    let grid_width = columns as f64 * (card_width + GUTTER) - GUTTER;
+    let grid_height = rows as f64 * (card_height + GUTTER) - GUTTER;
+    let left = (page_width - grid_width) / 2.;
+    let top = (page_height - grid_height) / 2.;
+
+    let surface = PdfSurface::new(page_width, page_height, path)?;
+    let ctx = Context::new(&surface)?;
+    let deck = cards();
+    let pages = deck.chunks(columns * rows).count();
+
+    for (page, chunk) in deck.chunks(columns * rows).enumerate() {
+        if page > 0 {
+            ctx.show_page()?;
+        }
+
+        for (ix, &card) in chunk.iter().enumerate() {
+            let (row, column) = (ix / columns, ix % columns);
+            let rect = Rectangle::new(
+                left + column as f64 * (card_width + GUTTER),
+                top + row as f64 * (card_height + GUTTER),
+                card_width,
+                card_height,
+            );
+
+            // a hairline to cut along (half of it is covered by the card)
+            ctx.rounded_rect(rect, card_corner_radius(rect));
+            ctx.set_source_gray(0.5);
+            ctx.set_line_width(1.);
+            ctx.stroke()?;
+
+            ctx.draw_card(card, rect, None, scheme)?;
+        }
+    }
+
+    drop(ctx);
+    surface.finish();
+    Ok(pages)
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    // keep values within reasonable ranges (9x9 fits the deck on one page)
+    let columns = clamp(cli.columns, (1, 9));
+    let rows = clamp(cli.rows, (1, 9));
+    let scheme = if cli.classic {
+        ColorScheme::Classic
+    } else {
+        ColorScheme::CMYK
+    };
+
+    match generate_sheet(&cli.output, cli.paper, columns, rows, scheme) {
+        Ok(pages) => println!("Wrote {} page(s) to {}", pages, cli.output),
+        Err(e) => log_error!("{}", e),
+    }
+}
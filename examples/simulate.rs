@@ -37,6 +37,11 @@
 //!          15 |     766_796 |  0.07668 %
 //!          18 |         472 |  0.00005 %
 //!
+//! With `--guarantee`, a second batch of games doctors the deck the
+//! way the game does (see `Deck::draw_guaranteeing_set`), so that 18
+//! cards always contain a set, and the end game distributions of both
+//! batches are compared.
+//!
 //! As an optimization, this program makes use of the fact that there is an
 //! isomorphism between a `core::Card` and its index. It only uses `core::Card`
 //! objects directly when initializing the `SETS` lookup table, and otherwise just
@@ -58,11 +63,10 @@ use std::sync::{mpsc, LazyLock};
 use std::thread;
 use std::time::Instant;
 
-use core::card::*;
-use core::deck::cards;
 use core::pair_iter::PairIter;
 use core::shuffle::Shuffle;
 use core::utils::*;
+use core::SetLookup;
 
 const NUM_GAMES: u64 = 1_000_000;
 const INITIAL_DEAL: usize = 12;
@@ -80,6 +84,10 @@ struct Cli {
     /// Set number of threads to utilize
     #[arg(short, long, default_value_t = num_cpus::get())]
     threads: usize,
+
+    /// Also simulate games that guarantee a set in 18 cards, and compare
+    #[arg(long)]
+    guarantee: bool,
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -171,6 +179,32 @@ impl Counts {
 
         table.printstd();
     }
+
+    /// Prints the end game distributions of `self` and `other` side by side.
+    fn print_end_game_comparison(&self, other: &Counts) {
+        let mut table = Table::new();
+        table.set_format(*consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(row![r => "cards left", "no guarantee", "% of games", "guarantee", "% of games"]);
+
+        let (games, other_games) = (self.num_simulated(), other.num_simulated());
+        let iter = self.remainder.iter().zip(other.remainder.iter()).enumerate();
+
+        for (hand_size, (&count, &other_count)) in iter {
+            if count == 0 && other_count == 0 {
+                continue;
+            }
+
+            let percentage = (count as f64 / games as f64) * 100.0;
+            let other_percentage = (other_count as f64 / other_games as f64) * 100.0;
+            table.add_row(row![r => &hand_size.to_string(),
+                               &pretty_print(count),
+                               &format!("{:.5} %", percentage),
+                               &pretty_print(other_count),
+                               &format!("{:.5} %", other_percentage)]);
+        }
+
+        table.printstd();
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -206,32 +240,71 @@ impl IndexDeck {
         let x = cmp::min(n, r);
         self.stock.split_off(r - x)
     }
-}
 
-////////////////////////////////////////////////////////////////////////////////
-// Support Functions
-////////////////////////////////////////////////////////////////////////////////
+    /// Draws 3 cards that make a set with the 15 cards in `hand`.
+    /// This follows `Deck::draw_guaranteeing_set`.
+    pub fn draw_guaranteeing_set(&mut self, hand: &[usize]) -> Vec<usize> {
+        let mut draw = self.draw(SET_SIZE);
+        let mut test = hand.to_owned();
+        test.extend_from_slice(&draw);
 
-/// Lookup table for Sets.
-static SETS: LazyLock<[[usize; 81]; 81]> = std::sync::LazyLock::new(|| build_lookup());
+        if contains_set(&test) {
+            return draw;
+        }
+        self.stock.append(&mut draw);
+
+        self.fix_one_card(hand)
+            .or_else(|| self.fix_two_cards(hand))
+            .or_else(|| self.fix_three_cards())
+            .expect("21 cards always contain a set")
+    }
 
-fn build_lookup() -> [[usize; 81]; 81] {
-    let cards = cards();
-    let mut table = [[0; 81]; 81];
+    fn fix_one_card(&mut self, hand: &[usize]) -> Option<Vec<usize>> {
+        let mut hand = hand.to_owned();
+        hand.shuffle();
 
-    for (&a, &b) in (0..81).collect::<Vec<_>>().pairs() {
-        let c = (cards[a], cards[b]).complete_set().index();
-        table[a][b] = c;
-        // `complete_set()` is commutative
-        table[b][a] = c;
+        for c in hand.pairs().map(|(&a, &b)| SETS.complete(a, b)) {
+            if let Some(ix) = self.stock.iter().position(|&x| x == c) {
+                let last_ix = self.stock.len() - 1;
+                self.stock.swap(ix, last_ix);
+                return Some(self.draw(SET_SIZE));
+            }
+        }
+
+        None
+    }
+
+    fn fix_two_cards(&mut self, hand: &[usize]) -> Option<Vec<usize>> {
+        let (&a, &b) = self.stock.pairs()
+            .find(|&(&a, &b)| hand.contains(&SETS.complete(a, b)))?;
+
+        self.stock.retain(|&x| x != a && x != b);
+        let mut result = vec![a, b];
+        result.append(&mut self.draw(1));
+        Some(result)
     }
 
-    table
+    fn fix_three_cards(&mut self) -> Option<Vec<usize>> {
+        let (a, b, c) = find_random_set(&self.stock)?;
+        self.stock.retain(|&x| x != a && x != b && x != c);
+        Some(vec![a, b, c])
+    }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Support Functions
+////////////////////////////////////////////////////////////////////////////////
+
+/// Lookup table for Sets.
+static SETS: LazyLock<SetLookup> = LazyLock::new(SetLookup::new);
+
 #[inline(always)]
 fn is_set(a: usize, b: usize, c: usize) -> bool {
-    unsafe { *SETS.get_unchecked(a).get_unchecked(b) == c }
+    SETS.is_set(a, b, c)
+}
+
+fn contains_set(hand: &[usize]) -> bool {
+    hand.pairs().any(|(&a, &b)| hand.contains(&SETS.complete(a, b)))
 }
 
 fn find_random_set(hand: &[usize]) -> Option<(usize, usize, usize)> {
@@ -262,7 +335,7 @@ fn find_random_set(hand: &[usize]) -> Option<(usize, usize, usize)> {
 // Simulate
 ////////////////////////////////////////////////////////////////////////////////
 
-fn simulate_game(counts: &mut Counts) {
+fn simulate_game(counts: &mut Counts, guarantee: bool) {
     let mut deck = IndexDeck::new();
     let mut hand = deck.draw(INITIAL_DEAL);
 
@@ -284,6 +357,10 @@ fn simulate_game(counts: &mut Counts) {
                 // no sets and no stock remaining: game over
                 counts.remainder[hand.len()] += 1;
                 break 'game;
+            } else if guarantee && hand.len() == 15 && deck.remainder() >= 6 {
+                // doctor the deck as the game does
+                let mut draw = deck.draw_guaranteeing_set(&hand);
+                hand.append(&mut draw);
             } else {
                 // deal more cards to increase odds of set
                 hand.append(&mut deck.draw(SET_SIZE));
@@ -292,13 +369,13 @@ fn simulate_game(counts: &mut Counts) {
     }
 }
 
-fn run_simulations(num_games: u64, num_threads: u64) {
+fn run_simulations(num_games: u64, num_threads: u64, guarantee: bool) -> Counts {
     let start_time = Instant::now();
     let (tx, rx) = mpsc::channel();
     let (thread_chunk, rem) = (num_games / num_threads, num_games % num_threads);
 
     // initialize set lookup table
-    build_lookup();
+    LazyLock::force(&SETS);
 
     // launch threads
     for ix in 0..num_threads {
@@ -308,7 +385,7 @@ fn run_simulations(num_games: u64, num_threads: u64) {
         thread::spawn(move || {
             let mut counts = Counts::zero();
             for _ in 0..num {
-                simulate_game(&mut counts)
+                simulate_game(&mut counts, guarantee)
             }
             tx.send(counts).unwrap();
         });
@@ -321,11 +398,8 @@ fn run_simulations(num_games: u64, num_threads: u64) {
         totals.add(&counts);
     }
 
-    // summary
     println!("{:?} elapsed.\n", start_time.elapsed());
-    totals.print_hand_stats();
-    println!();
-    totals.print_end_game_stats();
+    totals
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -344,5 +418,18 @@ fn main() {
         "Simulating {} games. This may take some time...",
         pretty_print(games)
     );
-    run_simulations(games, cli.threads as u64);
+    let threads = cli.threads as u64;
+    let totals = run_simulations(games, threads, false);
+    totals.print_hand_stats();
+    println!();
+
+    if cli.guarantee {
+        println!("Simulating {} games that guarantee a set in 18 cards...", pretty_print(games));
+        let guaranteed = run_simulations(games, threads, true);
+        guaranteed.print_hand_stats();
+        println!();
+        totals.print_end_game_comparison(&guaranteed);
+    } else {
+        totals.print_end_game_stats();
+    }
 }
@@ -0,0 +1,29 @@
+// Minimal browser usage of the `wasm` feature. Build and bind with:
+//
+//   cargo rustc --lib --release --target wasm32-unknown-unknown \
+//       --no-default-features --features wasm --crate-type cdylib
+//   wasm-bindgen --target web --out-dir pkg \
+//       target/wasm32-unknown-unknown/release/core.wasm
+//
+// Then load this file as a module next to `pkg/`.
+
+import init, { Game } from "./pkg/core.js";
+
+await init();
+
+const game = Game.new_game(42);
+let state = JSON.parse(game.state_json());
+console.log(`${state.tableau.length} cards, ${state.sets_on_table} sets`);
+
+// ask for a hint and select that card (cards are indices 0-80)
+const card = game.hint();
+if (card !== undefined) {
+    console.log(game.select(state.tableau.indexOf(card))); // "selected"
+}
+
+if (state.sets_on_table === 0) {
+    game.deal();
+}
+
+state = JSON.parse(game.state_json());
+console.log(state.selected, state.score, state.remaining, state.over);
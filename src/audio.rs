@@ -0,0 +1,175 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Optional sound effects. Rather than linking GStreamer or canberra,
+//! the bundled WAVs are handed to whichever command line player the
+//! system has, so playback depends on one of `PLAYERS` being installed
+//! (see the README). Without a player, sounds are silently skipped.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::config::{Config, ConfigResult};
+use core::log_warn;
+
+/// Players tried in order, each of which takes the file to play as
+/// its only argument.
+const PLAYERS: [&str; 4] = ["paplay", "pw-play", "aplay", "afplay"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sound {
+    /// A rising chime for a valid set
+    Set,
+    /// A low buzz for a selection that isn't a set
+    Invalid,
+    /// Cards being shuffled out onto the table
+    Deal,
+}
+
+impl Sound {
+    const ALL: [Sound; 3] = [Sound::Set, Sound::Invalid, Sound::Deal];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Sound::Set => "set.wav",
+            Sound::Invalid => "invalid.wav",
+            Sound::Deal => "deal.wav",
+        }
+    }
+
+    fn data(self) -> &'static [u8] {
+        match self {
+            Sound::Set => include_bytes!("../resources/sounds/set.wav"),
+            Sound::Invalid => include_bytes!("../resources/sounds/invalid.wav"),
+            Sound::Deal => include_bytes!("../resources/sounds/deal.wav"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Player
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct Player {
+    command: PathBuf,
+    dir: PathBuf,
+}
+
+impl Player {
+    /// Finds a player and writes out the sounds for it. Returns None
+    /// (after logging why) if either step fails.
+    pub fn new() -> Option<Player> {
+        let command = match find_player(&env::var_os("PATH").unwrap_or_default()) {
+            Some(command) => command,
+            None => {
+                log_warn!("No sound player found (tried {}).", PLAYERS.join(", "));
+                return None;
+            }
+        };
+
+        match write_sounds() {
+            Ok(dir) => Some(Player { command, dir }),
+            Err(err) => {
+                log_warn!("Could not prepare sound effects: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Starts playing `sound` without waiting for it to finish.
+    pub fn play(&self, sound: Sound) {
+        let child = Command::new(&self.command)
+            .arg(self.dir.join(sound.file_name()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match child {
+            // reap the player once it's done
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(err) => log_warn!("Could not play a sound: {}", err),
+        }
+    }
+}
+
+/// The first known player found in `path` (a `PATH`-style list).
+fn find_player(path: &OsStr) -> Option<PathBuf> {
+    PLAYERS.iter().find_map(|name| {
+        env::split_paths(path)
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Writes any sounds that are missing from the sounds directory.
+fn write_sounds() -> ConfigResult<PathBuf> {
+    let dir = Config::config_dir()?.join("sounds");
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+
+    for &sound in Sound::ALL.iter() {
+        let path = dir.join(sound.file_name());
+        if !is_current(&path, sound) {
+            fs::write(&path, sound.data())?;
+        }
+    }
+    Ok(dir)
+}
+
+/// Whether the file at `path` looks like this build's copy of `sound`
+/// (the sizes match).
+fn is_current(path: &Path, sound: Sound) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.len() == sound.data().len() as u64)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_bundled_sounds() {
+        for &sound in Sound::ALL.iter() {
+            let data = sound.data();
+            assert_eq!(&data[..4], b"RIFF", "{:?}", sound);
+            assert_eq!(&data[8..12], b"WAVE", "{:?}", sound);
+        }
+    }
+
+    #[test]
+    fn check_find_player() {
+        // nothing to find on an empty path
+        assert_eq!(find_player(OsStr::new("")), None);
+
+        let dir = env::temp_dir().join(format!("marmoset-players-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("aplay"), b"").unwrap();
+        let found = find_player(dir.as_os_str());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found, Some(dir.join("aplay")));
+    }
+}
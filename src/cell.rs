@@ -20,11 +20,13 @@ use core::card::Card;
 use core::geometry::*;
 use rand::{thread_rng, Rng};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum Cell {
     Deck,
     Score,
     Placeholder,
+    /// An empty cell that shows the game clock
+    Timer,
     Card(RenderData),
 }
 
@@ -38,6 +40,15 @@ impl Cell {
         }
     }
 
+    /// The render data of a card cell, for updating it in place
+    pub fn card_data_mut(&mut self) -> Option<&mut RenderData> {
+        if let Cell::Card(ref mut data) = *self {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
     /// Convenience method for matching a hotkey to a `Card`
     pub fn card_for_key(&self, hotkey: char) -> Option<Card> {
         if let Cell::Card(data) = *self {
@@ -56,6 +67,7 @@ impl Cell {
 // RenderData
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Maximum rotation in degrees for a fully sloppy layout.
 const MAX_ROTATION: f64 = 3.0;
 
 /// Returns a random angle between `-max` and `max` degrees, rounded
@@ -68,11 +80,16 @@ fn random_angle(max: f64) -> f64 {
     degrees.to_radians()
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct RenderData {
     pub card: Card,
     pub hotkey: char,
+    /// Rotation for a fully sloppy layout. This is scaled by the
+    /// configured sloppiness when rendering.
     pub angle: f64,
+    /// How many deals the card has stayed on the tableau through
+    #[serde(default)]
+    pub deals_survived: u32,
 }
 
 impl RenderData {
@@ -82,16 +99,27 @@ impl RenderData {
             card,
             hotkey,
             angle,
+            deals_survived: 0,
         }
     }
 
-    pub fn point_in_rect(&self, x: f64, y: f64, rect: Rectangle, transform: bool) -> bool {
-        if transform {
+    /// Picks a new random angle for the card.
+    pub fn reroll_angle(&mut self) {
+        self.angle = random_angle(MAX_ROTATION);
+    }
+
+    /// Returns the rotation to render with for the given sloppiness.
+    pub fn rotation(&self, sloppiness: f64) -> f64 {
+        self.angle * sloppiness
+    }
+
+    pub fn point_in_rect(&self, x: f64, y: f64, rect: Rectangle, sloppiness: f64) -> bool {
+        if sloppiness > 0. {
             let (cx, cy) = rect.center();
             let mut transform = Matrix::identity();
 
             transform.translate(cx, cy);
-            transform.rotate(self.angle);
+            transform.rotate(self.rotation(sloppiness));
             transform.translate(-cx, -cy);
             transform.invert();
 
@@ -102,3 +130,33 @@ impl RenderData {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_card_data_mut() {
+        let card = Card::new(5);
+        let mut cells = vec![Cell::Deck, Cell::Card(RenderData::with_card_and_hotkey(card, 'q'))];
+
+        assert!(cells[0].card_data_mut().is_none());
+        for data in cells.iter_mut().filter_map(Cell::card_data_mut) {
+            data.angle = 0.25;
+            data.deals_survived = 2;
+        }
+
+        match cells[1] {
+            Cell::Card(data) => {
+                assert_eq!(data.card, card);
+                assert_eq!(data.angle, 0.25);
+                assert_eq!(data.deals_survived, 2);
+            }
+            _ => panic!("the card cell was replaced"),
+        }
+    }
+}
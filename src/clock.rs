@@ -0,0 +1,128 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Timekeeping for timed game modes.
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Countdown {
+    started: Instant,
+    limit: Duration,
+}
+
+impl Countdown {
+    /// Starts counting down from `limit`.
+    pub fn start(limit: Duration) -> Countdown {
+        Countdown {
+            started: Instant::now(),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed().min(self.limit)
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.limit.saturating_sub(self.started.elapsed())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+/// Measures elapsed time, leaving out any time spent paused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stopwatch {
+    /// Time accumulated before the last pause
+    banked: Duration,
+    /// When the stopwatch last started running (None while paused)
+    resumed: Option<Instant>,
+}
+
+impl Stopwatch {
+    /// Starts running from zero.
+    pub fn start() -> Stopwatch {
+        Stopwatch {
+            banked: Duration::ZERO,
+            resumed: Some(Instant::now()),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.banked + self.resumed.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.resumed.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(since) = self.resumed.take() {
+            self.banked += since.elapsed();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.resumed.is_none() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_countdown() {
+        let countdown = Countdown::start(Duration::from_secs(60));
+        assert!(!countdown.is_expired());
+        assert!(countdown.remaining() <= countdown.limit());
+        assert!(countdown.elapsed() <= countdown.limit());
+
+        let expired = Countdown::start(Duration::from_secs(0));
+        assert!(expired.is_expired());
+        assert_eq!(expired.remaining(), Duration::from_secs(0));
+        assert_eq!(expired.elapsed(), expired.limit());
+    }
+
+    #[test]
+    fn check_stopwatch() {
+        let mut stopwatch = Stopwatch::start();
+        assert!(stopwatch.is_running());
+
+        stopwatch.pause();
+        assert!(!stopwatch.is_running());
+        let paused = stopwatch.elapsed();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(stopwatch.elapsed(), paused);
+
+        // resuming picks up where it left off
+        stopwatch.resume();
+        assert!(stopwatch.is_running());
+        assert!(stopwatch.elapsed() >= paused);
+    }
+}
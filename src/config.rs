@@ -19,20 +19,49 @@ use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use std::{env, error, fmt, result};
 
-use core::graphics::ColorScheme;
-use crate::rules::{self, Rules};
+use cairo::Antialias;
+use core::graphics::{BadgeColors, ColorScheme};
+pub use core::deck::ReplenishPolicy;
+use core::{log_debug, log_error, log_warn};
+use crate::rules::{self, HintLevel, HintMode, Rules};
 
+/// UltraSet isn't a separate game: it's SuperSet dealt onto the full
+/// Set table. See `rules::UltraSet`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Variant { Set, SuperSet }
+pub enum Variant { Set, SuperSet, UltraSet }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Deck { Simplified, Full }
 
+/// Trades rendering smoothness for speed on slower machines. Fast
+/// rendering leaves visibly jagged edges on curves (squiggles, ovals,
+/// and rounded corners), while High can be noticeably slower to redraw
+/// large windows. Striped cards always use a translucent fill, which
+/// is already the cheapest way to draw them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderQuality { High, Balanced, Fast }
+
+impl RenderQuality {
+    pub fn antialias(self) -> Antialias {
+	match self {
+	    RenderQuality::High => Antialias::Best,
+	    RenderQuality::Balanced => Antialias::Default,
+	    RenderQuality::Fast => Antialias::Fast,
+	}
+    }
+}
+
+/// Feedback for the card beneath the mouse. Highlight rings the card
+/// instead of enlarging it, for those who find the motion distracting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoverStyle { Explode, Highlight, None }
+
 ////////////////////////////////////////////////////////////////////////////////
 // Config
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// Game Variant: Set vs SuperSet
     pub variant: Variant,
@@ -40,10 +69,112 @@ pub struct Config {
     pub deck: Deck,
     /// Layout neatly or sloppily
     pub tidy_layout: bool,
-    /// Classic vs CMYK
+    /// How sloppily cards are rotated: 0.0 (tidy) to 1.0 (sloppy)
+    pub sloppiness: f64,
+    /// Classic vs CMYK, or custom colors set by editing the config
+    /// file, e.g. `color_scheme: !Custom { a: [0, 0, 255], ... }`
     pub color_scheme: ColorScheme,
-    /// Store last used window size
-    pub window_size: (i32, i32)
+    /// Tableau color, with channels in [0,1]. The badges and empty
+    /// cells are shaded to stay legible against it.
+    pub background: (f64, f64, f64),
+    /// Label cards with their selection hotkeys
+    pub show_hotkeys: bool,
+    /// Store last used window size (derived from the variant if unset)
+    pub window_size: Option<(i32, i32)>,
+    /// Minimum drawing area size (derived from the variant if unset)
+    pub min_view_size: Option<(i32, i32)>,
+    /// Seconds before another hint is allowed (0 disables the cooldown)
+    pub hint_cooldown: u32,
+    /// Whether cosmetic rearrangements are placed on the undo stack
+    pub cosmetic_undoable: bool,
+    /// Save the game in progress on exit and offer to resume it
+    pub auto_save: bool,
+    /// Length of a timed challenge in seconds
+    pub challenge_seconds: u32,
+    /// Ask before closing the window during a record-setting challenge
+    pub confirm_close_on_pace: bool,
+    /// Most recent actions kept for undo and redo (None is unlimited)
+    pub max_undo_depth: Option<usize>,
+    /// Explain why a found set is valid before removing it
+    pub explain_sets: bool,
+    /// Tint cards that have sat through several deals
+    pub tint_stale_cards: bool,
+    /// Antialiasing used when drawing the tableau
+    pub render_quality: RenderQuality,
+    /// Reveal one more card of the same set with each hint
+    pub escalating_hints: bool,
+    /// Outline the tableau cells beneath the cards
+    pub show_grid: bool,
+    /// Play with only this many cards from the deck (None plays them all)
+    pub quick_game: Option<usize>,
+    /// Always hint the same set for the same board
+    pub hint_deterministic: bool,
+    /// Cards dealt when asking for more (a set's worth if unset)
+    pub deal_batch: Option<usize>,
+    /// Draw soft shadows beneath the cards (skipped when rendering fast)
+    pub card_shadows: bool,
+    /// Show game messages in a status line instead of dialogs
+    pub use_status_line: bool,
+    /// Extra clickable margin around each card, as a fraction of its width
+    pub hit_padding: f64,
+    /// When cards are dealt to replace a found set
+    pub replenish_policy: ReplenishPolicy,
+    /// Rate how much help a game needed in its summary
+    pub show_efficiency: bool,
+    /// Clear a partial selection when a click misses every card
+    pub click_empty_deselects: bool,
+    /// How the card beneath the mouse is marked
+    pub hover_style: HoverStyle,
+    /// Seconds without activity before a hint is shown (0 never nudges)
+    pub nudge_seconds: u32,
+    /// Faintly show the cards the next deal would add
+    pub preview_deals: bool,
+    /// Keep selected cards that are still in play after undo or redo
+    pub preserve_selection_on_undo: bool,
+    /// Deal more cards when the deck badge is clicked
+    pub deck_button: bool,
+    /// Show the time played in the bottom right corner while it's empty
+    pub show_timer: bool,
+    /// Leave games that used hints out of the best times
+    pub unhinted_records: bool,
+    /// Deal more cards on its own when taking a set leaves no sets
+    pub auto_deal: bool,
+    /// How many cards of a set a hint selects (unless hints are gradual)
+    pub hint_level: HintLevel,
+    /// Play sounds for sets, misses, and deals
+    pub sound_enabled: bool,
+    /// Show how many sets are on the table in the window title
+    pub show_set_count: bool,
+    /// Deal a set to a stuck table when the rules allow it, instead of
+    /// leaving it to chance. Without it, a Set game can end early on a
+    /// full table (takes effect with the next game)
+    pub guarantee_sets: bool,
+    /// Text beneath the badges, chosen by the mode being played
+    /// rather than read from the file (never saved)
+    #[serde(skip)]
+    pub badge_labels: BadgeLabels,
+    /// Whether changes are waiting to be written (never saved)
+    #[serde(skip)]
+    pub saving: Saving,
+}
+
+/// Tracks deferred saves. While deferred, setters only mark the
+/// config dirty and `flush` writes all of the changes at once.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Saving {
+    deferred: bool,
+    dirty: bool,
+    pub store: ConfigStore,
+}
+
+/// Where saves go. Controllers without a window keep their config in
+/// memory, so they never write over the player's settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigStore {
+    #[default]
+    File,
+    /// Drops each write, but counts it.
+    Memory { writes: usize },
 }
 
 impl Config {
@@ -52,19 +183,69 @@ impl Config {
 	    variant: Variant::Set,
 	    deck: Deck::Full,
 	    tidy_layout: false,
+	    sloppiness: 1.0,
 	    color_scheme: ColorScheme::CMYK,
-	    window_size: (1200, 700)
+	    background: BadgeColors::default().background,
+	    show_hotkeys: true,
+	    window_size: None,
+	    min_view_size: None,
+	    hint_cooldown: 0,
+	    cosmetic_undoable: false,
+	    auto_save: true,
+	    challenge_seconds: 60,
+	    confirm_close_on_pace: false,
+	    max_undo_depth: None,
+	    explain_sets: false,
+	    tint_stale_cards: false,
+	    render_quality: RenderQuality::Balanced,
+	    escalating_hints: false,
+	    show_grid: false,
+	    quick_game: None,
+	    hint_deterministic: false,
+	    deal_batch: None,
+	    card_shadows: false,
+	    use_status_line: false,
+	    hit_padding: 0.0,
+	    replenish_policy: ReplenishPolicy::IfBelowInitial,
+	    show_efficiency: false,
+	    click_empty_deselects: false,
+	    hover_style: HoverStyle::Explode,
+	    nudge_seconds: 0,
+	    preview_deals: false,
+	    preserve_selection_on_undo: false,
+	    deck_button: false,
+	    show_timer: true,
+	    unhinted_records: false,
+	    auto_deal: false,
+	    hint_level: HintLevel::Pair,
+	    sound_enabled: false,
+	    show_set_count: false,
+	    guarantee_sets: true,
+	    badge_labels: BadgeLabels::default(),
+	    saving: Saving::default(),
 	}
     }
 
+    /// Returns the sloppiness actually used for layout. A tidy layout
+    /// is always treated as a sloppiness of 0.
+    pub fn effective_sloppiness(&self) -> f64 {
+	if self.tidy_layout { 0.0 } else { self.sloppiness }
+    }
+
+    pub fn hint_mode(&self) -> HintMode {
+	if self.hint_deterministic { HintMode::Deterministic } else { HintMode::Random }
+    }
+
     pub fn rules(&self) -> Box<dyn Rules> {
 	match self.variant {
 	    Variant::Set => Box::new(rules::Set),
-	    Variant::SuperSet => Box::new(rules::SuperSet)
+	    Variant::SuperSet => Box::new(rules::SuperSet),
+	    Variant::UltraSet => Box::new(rules::UltraSet),
 	}
     }
 
-    pub fn config_path() -> ConfigResult<PathBuf> {
+    /// Directory holding the config and any other saved app data.
+    pub fn config_dir() -> ConfigResult<PathBuf> {
 	let home_dir = env::var("HOME")?;
 	let path = PathBuf::from(&home_dir).join(".config/marmoset/");
 
@@ -73,7 +254,11 @@ impl Config {
 	    fs::create_dir_all(&path)?;
 	}
 
-	Ok(path.join("marmoset.yml"))
+	Ok(path)
+    }
+
+    pub fn config_path() -> ConfigResult<PathBuf> {
+	Config::config_dir().map(|dir| dir.join("marmoset.yml"))
     }
 
     pub fn load() -> Config {
@@ -86,21 +271,31 @@ impl Config {
 		      .map_err(ConfigError::Io))
 	    .and_then(|_| serde_yaml::from_str(&serialized)
 		      .map_err(ConfigError::Yaml))
-	    .unwrap_or_default()
+	    .unwrap_or_else(|err| {
+		// a missing config just means this is the first run
+		match &err {
+		    ConfigError::Io(e) if e.kind() == io::ErrorKind::NotFound =>
+			log_debug!("Using default app settings: {}", err),
+		    _ => log_warn!("Could not load app settings, using defaults: {}", err),
+		}
+		Config::default()
+	    })
     }
 
-    pub fn save(&self) {
+    fn save(&mut self) {
 	let serialized = serde_yaml::to_string(&self).unwrap();
 
-	Config::config_path()
-	    .and_then(|path| File::create(&path)
-		      .map_err(ConfigError::Io))
-	    .and_then(|mut file| file.write_all(serialized.as_bytes())
-		      .map_err(ConfigError::Io))
-	    .unwrap_or_else(|err| {
-		println!("Could not save app settings.");
-		println!("{}", err);
-	    });
+	match &mut self.saving.store {
+	    ConfigStore::File => Config::config_path()
+		.and_then(|path| File::create(&path)
+			  .map_err(ConfigError::Io))
+		.and_then(|mut file| file.write_all(serialized.as_bytes())
+			  .map_err(ConfigError::Io))
+		.unwrap_or_else(|err| {
+		    log_error!("Could not save app settings: {}", err);
+		}),
+	    ConfigStore::Memory { writes } => *writes += 1,
+	}
     }
 }
 
@@ -108,11 +303,39 @@ impl Config {
 // Config: Setters
 ////////////////////////////////////////////////////////////////////////////////
 
-/// Create setter methods that automatically save the config.
+/// Create setter methods that automatically save the config (or mark
+/// it dirty while saves are deferred).
 macro_rules! make_setter {
     ($name:ident, $field:ident: $t:ty) => {
 	pub fn $name(&mut self, $field: $t) {
 	    self.$field = $field;
+	    self.changed();
+	}
+    }
+}
+
+impl Config {
+    /// Defers saves until the next `flush`, so that a burst of changes
+    /// costs a single write. Turning deferral off flushes right away.
+    pub fn set_deferred(&mut self, deferred: bool) {
+	self.saving.deferred = deferred;
+	if !deferred {
+	    self.flush();
+	}
+    }
+
+    /// Writes any deferred changes.
+    pub fn flush(&mut self) {
+	if self.saving.dirty {
+	    self.saving.dirty = false;
+	    self.save();
+	}
+    }
+
+    fn changed(&mut self) {
+	if self.saving.deferred {
+	    self.saving.dirty = true;
+	} else {
 	    self.save();
 	}
     }
@@ -121,9 +344,132 @@ macro_rules! make_setter {
 impl Config {
     make_setter!(set_variant, variant: Variant);
     make_setter!(set_deck, deck: Deck);
-    make_setter!(set_tidy_layout, tidy_layout: bool);
     make_setter!(set_color_scheme, color_scheme: ColorScheme);
-    make_setter!(set_window_size, window_size: (i32, i32));
+    make_setter!(set_show_hotkeys, show_hotkeys: bool);
+    make_setter!(set_window_size, window_size: Option<(i32, i32)>);
+    make_setter!(set_cosmetic_undoable, cosmetic_undoable: bool);
+    make_setter!(set_auto_save, auto_save: bool);
+    make_setter!(set_confirm_close_on_pace, confirm_close_on_pace: bool);
+    make_setter!(set_max_undo_depth, max_undo_depth: Option<usize>);
+    make_setter!(set_explain_sets, explain_sets: bool);
+    make_setter!(set_tint_stale_cards, tint_stale_cards: bool);
+    make_setter!(set_render_quality, render_quality: RenderQuality);
+    make_setter!(set_escalating_hints, escalating_hints: bool);
+    make_setter!(set_show_grid, show_grid: bool);
+    make_setter!(set_quick_game, quick_game: Option<usize>);
+    make_setter!(set_hint_deterministic, hint_deterministic: bool);
+    make_setter!(set_deal_batch, deal_batch: Option<usize>);
+    make_setter!(set_card_shadows, card_shadows: bool);
+    make_setter!(set_use_status_line, use_status_line: bool);
+    make_setter!(set_hit_padding, hit_padding: f64);
+    make_setter!(set_replenish_policy, replenish_policy: ReplenishPolicy);
+    make_setter!(set_show_efficiency, show_efficiency: bool);
+    make_setter!(set_click_empty_deselects, click_empty_deselects: bool);
+    make_setter!(set_hover_style, hover_style: HoverStyle);
+    make_setter!(set_nudge_seconds, nudge_seconds: u32);
+    make_setter!(set_preview_deals, preview_deals: bool);
+    make_setter!(set_preserve_selection_on_undo, preserve_selection_on_undo: bool);
+    make_setter!(set_deck_button, deck_button: bool);
+    make_setter!(set_show_timer, show_timer: bool);
+    make_setter!(set_unhinted_records, unhinted_records: bool);
+    make_setter!(set_auto_deal, auto_deal: bool);
+    make_setter!(set_hint_level, hint_level: HintLevel);
+    make_setter!(set_sound_enabled, sound_enabled: bool);
+    make_setter!(set_show_set_count, show_set_count: bool);
+    make_setter!(set_guarantee_sets, guarantee_sets: bool);
+    make_setter!(set_badge_labels, badge_labels: BadgeLabels);
+
+    /// Keeps `tidy_layout` in sync so that older configs remain meaningful.
+    pub fn set_sloppiness(&mut self, sloppiness: f64) {
+	self.sloppiness = sloppiness;
+	self.tidy_layout = sloppiness == 0.0;
+	self.changed();
+    }
+
+    /// Sets both with a single save, since saved games and lessons
+    /// always bring their own variant and deck.
+    pub fn set_variant_and_deck(&mut self, variant: Variant, deck: Deck) {
+	self.variant = variant;
+	self.deck = deck;
+	self.changed();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Config: Themes
+////////////////////////////////////////////////////////////////////////////////
+
+/// The settings that together make up the look of the tableau.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub color_scheme: ColorScheme,
+    pub background: (f64, f64, f64),
+    pub sloppiness: f64,
+    pub card_shadows: bool,
+    pub hover_style: HoverStyle,
+}
+
+impl Config {
+    pub fn theme(&self) -> Theme {
+	Theme {
+	    color_scheme: self.color_scheme,
+	    background: self.background,
+	    sloppiness: self.effective_sloppiness(),
+	    card_shadows: self.card_shadows,
+	    hover_style: self.hover_style,
+	}
+    }
+
+    /// Sets every field of `theme` with a single save (or none while
+    /// saves are deferred), unlike calling the individual setters.
+    pub fn apply_theme(&mut self, theme: Theme) {
+	self.color_scheme = theme.color_scheme;
+	self.background = theme.background;
+	self.sloppiness = theme.sloppiness;
+	self.tidy_layout = theme.sloppiness == 0.0;
+	self.card_shadows = theme.card_shadows;
+	self.hover_style = theme.hover_style;
+	self.changed();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Config: Badge Labels
+////////////////////////////////////////////////////////////////////////////////
+
+/// Text shown beneath the deck and score badges, kept in one place so
+/// that other modes and languages can replace it as a whole.
+#[derive(Clone, Copy, Debug)]
+pub struct BadgeLabels {
+    pub found: &'static str,
+    pub card_left: &'static str,
+    pub cards_left: &'static str,
+    pub second_left: &'static str,
+    pub seconds_left: &'static str,
+    pub elapsed: &'static str,
+}
+
+impl BadgeLabels {
+    pub fn cards_left(&self, count: usize) -> &'static str {
+	if count == 1 { self.card_left } else { self.cards_left }
+    }
+
+    pub fn seconds_left(&self, count: usize) -> &'static str {
+	if count == 1 { self.second_left } else { self.seconds_left }
+    }
+}
+
+impl Default for BadgeLabels {
+    fn default() -> Self {
+	BadgeLabels {
+	    found: "found",
+	    card_left: "card left",
+	    cards_left: "cards left",
+	    second_left: "second left",
+	    seconds_left: "seconds left",
+	    elapsed: "elapsed",
+	}
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -13,33 +13,97 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use cairo::{Context, Rectangle};
+use cairo::{Context, Format, ImageSurface, IoError, Rectangle};
 use gdk::{self, EventMask};
 use gtk::prelude::*;
 use gtk::{Allocation, DrawingArea};
 use num_traits::ToPrimitive;
 use std::cell::RefCell;
-use std::rc::Rc;
-use std::{f64, i32};
+use std::env;
+use std::fs::File;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{error, f64, fmt, i32};
 
 use crate::cell::Cell;
-use crate::config::{self, Config};
-use crate::game_state::{GameState, COLUMNS, ROWS};
-use crate::rules::Rules;
+use crate::clock::{Countdown, Stopwatch};
+use crate::config::{self, Config, ConfigResult, HoverStyle, RenderQuality, ReplenishPolicy, Theme};
+use crate::game_state::GameState;
+use crate::rules::{HintLevel, Rules};
+use crate::scores::{BestTime, ScoreStore, Scores};
+use crate::session::{self, Session, SlotError};
+use crate::share::{ShareCode, ShareError};
 use core::card::Card;
 use core::geometry::{zero_rect, RectangleExt};
-use core::graphics::{ColorScheme, ContextExt};
+use core::log_error;
+use core::graphics::{ensure_contrast, BadgeColors, ColorScheme, ContextExt, INVALID_SELECTION_COLOR, SELECTION_COLOR};
+use core::solve::{solve, Dealing};
+use core::utils::clamp_float;
 
 const CARD_WIDTH: f64 = 3.5;
 const CARD_HEIGHT: f64 = 2.25;
+/// spacing between cards as a fraction of the card width
+const SPACING_PERCENTAGE: f64 = 0.15;
+/// narrowest card (in pixels) that stays legible on a full Set tableau
+const MIN_CARD_WIDTH: f64 = 130.;
+/// card width (in pixels) used to size the window on first run
+const DEFAULT_CARD_WIDTH: f64 = 200.;
+/// number of card cells on a full Set tableau
+const FULL_TABLEAU: f64 = 18.;
 /// for debugging dirty rects
 const VISUALIZE_REDRAWS: bool = false;
+/// set to start with the debug overlay showing (Ctrl+Shift+D toggles it)
+const DEBUG_OVERLAY_VAR: &str = "MARMOSET_DEBUG_OVERLAY";
 /// scaling factor used when hovering over a card
 const EXPLODE: f64 = 1.04;
+/// how long newly dealt cards stay highlighted
+const DEAL_HIGHLIGHT: Duration = Duration::from_secs(1);
+/// starting opacity of the deal highlight
+const DEAL_HIGHLIGHT_ALPHA: f64 = 0.6;
+/// how long a selection that isn't a set stays outlined in red
+const INVALID_FLASH: Duration = Duration::from_millis(300);
+/// delay between frames of the deal highlight animation
+const ANIMATION_FRAME: Duration = Duration::from_millis(40);
+/// deals a card must sit through before it's tinted (if enabled)
+const STALE_DEALS: u32 = 3;
+/// opacity of the tint on long-lived cards
+const STALE_TINT: f64 = 0.08;
+/// opacity of the cards previewed for the next deal
+const PREVIEW_ALPHA: f64 = 0.25;
+/// how often deferred settings changes are written
+const CONFIG_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
-/// Callback for undo status changes
+/// Callback for status changes
 type Notification = Box<dyn Fn(&Controller) -> ()>;
 
+/// Callback for game events
+type EventObserver = Box<dyn Fn(&Controller, GameEvent) -> ()>;
+
+/// Game state transitions, sent to event observers in the order they
+/// happen. Observers can query the controller for the details.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A valid set was taken from the board
+    SetFound,
+    /// A full selection wasn't a valid set
+    InvalidAttempt,
+    /// More cards were dealt on request
+    Dealt,
+    /// An action was undone
+    Undone,
+    /// An undone action was redone
+    Redone,
+    /// The last action ended the game
+    GameOver,
+    /// The score went up or down
+    ScoreChanged,
+    /// The undo or redo stack changed
+    UndoChanged,
+    /// The cards on the table changed (the set count is already updated)
+    BoardChanged,
+}
+
 pub struct Controller {
     /// Settings
     pub config: Config,
@@ -50,40 +114,134 @@ pub struct Controller {
     /// Undo Stacks
     undo_stack: Vec<UndoItem>,
     redo_stack: Vec<UndoItem>,
-    undo_observers: Vec<Notification>,
+    /// Hints
+    last_hint: Option<Instant>,
+    /// Last input or board change (for nudging idle players)
+    last_activity: Instant,
+    hint_escalation: Option<HintEscalation>,
+    hint_observers: Vec<Notification>,
+    /// Notified of every game event
+    event_observers: Vec<EventObserver>,
+    /// Timed challenge (if one is being played)
+    challenge: Option<Challenge>,
+    /// Cells filled by the last deal, and when it happened
+    deal_highlight: Option<(Instant, Vec<usize>)>,
+    /// When a selection that isn't a set was rejected, and its cards
+    invalid_flash: Option<(Instant, Vec<Card>)>,
+    /// A found set being explained before it's removed
+    explanation: Option<Explanation>,
+    /// Remaining steps of a solution being played back, last step first
+    solution: Option<(Instant, Vec<[Card; 3]>)>,
+    /// How quickly sets are being found
+    reactions: Reactions,
+    /// Time spent playing the current game
+    game_clock: Stopwatch,
+    /// Help taken this game
+    assists: Assists,
+    /// Whether finishing can set a best time, which a resumed game or
+    /// a shown solution rules out
+    records_time: bool,
+    /// Tallies for the current game
+    stats: GameStats,
+    /// Best times and challenge records
+    scores: ScoreStore,
+    /// Sets on the table, recounted only when the cards change
+    available_sets: usize,
+    /// Notified when the controller changes settings on its own
+    settings_observers: Vec<Notification>,
+    /// Latest game message (when using the status line)
+    status: Option<String>,
+    status_observers: Vec<Notification>,
+    /// Notified with messages meant for the player
+    message_observers: Vec<Box<dyn Fn(&str)>>,
     /// Layout
     tableau_bounds: Rectangle,
     cell_rects: Vec<Rectangle>,
-    /// Widget
-    view: DrawingArea,
+    view_size: (i32, i32),
+    /// Device pixels per layout unit (2 on most HiDPI screens)
+    scale: f64,
+    /// Widget (absent when running headless)
+    view: Option<DrawingArea>,
+    weak_self: Weak<RefCell<Controller>>,
+    /// Outline the cells and tableau bounds (for diagnosing layout)
+    debug_overlay: bool,
     /// Event Bookkeeping
     clicked_card: Option<Card>,
     // is the mouse inside the click card?
     inside_clicked_card: bool,
     exploded_cell: Option<usize>,
+    // is the mouse over the deck badge?
+    hovering_deck: bool,
+    // most recent click or hotkey toggle
+    last_toggle: Option<(Card, Input, Instant)>,
+    // has a key toggled a card since the last key release?
+    key_toggled: bool,
+    /// Cell moved by the arrow keys (None until they're first used)
+    focused_cell: Option<usize>,
 }
 
 impl Controller {
-    pub fn shared_with_config(config: Config) -> Rc<RefCell<Controller>> {
-        let drawing_area = Controller::new_drawing_area();
-        let controller = Controller {
+    fn with_config(config: Config, view: Option<DrawingArea>) -> Controller {
+        let state = GameState::with_config(config);
+        let cells = state.tableau.len();
+        let available_sets = config.rules().count_sets(&state.cards());
+
+        Controller {
             config,
-            state: GameState::with_config(config),
+            state,
             rules: config.rules(),
             selected: vec![],
             undo_stack: vec![],
             redo_stack: vec![],
-            undo_observers: vec![],
+            last_hint: None,
+            last_activity: Instant::now(),
+            hint_escalation: None,
+            hint_observers: vec![],
+            event_observers: vec![],
+            challenge: None,
+            deal_highlight: None,
+            invalid_flash: None,
+            explanation: None,
+            solution: None,
+            reactions: Reactions::default(),
+            game_clock: Stopwatch::start(),
+            assists: Assists::default(),
+            records_time: true,
+            stats: GameStats::default(),
+            scores: ScoreStore::File,
+            available_sets,
+            settings_observers: vec![],
+            status: None,
+            status_observers: vec![],
+            message_observers: vec![],
             tableau_bounds: zero_rect(),
-            cell_rects: vec![zero_rect(); ROWS * COLUMNS],
-            view: drawing_area.clone(),
+            cell_rects: vec![zero_rect(); cells],
+            view_size: (0, 0),
+            scale: 1.,
+            view,
+            weak_self: Weak::new(),
+            debug_overlay: env::var_os(DEBUG_OVERLAY_VAR).is_some(),
             clicked_card: None,
             inside_clicked_card: false,
             exploded_cell: None,
-        };
+            focused_cell: None,
+            hovering_deck: false,
+            last_toggle: None,
+            key_toggled: false,
+        }
+    }
+
+    pub fn shared_with_config(config: Config) -> Rc<RefCell<Controller>> {
+        let drawing_area = Controller::new_drawing_area(&config);
+        let controller = Controller::with_config(config, Some(drawing_area.clone()));
 
         // need a shared reference that can be moved into event callbacks
         let shared_controller = Rc::new(RefCell::new(controller));
+        shared_controller.borrow_mut().weak_self = Rc::downgrade(&shared_controller);
+
+        // settings changes are batched; closing the window flushes them
+        shared_controller.borrow_mut().config.set_deferred(true);
+        shared_controller.borrow_mut().flush_config();
 
         macro_rules! connect {
             ($connect:ident :> $action:ident) => {{
@@ -100,10 +258,22 @@ impl Controller {
         connect!(connect_key_release_event :> key_release);
         connect!(connect_motion_notify_event :> motion_notify);
 
+        let controller = shared_controller.clone();
+        drawing_area.connect_scale_factor_notify(move |widget| controller.borrow_mut().scale_changed(widget));
+
         shared_controller
     }
 
-    fn new_drawing_area() -> DrawingArea {
+    /// A controller without a widget, for exercising game logic in tests.
+    #[cfg(test)]
+    fn headless(mut config: Config) -> Controller {
+        config.saving.store = config::ConfigStore::Memory { writes: 0 };
+        let mut controller = Controller::with_config(config, None);
+        controller.scores = ScoreStore::Memory(RefCell::new(Scores::default()));
+        controller
+    }
+
+    fn new_drawing_area(config: &Config) -> DrawingArea {
         let drawing_area = DrawingArea::new();
         let event_mask = EventMask::POINTER_MOTION_MASK
             | EventMask::BUTTON_PRESS_MASK
@@ -115,12 +285,44 @@ impl Controller {
         drawing_area.add_events(event_mask);
 
         // establish a reasonable minimum view size
-        drawing_area.set_size_request(800, 450);
+        let (width, height) = minimum_view_size(config);
+        drawing_area.set_size_request(width, height);
         drawing_area
     }
 
+    fn update_minimum_view_size(&self) {
+        if let Some(view) = &self.view {
+            let (width, height) = minimum_view_size(&self.config);
+            view.set_size_request(width, height);
+        }
+    }
+
     pub fn get_drawing_area(&self) -> DrawingArea {
-        self.view.clone()
+        self.view.clone().expect("controller has no drawing area")
+    }
+
+    /// Writes deferred settings changes, then checks again later.
+    fn flush_config(&mut self) {
+        self.config.flush();
+        self.schedule(CONFIG_FLUSH_INTERVAL, Controller::flush_config);
+    }
+
+    /// Runs `f` on the controller after `delay`. Since there's no
+    /// main loop when running headless, nothing is scheduled then.
+    fn schedule<F>(&self, delay: Duration, f: F) -> Option<glib::SourceId>
+    where
+        F: FnOnce(&mut Controller) + 'static,
+    {
+        self.view.as_ref()?;
+        let weak_self = self.weak_self.clone();
+
+        let source = glib::timeout_add_local_once(delay, move || {
+            if let Some(controller) = weak_self.upgrade() {
+                f(&mut controller.borrow_mut());
+            }
+        });
+
+        Some(source)
     }
 }
 
@@ -128,20 +330,177 @@ impl Controller {
 // Actions
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Where the current game is in its lifecycle. The phase is derived
+/// from the game state, so undoing out of a finished game resumes play.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Playing,
+    /// The deck is exhausted and nothing on the tableau can be taken.
+    Finished,
+}
+
 impl Controller {
+    pub fn phase(&self) -> Phase {
+        if let Some(challenge) = &self.challenge {
+            // challenges only end when time runs out
+            if challenge.countdown.is_expired() {
+                Phase::Finished
+            } else {
+                Phase::Playing
+            }
+        } else if self.rules.stuck(&self.state.cards()) && !self.can_deal() {
+            Phase::Finished
+        } else {
+            Phase::Playing
+        }
+    }
+
+    /// Whether there are cards left and room for them. Without the set
+    /// guarantee, a full table can be stuck with cards still in the deck.
+    fn can_deal(&self) -> bool {
+        !self.state.deck.is_empty() && self.state.open_cells() >= self.rules.set_size()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase() == Phase::Finished
+    }
+
+    /// Describes the outcome of a finished game.
+    pub fn result_summary(&self) -> String {
+        let name = self.rules.name();
+        let score = self.state.score;
+        let found = if score == 1 {
+            format!("1 {}", name)
+        } else {
+            format!("{} {}s", score, name)
+        };
+
+        let summary = if let Some(challenge) = &self.challenge {
+            let seconds = challenge.countdown.limit().as_secs();
+            let record = match challenge.previous_best {
+                Some(best) if best >= score => format!("Your best is {}.", best),
+                _ => "That's a new best!".to_string(),
+            };
+            format!("You found {} in {} seconds. {}", found, seconds, record)
+        } else {
+            let leftover = self.state.card_count();
+            format!("You found {} with {} cards left over.", found, leftover)
+        };
+
+        let summary = match self.average_reaction() {
+            Some(average) => format!(
+                "{} On average, each {} took {:.1} seconds to find.",
+                summary,
+                name,
+                average.as_secs_f64()
+            ),
+            None => summary,
+        };
+
+        match self.efficiency_summary() {
+            Some(rating) if self.config.show_efficiency => format!("{} {}", summary, rating),
+            _ => summary,
+        }
+    }
+
+    /// Notifies observers if the last action ended the game.
+    fn check_for_finish(&self) {
+        if self.is_finished() {
+            self.record_best_time();
+            self.emit(GameEvent::GameOver);
+        }
+    }
+
+    /// Saves the time taken by a finished game if it's the fastest yet.
+    /// Only games played through the whole deck from the start count.
+    fn record_best_time(&self) {
+        // challenges keep their own records
+        if self.challenge.is_some() || (self.config.unhinted_records && self.assists.hints > 0) {
+            return;
+        }
+
+        // a stuck table with cards still in the deck wasn't cleared
+        if !self.records_time || self.state.is_quick() || !self.state.deck.is_empty() {
+            return;
+        }
+
+        let finished = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        let time = BestTime {
+            variant: self.config.variant,
+            deck: self.config.deck,
+            seconds: self.game_time().as_secs(),
+            finished,
+        };
+
+        let mut scores = self.scores.load();
+        if scores.record_time(time) {
+            self.scores.save(&scores);
+        }
+    }
+
+    pub fn set_unhinted_records(&mut self, unhinted: bool) {
+        self.config.set_unhinted_records(unhinted);
+    }
+
+    pub fn add_finish_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller) -> () + 'static,
+    {
+        self.add_event_observer(move |controller, event| {
+            if event == GameEvent::GameOver {
+                f(controller)
+            }
+        });
+    }
+
+    fn emit(&self, event: GameEvent) {
+        for f in &self.event_observers {
+            f(self, event)
+        }
+    }
+
+    /// Observers are notified after each state transition. A single
+    /// action may send several events (e.g. finding the last set sends
+    /// `UndoChanged`, `SetFound`, `ScoreChanged`, then `GameOver`).
+    pub fn add_event_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller, GameEvent) -> () + 'static,
+    {
+        self.event_observers.push(Box::new(f));
+    }
+
     fn new_game_with_state(&mut self, start_state: Option<GameState>) {
         if let Some(state) = start_state {
             self.state = state;
+            self.fit_grid();
         }
 
+        self.challenge = None;
+        self.deal_highlight = None;
+        self.invalid_flash = None;
+        self.explanation = None;
+        self.solution = None;
+        self.hint_escalation = None;
         self.selected.clear();
         self.reset_undo_stacks();
+        self.reset_hint_cooldown();
+        self.restart_game_clock();
+        self.board_changed();
+        self.assists = Assists::default();
+        self.records_time = true;
+        self.stats = GameStats::default();
+        self.set_status(None);
         self.redraw();
     }
 
     pub fn restart(&mut self) {
-        // state will be None if the undo stack is empty
-        let state = self.undo_stack.first().map(|item| item.state.clone());
+        // state will be None if no game action has been taken
+        let state = self.undo_stack.iter().find_map(|item| match item.snapshot {
+            Snapshot::Game(ref state) => Some(state.clone()),
+            Snapshot::Layout(_) => None,
+        });
         self.new_game_with_state(state);
     }
 
@@ -150,31 +509,58 @@ impl Controller {
         self.new_game_with_state(Some(state));
     }
 
-    pub fn show_hint(&mut self) -> Option<String> {
+    pub fn show_hint(&mut self) {
+        self.finish_explanation();
+
+        if !self.can_hint() {
+            return;
+        }
+
         self.deselect_all();
 
-        if let Some(hint_cards) = self.rules.hint(&self.state.cards()) {
+        let cards = self.state.cards();
+        let hint = if self.config.escalating_hints {
+            self.escalate_hint(&cards)
+        } else {
+            self.rules.hint_with_level(&cards, self.config.hint_mode(), self.config.hint_level)
+        };
+
+        if let Some(hint_cards) = hint {
+            // an escalating hint only counts once, for its first card
+            let escalated = matches!(self.hint_escalation, Some(HintEscalation { revealed, .. }) if revealed > 1);
+            if !(self.config.escalating_hints && escalated) {
+                self.assists.hints += 1;
+                self.stats.hints += 1;
+            }
             self.selected = hint_cards;
+            self.start_hint_cooldown();
+            self.restart_idle_clock();
             self.redraw();
-            None
-        } else if self.state.deck.is_empty() {
-            Some("No more moves!".to_string())
         } else {
-            self.deal_more_cards()
+            self.deal_more_cards();
         }
     }
 
-    pub fn deal_more_cards(&mut self) -> Option<String> {
-        if self.rules.stuck(&self.state.cards()) {
-            if self.state.deck.is_empty() {
-                return Some("No more moves!".to_string());
-            } else {
-                self.register_undo("Deal More Cards");
-                self.state.deal(self.rules.set_size());
-                self.redraw();
-            }
+    /// Deals another batch if the board is stuck, or tops it back up
+    /// to the initial deal. Otherwise announces how many sets there are.
+    pub fn deal_more_cards(&mut self) {
+        self.finish_explanation();
 
-            None
+        if self.is_finished() {
+            self.announce(self.result_summary());
+            return;
+        }
+
+        // a board that wasn't replenished can be topped back up
+        let cards = self.state.cards();
+        let stuck = self.rules.stuck(&cards);
+        let shortfall = self.rules.initial_deal_size().saturating_sub(cards.len());
+        if stuck || (shortfall > 0 && self.can_deal()) {
+            let n = if stuck { self.deal_batch() } else { shortfall };
+            self.register_undo("Deal More Cards");
+            self.stats.deals += 1;
+            self.deal_batch_into_board(n);
+            self.check_for_finish();
         } else {
             let num_in_play = self.rules.count_sets(&self.state.cards());
             let string = if num_in_play == 1 {
@@ -187,421 +573,3896 @@ impl Controller {
                 )
             };
 
-            Some(string)
+            self.announce(string);
+        }
+    }
+
+    /// Cards dealt when asking for more: the configured batch rounded
+    /// down to whole sets, but no more than the empty cells can hold.
+    fn deal_batch(&self) -> usize {
+        let set_size = self.rules.set_size();
+        let batch = self.config.deal_batch.unwrap_or(set_size);
+        let batch = (batch / set_size).max(1) * set_size;
+        let room = self.state.open_cells() / set_size * set_size;
+        batch.min(room.max(set_size))
+    }
+
+    pub fn set_deal_batch(&mut self, batch: Option<usize>) {
+        self.config.set_deal_batch(batch);
+        self.redraw();
+    }
+
+    /// The cards the next deal would add. This is the naive draw, so
+    /// it differs from the actual deal when the set guarantee has to
+    /// doctor the deck.
+    pub fn peek_next_deal(&self) -> Vec<Card> {
+        self.next_deal_cells().into_iter().map(|(_, card)| card).collect()
+    }
+
+    /// The next deal's cards, along with the cells they'd fill.
+    fn next_deal_cells(&self) -> Vec<(usize, Card)> {
+        let n = self.deal_batch().min(self.state.open_cells());
+        self.state.peek_deal(n)
+    }
+
+    pub fn set_preview_deals(&mut self, preview: bool) {
+        self.config.set_preview_deals(preview);
+        self.redraw();
+    }
+
+    pub fn set_replenish_policy(&mut self, policy: ReplenishPolicy) {
+        self.config.set_replenish_policy(policy);
+    }
+
+    /// Deals more cards even if the board isn't stuck. Announces why
+    /// not if there's no room or nothing left to deal.
+    pub fn force_deal(&mut self) {
+        self.finish_explanation();
+
+        if self.is_finished() {
+            return self.announce(self.result_summary());
+        } else if self.state.deck.is_empty() {
+            return self.announce("There are no cards left to deal.".to_string());
+        } else if self.state.open_cells() == 0 {
+            return self.announce("There's no room for more cards.".to_string());
+        }
+
+        // a board that isn't stuck has a set, so the guarantee in
+        // `deal` never needs to doctor the deck here
+        let n = self.deal_batch().min(self.state.open_cells());
+        if !self.rules.stuck(&self.state.cards()) {
+            self.assists.early_deals += 1;
+        }
+        self.register_undo("Deal More Cards");
+        self.stats.deals += 1;
+        self.deal_batch_into_board(n);
+        self.check_for_finish();
+    }
+
+    /// Deals `n` cards and shows them. Callers decide whether the deal
+    /// is the player's, with its own undo entry and place in the stats.
+    fn deal_batch_into_board(&mut self, n: usize) {
+        let dealt = self.state.deal(n);
+        self.highlight_dealt_cells(dealt);
+        self.board_changed();
+        self.set_status(None);
+        self.prune_selection();
+        self.redraw();
+        self.emit(GameEvent::Dealt);
+    }
+
+    /// Rearranges the cards in play for a fresh look, without dealing
+    /// or touching the deck. This is always undoable as "Shuffle".
+    pub fn shuffle_tableau(&mut self) {
+        self.finish_explanation();
+        if self.is_finished() || self.solution.is_some() {
+            return;
         }
+
+        self.push_undo(Snapshot::Layout(self.state.tableau.clone()), "Shuffle");
+        self.clear_deal_highlight();
+        self.state.shuffle_tableau();
+        self.restart_idle_clock();
+        self.redraw();
+    }
+
+    /// Whether selecting exactly `cards` would make a valid set under
+    /// the current rules. Nothing on the board changes.
+    pub fn would_be_valid(&self, cards: &[Card]) -> bool {
+        self.rules.is_valid_selection(cards)
     }
 
     fn check_for_set(&mut self) {
+        // the found set is already on its way out
+        if self.explanation.is_some() {
+            return;
+        }
+
+        // never evaluate cards that have left the table
+        self.prune_selection();
+
         if self.selected.len() == self.rules.set_size() {
             // if we found a valid set, remove it, otherwise deselect the last selected card
-            if self.rules.valid_set(&self.selected) {
+            if self.would_be_valid(&self.selected) {
                 let action_name = self.rules.name();
                 self.register_undo(action_name);
+                self.record_reaction();
 
-                self.state.take_cards(&self.selected, &*self.rules);
-                self.deselect_all();
+                if self.config.explain_sets {
+                    self.explain_selection();
+                } else {
+                    self.take_selection();
+                }
             } else if let Some(card) = self.selected.pop() {
-                self.redraw_cell(self.state.index_of_card(card));
+                self.stats.invalid_selections += 1;
+                self.flash_invalid_selection(card);
+                self.emit(GameEvent::InvalidAttempt);
             }
         }
     }
+
+    fn take_selection(&mut self) {
+        self.top_up_challenge_deck();
+        self.state.take_cards(&self.selected, &*self.rules, self.config.replenish_policy);
+        self.keep_challenge_playable();
+        self.board_changed();
+        self.set_status(None);
+        self.deselect_all();
+        self.prune_selection();
+        self.reset_hint_cooldown();
+        self.stats.sets_found += 1;
+        self.emit(GameEvent::SetFound);
+        self.emit(GameEvent::ScoreChanged);
+        if self.config.auto_deal {
+            self.auto_deal();
+        }
+        self.check_for_finish();
+    }
+
+    /// Deals until there's a set on the board, for as long as there are
+    /// cards to deal and room for them. The deals aren't the player's:
+    /// undoing the take that made them undoes them too, and they don't
+    /// count in the stats.
+    fn auto_deal(&mut self) {
+        while self.rules.stuck(&self.state.cards()) && self.can_deal() {
+            let n = self.deal_batch();
+            self.deal_batch_into_board(n);
+        }
+    }
+
+    pub fn set_auto_deal(&mut self, auto_deal: bool) {
+        self.config.set_auto_deal(auto_deal);
+    }
+
+    pub fn set_sound_enabled(&mut self, enabled: bool) {
+        self.config.set_sound_enabled(enabled);
+    }
+
+    pub fn set_guarantee_sets(&mut self, guarantee: bool) {
+        self.config.set_guarantee_sets(guarantee);
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Config
+// Reaction Time
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Times how long each set took to find, measured from when the board
+/// last changed. Time spent on a stuck board doesn't count.
+#[derive(Default)]
+struct Reactions {
+    /// When the current board appeared (None while it's stuck)
+    solvable_since: Option<Instant>,
+    /// Time taken to find each set, indexed by score. Sets that were
+    /// redone after an undo have no time.
+    samples: Vec<Option<Duration>>,
+}
+
 impl Controller {
-    pub fn set_deck(&mut self, deck: config::Deck) {
-        self.config.set_deck(deck);
-        self.new_game();
+    /// Restarts everything that times the current board.
+    fn board_changed(&mut self) {
+        self.restart_reaction_clock();
+        self.restart_idle_clock();
+        self.sync_game_clock();
+        self.recount_available_sets();
+        self.emit(GameEvent::BoardChanged);
     }
 
-    pub fn set_variant(&mut self, variant: config::Variant) {
-        self.config.set_variant(variant);
-        self.rules = self.config.rules();
-        self.new_game();
+    /// Starts timing the current board. Times for sets that are no
+    /// longer part of the score (i.e. undone) are dropped.
+    fn restart_reaction_clock(&mut self) {
+        let solvable = !self.rules.stuck(&self.state.cards());
+        self.reactions.solvable_since = if solvable { Some(Instant::now()) } else { None };
+        self.reactions.samples.truncate(self.state.score);
     }
 
-    pub fn set_tidy_layout(&mut self, tidy: bool) {
-        self.config.set_tidy_layout(tidy);
-        self.redraw();
+    /// Records the time taken to find the set about to be scored.
+    fn record_reaction(&mut self) {
+        let score = self.state.score;
+        let elapsed = self.reactions.solvable_since.map(|since| since.elapsed());
+        self.reactions.samples.resize(score, None);
+        self.reactions.samples.push(elapsed);
     }
 
-    pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
-        self.config.set_color_scheme(scheme);
-        self.redraw();
+    /// The average time taken to find a set this game.
+    pub fn average_reaction(&self) -> Option<Duration> {
+        let times: Vec<Duration> = self.reactions.samples.iter().flatten().cloned().collect();
+        if times.is_empty() {
+            None
+        } else {
+            Some(times.iter().sum::<Duration>() / times.len() as u32)
+        }
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Selection
+// Idle Nudge
 ////////////////////////////////////////////////////////////////////////////////
 
 impl Controller {
-    fn deselect_all(&mut self) {
-        if !self.selected.is_empty() {
-            self.selected.clear();
-            self.redraw();
+    fn nudge_delay(&self) -> Duration {
+        Duration::from_secs(u64::from(self.config.nudge_seconds))
+    }
+
+    /// Restarts the wait before an idle player is nudged with a hint.
+    fn restart_idle_clock(&mut self) {
+        let since = Instant::now();
+        self.last_activity = since;
+
+        let delay = self.nudge_delay();
+        if !delay.is_zero() {
+            self.schedule(delay, move |controller| controller.nudge(since));
         }
     }
 
-    fn toggle_selected(&mut self, card: Card) {
-        if self.is_selected(card) {
-            self.selected.retain(|&c| c != card);
-        } else if self.selected.len() < self.rules.set_size() {
-            self.selected.push(card);
+    /// Shows a hint if nothing has happened since `since`. Nudges are
+    /// ordinary hints, so they honor the cooldown and count as help.
+    fn nudge(&mut self, since: Instant) {
+        // ignore nudges from before the latest activity
+        if self.last_activity != since || self.nudge_delay().is_zero() {
+            return;
         }
 
-        self.redraw_cell(self.state.index_of_card(card));
+        // stay quiet during playback, after the game, and on a stuck board
+        let busy = self.solution.is_some() || self.explanation.is_some();
+        if busy || self.is_finished() || self.rules.stuck(&self.state.cards()) {
+            return;
+        }
+
+        match self.last_hint {
+            // try again once the cooldown runs out
+            Some(time) if !self.can_hint() => {
+                let wait = self.hint_cooldown().saturating_sub(time.elapsed());
+                self.schedule(wait, move |controller| controller.nudge(since));
+            }
+            _ => {
+                self.show_hint();
+            }
+        }
     }
 
-    fn is_selected(&self, card: Card) -> bool {
-        // this is an O(n) test, but n is <= 4
-        self.selected.contains(&card)
+    pub fn set_nudge_seconds(&mut self, seconds: u32) {
+        self.config.set_nudge_seconds(seconds);
+        self.restart_idle_clock();
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Undo
+// Efficiency
 ////////////////////////////////////////////////////////////////////////////////
 
-struct UndoItem {
-    state: GameState,
-    action_name: &'static str,
+/// Formats a count with its noun, e.g. "1 hint" or "2 hints".
+fn quantity(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
 }
 
-/// Undo and Redo are symmetrical operations. This is implemented from
-/// the undo perspective, but redo is the same operation with the
-/// corresponding parameters swapped.
-macro_rules! create_do {
-    ($name:ident, $undo_stack:ident, $redo_stack:ident) => {
-        pub fn $name(&mut self) {
-            if let Some(prev) = self.$undo_stack.pop() {
-                // push the current state onto the redo stack
-                let redo = UndoItem {
-                    state: self.state.clone(),
-                    action_name: prev.action_name,
-                };
-                self.$redo_stack.push(redo);
-
-                // set the current state to the undo state
-                self.state = prev.state;
-                self.selected.clear();
-                self.redraw();
-
-                self.undo_status_changed();
-            }
-        }
-    };
+/// Help taken during a game. Every set removes the same number of
+/// cards, so a game always takes the minimum number of moves (the
+/// score). Efficiency instead rates how much help those moves needed.
+#[derive(Default)]
+struct Assists {
+    /// Hints shown
+    hints: usize,
+    /// Cards dealt while there was still a set on the board
+    early_deals: usize,
 }
 
 impl Controller {
-    fn register_undo(&mut self, action_name: &'static str) {
-        let item = UndoItem {
-            state: self.state.clone(),
-            action_name,
-        };
-        self.undo_stack.push(item);
-        self.redo_stack.clear();
-        self.undo_status_changed();
-    }
+    /// Moves as a fraction of moves plus help taken, where 1.0 means
+    /// every set was found unaided. None until a set has been found.
+    pub fn efficiency(&self) -> Option<f64> {
+        let moves = self.state.score;
+        if moves == 0 {
+            return None;
+        }
 
-    fn reset_undo_stacks(&mut self) {
-        self.undo_stack.clear();
-        self.redo_stack.clear();
-        self.undo_status_changed();
+        let help = self.assists.hints + self.assists.early_deals;
+        Some(moves as f64 / (moves + help) as f64)
     }
 
-    fn undo_status_changed(&self) {
-        // post undo nofifications
-        for f in &self.undo_observers {
-            f(self)
-        }
+    fn efficiency_summary(&self) -> Option<String> {
+        let rating = self.efficiency()?;
+        Some(format!(
+            "Your efficiency was {:.0}% ({}, {}, and {}).",
+            rating * 100.0,
+            quantity(self.state.score, "move"),
+            quantity(self.assists.hints, "hint"),
+            quantity(self.assists.early_deals, "early deal")
+        ))
     }
 
-    pub fn add_undo_observer<F>(&mut self, f: F)
-    where
-        F: Fn(&Controller) -> () + 'static,
-    {
-        self.undo_observers.push(Box::new(f));
+    pub fn set_show_efficiency(&mut self, show: bool) {
+        self.config.set_show_efficiency(show);
     }
+}
 
-    pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
-    }
+////////////////////////////////////////////////////////////////////////////////
+// Statistics
+////////////////////////////////////////////////////////////////////////////////
 
-    pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
-    }
+/// Running tallies for the current game. Undo doesn't take anything
+/// back, so these count what was done rather than what's on the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameStats {
+    /// Valid sets taken from the board
+    pub sets_found: usize,
+    /// Full selections that weren't a set
+    pub invalid_selections: usize,
+    /// Hints shown
+    pub hints: usize,
+    /// Times more cards were dealt
+    pub deals: usize,
+}
 
-    pub fn undo_action_name(&self) -> Option<&str> {
-        self.undo_stack.last().map(|item| item.action_name)
+impl Controller {
+    pub fn stats(&self) -> GameStats {
+        self.stats
     }
+}
 
-    pub fn redo_action_name(&self) -> Option<&str> {
-        self.redo_stack.last().map(|item| item.action_name)
+////////////////////////////////////////////////////////////////////////////////
+// Set Density
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    /// How many sets are on the table, a rough measure of how hard the
+    /// current board is. Counting is done when the cards change, so
+    /// this is cheap enough to call on every redraw.
+    pub fn available_set_count(&self) -> usize {
+        self.available_sets
     }
 
-    // pub fn undo(&mut self);
-    create_do!(undo, undo_stack, redo_stack);
+    fn recount_available_sets(&mut self) {
+        self.available_sets = self.rules.count_sets(&self.state.cards());
+    }
 
-    // pub fn redo(&mut self);
-    create_do!(redo, redo_stack, undo_stack);
+    pub fn set_show_set_count(&mut self, show: bool) {
+        self.config.set_show_set_count(show);
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Event Handling
+// Explanation
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Controller {
-    fn card_for_point(&self, x: f64, y: f64) -> Option<Card> {
-        // calculate the tableau row and column of the mouse location
-        let cell_width = self.tableau_bounds.width() / COLUMNS as f64;
-        let cell_height = self.tableau_bounds.height() / ROWS as f64;
+/// How long a found set is explained before it's removed.
+const EXPLANATION_DELAY: Duration = Duration::from_millis(1500);
 
-        let col = ((x - self.tableau_bounds.x()) / cell_width) as i32;
-        let row = ((y - self.tableau_bounds.y()) / cell_height) as i32;
+struct Explanation {
+    started: Instant,
+    text: String,
+}
 
-        let col_valid = 0 <= col && col < COLUMNS as i32;
-        let row_valid = 0 <= row && row < ROWS as i32;
+impl Controller {
+    /// Leaves the selected set on the board, along with the reason
+    /// it's valid, until the explanation delay passes.
+    fn explain_selection(&mut self) {
+        let started = Instant::now();
+        self.explanation = Some(Explanation {
+            started,
+            text: self.rules.explain(&self.selected),
+        });
+        self.redraw();
 
-        if col_valid && row_valid {
-            let cell_index = row as usize * COLUMNS + col as usize;
-            let cell = self.state.tableau[cell_index];
-            let cell_rect = self.cell_rects[cell_index];
-
-            if let Cell::Card(data) = cell {
-                let transform = !self.config.tidy_layout;
-                if data.point_in_rect(x, y, cell_rect, transform) {
-                    return Some(data.card);
-                }
+        self.schedule(EXPLANATION_DELAY, move |controller| {
+            // ignore explanations that were cut short
+            if controller.explanation.as_ref().map(|e| e.started) == Some(started) {
+                controller.finish_explanation();
             }
+        });
+    }
+
+    /// Removes the explained set right away. Anything that changes the
+    /// board calls this first, so that it acts on the board as it will
+    /// be after the removal.
+    fn finish_explanation(&mut self) {
+        if self.explanation.take().is_some() {
+            self.take_selection();
+            self.redraw();
         }
+    }
 
-        None
+    pub fn set_explain_sets(&mut self, explain: bool) {
+        self.config.set_explain_sets(explain);
+        if !explain {
+            self.finish_explanation();
+        }
     }
+}
 
-    fn set_exploded_cell(&mut self, cell: Option<usize>) {
-        if self.exploded_cell != cell {
-            // redisplay old cell
-            self.redraw_cell(self.exploded_cell);
-            self.exploded_cell = cell;
-            // redisplay new cell
-            self.redraw_cell(self.exploded_cell);
+////////////////////////////////////////////////////////////////////////////////
+// Challenge
+////////////////////////////////////////////////////////////////////////////////
+
+/// A timed game: find as many sets as possible before time runs out.
+struct Challenge {
+    countdown: Countdown,
+    /// best result before this challenge, for the final score screen
+    previous_best: Option<usize>,
+}
+
+/// Projects the current rate of finding sets to the end of the
+/// challenge and compares it with the best result. Too little time has
+/// passed in the first few seconds to say anything meaningful.
+fn on_pace(score: usize, best: Option<usize>, elapsed: Duration, limit: Duration) -> bool {
+    const WARMUP: Duration = Duration::from_secs(5);
+
+    if score == 0 || elapsed < WARMUP.min(limit) {
+        return false;
+    }
+
+    match best {
+        Some(best) => {
+            let projected = score as f64 * limit.as_secs_f64() / elapsed.as_secs_f64();
+            projected > best as f64
         }
+        // a first result is always a best
+        None => true,
     }
+}
 
-    fn set_inside_clicked_card(&mut self, flag: bool) {
-        if self.inside_clicked_card != flag {
-            self.inside_clicked_card = flag;
+impl Controller {
+    /// Starts a timed challenge with a full deck.
+    pub fn start_challenge(&mut self) {
+        let config = Config { deck: config::Deck::Full, ..self.config };
+        let seconds = self.config.challenge_seconds;
 
-            if let Some(card) = self.clicked_card {
-                // we transitioned in or out of the clicked card, so
-                // we need to toggle its selection state
-                self.toggle_selected(card);
-            }
+        self.new_game_with_state(Some(GameState::with_config(config)));
+        self.challenge = Some(Challenge {
+            countdown: Countdown::start(Duration::from_secs(seconds.into())),
+            previous_best: self.scores.load().best_challenge(config.variant, seconds),
+        });
+
+        self.keep_challenge_playable();
+        self.board_changed();
+        self.schedule_challenge_tick();
+    }
+
+    /// Makes sure the stock can always replenish the tableau with a
+    /// guaranteed set, reshuffling the discards back in when it runs low.
+    fn top_up_challenge_deck(&mut self) {
+        if self.challenge.is_some() && self.state.deck.remainder() < 2 * self.rules.set_size() {
+            let in_play = self.state.cards();
+            self.state.deck.restock(&in_play);
         }
     }
 
-    fn motion_notify(&mut self, _widget: &DrawingArea, event: &gdk::EventMotion) -> Inhibit {
-        let (x, y) = event.position();
-        let mouse_down_in_card = self.clicked_card.is_some();
-        let mut inside = false;
+    /// Deals until there's something to find, so the challenge is
+    /// about speed, not luck.
+    fn keep_challenge_playable(&mut self) {
+        if self.challenge.is_none() {
+            return;
+        }
 
-        if let Some(card) = self.card_for_point(x, y) {
-            inside = Some(card) == self.clicked_card;
-            if !mouse_down_in_card || inside {
-                let ix = self.state.index_of_card(card);
-                self.set_exploded_cell(ix);
-            }
+        let capacity = self.rules.deal_order().len();
+        while self.rules.stuck(&self.state.cards()) && self.state.card_count() < capacity {
+            self.top_up_challenge_deck();
+            self.state.deal(self.rules.set_size());
+        }
+
+        self.redraw();
+    }
+
+    fn schedule_challenge_tick(&self) {
+        if let Some(challenge) = &self.challenge {
+            let countdown = challenge.countdown;
+            // tick on whole seconds so the badge stays accurate
+            let delay = challenge.countdown.remaining().subsec_nanos();
+            let delay = if delay == 0 { Duration::from_secs(1) } else { Duration::from_nanos(delay.into()) };
+
+            self.schedule(delay, move |controller| controller.challenge_tick(countdown));
+        }
+    }
+
+    fn challenge_tick(&mut self, countdown: Countdown) {
+        // ignore ticks from a challenge that has since been abandoned
+        match &self.challenge {
+            Some(challenge) if challenge.countdown == countdown => (),
+            _ => return,
+        }
+
+        if countdown.is_expired() {
+            self.end_challenge();
         } else {
-            self.set_exploded_cell(None);
+            self.redraw();
+            self.schedule_challenge_tick();
         }
+    }
 
-        self.set_inside_clicked_card(inside);
+    /// True if the challenge in progress is on track to set a new best.
+    pub fn on_pace_for_best(&self) -> bool {
+        match &self.challenge {
+            Some(challenge) if !challenge.countdown.is_expired() => on_pace(
+                self.state.score,
+                challenge.previous_best,
+                challenge.countdown.elapsed(),
+                challenge.countdown.limit(),
+            ),
+            _ => false,
+        }
+    }
 
-        Inhibit(false)
+    pub fn set_confirm_close_on_pace(&mut self, confirm: bool) {
+        self.config.set_confirm_close_on_pace(confirm);
     }
 
-    fn button_press(&mut self, _widget: &DrawingArea, event: &gdk::EventButton) -> Inhibit {
-        let single = event.event_type() == gdk::EventType::ButtonPress;
-        let primary = event.button() == 1;
+    fn end_challenge(&mut self) {
+        let seconds = match &self.challenge {
+            Some(challenge) => challenge.countdown.limit().as_secs() as u32,
+            None => return,
+        };
 
-        if single && primary {
-            let (x, y) = event.position();
+        // a set found just before time ran out still counts
+        if self.explanation.take().is_some() {
+            self.state.take_cards(&self.selected, &*self.rules, self.config.replenish_policy);
+        }
 
-            if let Some(card) = self.card_for_point(x, y) {
-                self.clicked_card = Some(card);
-                self.inside_clicked_card = true;
-                self.toggle_selected(card);
-            }
+        let mut scores = self.scores.load();
+
+        if scores.record_challenge(self.config.variant, seconds, self.state.score) {
+            self.scores.save(&scores);
         }
 
-        Inhibit(false)
+        self.selected.clear();
+        self.sync_game_clock();
+        self.recount_available_sets();
+        self.emit(GameEvent::BoardChanged);
+        self.redraw();
+        self.check_for_finish();
     }
+}
 
-    fn button_release(&mut self, _widget: &DrawingArea, event: &gdk::EventButton) -> Inhibit {
-        if event.button() == 1 {
-            self.clicked_card = None;
-            self.inside_clicked_card = false;
-            self.check_for_set();
+////////////////////////////////////////////////////////////////////////////////
+// Game Clock
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    /// Starts timing a new game from zero.
+    fn restart_game_clock(&mut self) {
+        self.game_clock = Stopwatch::start();
+        self.schedule_clock_tick();
+    }
+
+    /// Stops the clock once the game is over, and starts it again if
+    /// an undo resumes play.
+    fn sync_game_clock(&mut self) {
+        if self.is_finished() {
+            self.game_clock.pause();
+        } else if !self.game_clock.is_running() {
+            self.game_clock.resume();
+            self.schedule_clock_tick();
         }
+    }
 
-        Inhibit(false)
+    pub fn game_time(&self) -> Duration {
+        self.game_clock.elapsed()
     }
 
-    fn key_press(&mut self, _widget: &DrawingArea, event: &gdk::EventKey) -> Inhibit {
-        if let Some(byte) = event.keyval().to_u8() {
-            let letter = byte as char;
+    fn schedule_clock_tick(&self) {
+        let clock = self.game_clock;
+        // tick on whole seconds so the timer stays accurate
+        let fraction = Duration::from_nanos(clock.elapsed().subsec_nanos().into());
+        let delay = Duration::from_secs(1) - fraction;
 
-            // only pay attention to lowercase letters with no modifiers
-            if letter.is_alphabetic() && event.state().is_empty() {
-                if let Some(hotkey) = letter.to_lowercase().next() {
-                    if let Some(card) = self.state.card_for_key(hotkey) {
-                        self.toggle_selected(card);
-                    }
-                }
-            }
+        self.schedule(delay, move |controller| controller.clock_tick(clock));
+    }
+
+    fn clock_tick(&mut self, clock: Stopwatch) {
+        // ignore ticks from a clock that has since been paused or replaced
+        if self.game_clock != clock || !clock.is_running() {
+            return;
         }
 
-        // make sure we don't lose focus
-        let inhibit = event.keyval() == gdk::keys::constants::Tab;
-        Inhibit(inhibit)
+        let timer = self.state.timer_cell();
+        let showing = matches!(self.state.tableau[timer], Cell::Timer);
+        if self.config.show_timer && showing && timer < self.cell_rects.len() {
+            self.redraw_cell(Some(timer));
+        }
+        self.schedule_clock_tick();
     }
 
-    fn key_release(&mut self, _widget: &DrawingArea, _event: &gdk::EventKey) -> Inhibit {
-        self.check_for_set();
-        Inhibit(false)
+    pub fn set_show_timer(&mut self, show: bool) {
+        self.config.set_show_timer(show);
+        self.redraw();
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Rendering
+// Hint Escalation
 ////////////////////////////////////////////////////////////////////////////////
 
-/// Returns the space needed for `n` `items` separated by `spacing`. Assumes
-/// spacing is applied between items, at the beginning, and at the end.
-fn span(n: usize, item: f64, spacing: f64) -> f64 {
-    (item + spacing) * (n as f64) + spacing
+/// The set that repeated hints are revealing, one card at a time.
+struct HintEscalation {
+    /// The board the set was chosen for
+    board: Vec<Card>,
+    /// Cards of the set in the order they're revealed
+    grouping: Vec<Card>,
+    revealed: usize,
 }
 
 impl Controller {
-    fn layout(&mut self, _widget: &DrawingArea, allocation: &Allocation) {
-        let (w, h) = (allocation.width(), allocation.height());
+    /// Reveals one more card of the hinted set than last time. A new
+    /// set is chosen whenever the board has changed in between.
+    fn escalate_hint(&mut self, cards: &[Card]) -> Option<Vec<Card>> {
+        let stale = match &self.hint_escalation {
+            Some(escalation) => escalation.board != cards,
+            None => true,
+        };
 
-        // figure out the tableau aspect ratio
-        let spacing_percentage = 0.15;
-        let tableau_spacing = CARD_WIDTH * spacing_percentage;
-        let tableau_width = span(COLUMNS, CARD_WIDTH, tableau_spacing);
-        let tableau_height = span(ROWS, CARD_HEIGHT, tableau_spacing);
-        let tableau_aspect_ratio = tableau_width / tableau_height;
+        if stale {
+            self.hint_escalation = None;
 
-        // figure out the view aspect ratio
-        let (view_width, view_height) = (f64::from(w), f64::from(h));
-        let view_aspect_ratio = view_width / view_height;
+            // start from a regular hint so the choice follows the hint mode
+            let grouping = self.rules.hint_with_level(cards, self.config.hint_mode(), HintLevel::Full)?;
 
-        // now squeeze the tableau into the view
-        let effective_view_width = if view_aspect_ratio > tableau_aspect_ratio {
-            // height constrained...
-            view_height * tableau_aspect_ratio
-        } else {
-            view_width
-        };
+            self.hint_escalation = Some(HintEscalation {
+                board: cards.to_owned(),
+                grouping,
+                revealed: 0,
+            });
+        }
 
-        let card_width = effective_view_width / span(COLUMNS, 1., spacing_percentage);
-        let card_height = CARD_HEIGHT / CARD_WIDTH * card_width;
-        let spacing = card_width * spacing_percentage;
+        let escalation = self.hint_escalation.as_mut()?;
+        escalation.revealed = (escalation.revealed + 1).min(escalation.grouping.len());
+        Some(escalation.grouping[..escalation.revealed].to_vec())
+    }
 
-        // ... and center it
-        let offset_x = (view_width - span(COLUMNS, card_width, spacing)) / 2.;
-        let offset_y = (view_height - span(ROWS, card_height, spacing)) / 2.;
+    pub fn set_escalating_hints(&mut self, escalating: bool) {
+        self.config.set_escalating_hints(escalating);
+        self.hint_escalation = None;
+    }
 
-        for y in 0..ROWS {
-            let dy = offset_y + span(y, card_height, spacing);
-            for x in 0..COLUMNS {
-                let dx = offset_x + span(x, card_width, spacing);
-                let rect = Rectangle::new(dx, dy, card_width, card_height);
-                self.cell_rects[y * COLUMNS + x] = rect.round();
-            }
+    pub fn set_hint_level(&mut self, level: HintLevel) {
+        self.config.set_hint_level(level);
+    }
+
+    pub fn set_hint_deterministic(&mut self, deterministic: bool) {
+        self.config.set_hint_deterministic(deterministic);
+        self.hint_escalation = None;
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Hint Cooldown
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    fn hint_cooldown(&self) -> Duration {
+        Duration::from_secs(u64::from(self.config.hint_cooldown))
+    }
+
+    /// Hints are unavailable while the cooldown is running.
+    pub fn can_hint(&self) -> bool {
+        match self.last_hint {
+            Some(time) => time.elapsed() >= self.hint_cooldown(),
+            None => true,
         }
+    }
 
-        let bounds = Rectangle::new(
-            offset_x,
-            offset_y,
-            span(COLUMNS, card_width, spacing),
-            span(ROWS, card_height, spacing),
-        );
+    fn start_hint_cooldown(&mut self) {
+        let cooldown = self.hint_cooldown();
 
-        self.tableau_bounds = bounds.inset(spacing, spacing);
+        if !cooldown.is_zero() {
+            self.last_hint = Some(Instant::now());
+            self.hint_status_changed();
+            self.schedule(cooldown, |controller| controller.hint_status_changed());
+        }
     }
 
-    fn draw(&self, _widget: &DrawingArea, ctx: &Context) -> Inhibit {
-        let remainder = self.state.deck.remainder();
-        let remainder_label = if remainder == 1 {
-            "card left"
-        } else {
-            "cards left"
-        };
-        let scheme = self.config.color_scheme;
+    /// Finding a set ends the cooldown early.
+    fn reset_hint_cooldown(&mut self) {
+        if self.last_hint.take().is_some() {
+            self.hint_status_changed();
+        }
+    }
 
-        // view background
-        if VISUALIZE_REDRAWS {
-            ctx.set_source_random_rgb()
-        } else {
-            ctx.set_source_gray(0.8)
-        }
-        ctx.paint().unwrap();
-
-        let iter = self.state.tableau.iter().zip(self.cell_rects.iter());
-        for (ix, (&cell, &rect)) in iter.enumerate() {
-            match cell {
-                Cell::Deck => ctx.draw_badge(rect, remainder, remainder_label),
-                Cell::Score => ctx.draw_badge(rect, self.state.score, "found"),
-                Cell::Placeholder => ctx.draw_card_placeholder(rect),
-                Cell::Card(data) => {
-                    ctx.save().unwrap();
-                    ctx.with_pivot(rect.center(), || {
-                        if self.exploded_cell == Some(ix) {
-                            ctx.scale(EXPLODE, EXPLODE)
-                        }
-                        if !self.config.tidy_layout {
-                            ctx.rotate(data.angle)
-                        }
-                    });
-                    if self.is_selected(data.card) {
-                        ctx.draw_card_selection(rect).unwrap();
-                    }
-                    ctx.draw_card(data.card, rect, Some(&data.hotkey.to_string()), scheme)
-                        .unwrap();
-                    ctx.restore()
-                }
-            }
-            .unwrap();
+    fn hint_status_changed(&self) {
+        for f in &self.hint_observers {
+            f(self)
         }
+    }
 
-        Inhibit(false)
+    pub fn add_hint_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller) -> () + 'static,
+    {
+        self.hint_observers.push(Box::new(f));
     }
+}
 
-    fn redraw(&self) {
-        self.view.queue_draw();
+////////////////////////////////////////////////////////////////////////////////
+// Config
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    pub fn set_deck(&mut self, deck: config::Deck) {
+        self.config.set_deck(deck);
+        self.new_game();
     }
 
-    fn redraw_in_rect(&self, rect: Rectangle) {
-        let integral_rect = rect.round();
-        self.view.queue_draw_area(
-            integral_rect.x() as i32,
-            integral_rect.y() as i32,
-            integral_rect.width() as i32,
-            integral_rect.height() as i32,
-        );
+    pub fn set_quick_game(&mut self, size: Option<usize>) {
+        self.config.set_quick_game(size);
+        self.new_game();
     }
 
-    fn redraw_cell(&self, cell_index: Option<usize>) {
-        if let Some(ix) = cell_index {
-            let rect = self.cell_rects[ix];
-            let padding = rect.width() * 0.2;
-            self.redraw_in_rect(rect.inset(-padding, -padding));
+    pub fn set_variant(&mut self, variant: config::Variant) {
+        self.config.set_variant(variant);
+        self.rules = self.config.rules();
+        self.update_minimum_view_size();
+        self.new_game();
+    }
+
+    pub fn set_sloppiness(&mut self, sloppiness: f64) {
+        let sloppiness = clamp_float(sloppiness, (0., 1.));
+        self.config.set_sloppiness(sloppiness);
+        // give the cards a fresh look within the new bounds
+        if sloppiness > 0. {
+            self.register_cosmetic_undo("Rearrange");
+        }
+        self.state.reroll_angles();
+        self.redraw();
+    }
+
+    pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        self.config.set_color_scheme(scheme);
+        self.redraw();
+    }
+
+    pub fn set_show_hotkeys(&mut self, show: bool) {
+        self.config.set_show_hotkeys(show);
+        self.redraw();
+    }
+
+    pub fn set_cosmetic_undoable(&mut self, undoable: bool) {
+        self.config.set_cosmetic_undoable(undoable);
+    }
+
+    pub fn set_hover_style(&mut self, hover_style: HoverStyle) {
+        self.config.set_hover_style(hover_style);
+        self.redraw_cell(self.exploded_cell);
+    }
+
+    pub fn set_render_quality(&mut self, quality: config::RenderQuality) {
+        self.config.set_render_quality(quality);
+        self.redraw();
+    }
+
+    pub fn set_show_grid(&mut self, show: bool) {
+        self.config.set_show_grid(show);
+        self.redraw();
+    }
+
+    pub fn set_click_empty_deselects(&mut self, deselects: bool) {
+        self.config.set_click_empty_deselects(deselects);
+    }
+
+    pub fn set_preserve_selection_on_undo(&mut self, preserve: bool) {
+        self.config.set_preserve_selection_on_undo(preserve);
+    }
+
+    pub fn set_card_shadows(&mut self, shadows: bool) {
+        self.config.set_card_shadows(shadows);
+        self.redraw();
+    }
+
+    pub fn set_tint_stale_cards(&mut self, tint: bool) {
+        self.config.set_tint_stale_cards(tint);
+        self.redraw();
+    }
+
+    /// Switches to `theme` with one save and one redraw. Settings
+    /// observers are notified so that menus can follow along.
+    pub fn apply_theme(&mut self, theme: Theme) {
+        if theme == self.config.theme() {
+            return;
+        }
+
+        if theme.sloppiness != self.config.effective_sloppiness() {
+            if theme.sloppiness > 0. {
+                self.register_cosmetic_undo("Rearrange");
+            }
+            self.state.reroll_angles();
+        }
+
+        self.config.apply_theme(theme);
+        self.redraw();
+        self.settings_changed();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Selection
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    fn deselect_all(&mut self) {
+        if !self.selected.is_empty() {
+            self.selected.clear();
+            self.redraw();
+        }
+    }
+
+    fn toggle_selected(&mut self, card: Card) {
+        // the board is only up for review once the game is over, and
+        // a set being explained stays selected until it's removed
+        if self.is_finished() || self.explanation.is_some() || self.solution.is_some() {
+            return;
         }
+
+        self.clear_deal_highlight();
+        self.cancel_invalid_flash();
+
+        if self.is_selected(card) {
+            self.selected.retain(|&c| c != card);
+        } else if self.selected.len() < self.rules.set_size() {
+            self.selected.push(card);
+        }
+
+        self.redraw_cell(self.state.index_of_card(card));
+    }
+
+    /// Drops any selected cards that are no longer on the tableau.
+    fn prune_selection(&mut self) {
+        let state = &self.state;
+        let before = self.selected.len();
+        self.selected.retain(|&card| state.index_of_card(card).is_some());
+
+        if self.selected.len() != before {
+            self.redraw();
+        }
+    }
+
+    fn is_selected(&self, card: Card) -> bool {
+        // this is an O(n) test, but n is <= 4
+        self.selected.contains(&card)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Deal Highlight
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    fn highlight_dealt_cells(&mut self, cells: Vec<usize>) {
+        self.deal_highlight = Some((Instant::now(), cells));
+        self.schedule(ANIMATION_FRAME, Controller::animate_deal_highlight);
+    }
+
+    fn animate_deal_highlight(&mut self) {
+        if let Some((start, ref cells)) = self.deal_highlight {
+            for &ix in cells {
+                self.redraw_cell(Some(ix));
+            }
+
+            if start.elapsed() < DEAL_HIGHLIGHT {
+                self.schedule(ANIMATION_FRAME, Controller::animate_deal_highlight);
+            } else {
+                self.deal_highlight = None;
+            }
+        }
+    }
+
+    fn clear_deal_highlight(&mut self) {
+        if let Some((_, cells)) = self.deal_highlight.take() {
+            for ix in cells {
+                self.redraw_cell(Some(ix));
+            }
+        }
+    }
+
+    /// Opacity of the highlight for a cell, fading out over time.
+    fn deal_highlight_alpha(&self, cell_index: usize) -> Option<f64> {
+        match self.deal_highlight {
+            Some((start, ref cells)) if cells.contains(&cell_index) => {
+                let progress = start.elapsed().as_secs_f64() / DEAL_HIGHLIGHT.as_secs_f64();
+                if progress < 1. {
+                    Some(DEAL_HIGHLIGHT_ALPHA * (1. - progress))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Invalid Flash
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    /// Briefly outlines a rejected selection in red. The selection itself
+    /// has already moved on (`last` was dropped from it), so the flash is
+    /// only decoration and never holds up the next click.
+    fn flash_invalid_selection(&mut self, last: Card) {
+        let mut cards = self.selected.clone();
+        cards.push(last);
+        self.redraw_cards(&cards);
+
+        let start = Instant::now();
+        self.invalid_flash = Some((start, cards));
+        self.schedule(INVALID_FLASH, move |controller| controller.end_invalid_flash(start));
+    }
+
+    fn end_invalid_flash(&mut self, start: Instant) {
+        // a flash that was canceled or replaced has nothing left to clear
+        if matches!(self.invalid_flash, Some((started, _)) if started == start) {
+            self.cancel_invalid_flash();
+        }
+    }
+
+    fn cancel_invalid_flash(&mut self) {
+        if let Some((_, cards)) = self.invalid_flash.take() {
+            self.redraw_cards(&cards);
+        }
+    }
+
+    fn redraw_cards(&self, cards: &[Card]) {
+        for &card in cards {
+            self.redraw_cell(self.state.index_of_card(card));
+        }
+    }
+
+    fn flashed_cards(&self) -> &[Card] {
+        match self.invalid_flash {
+            Some((_, ref cards)) => cards,
+            None => &[],
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Undo
+////////////////////////////////////////////////////////////////////////////////
+
+/// What an undo item restores. Game snapshots rewind everything,
+/// while layout snapshots only restore the tableau so that undoing a
+/// cosmetic rearrangement never rewinds the deck or score.
+#[derive(Clone, Serialize, Deserialize)]
+enum Snapshot {
+    Game(GameState),
+    Layout(Vec<Cell>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UndoItem {
+    snapshot: Snapshot,
+    action_name: String,
+}
+
+impl UndoItem {
+    fn is_cosmetic(&self) -> bool {
+        match self.snapshot {
+            Snapshot::Layout(_) => true,
+            Snapshot::Game(_) => false,
+        }
+    }
+}
+
+/// Undo and Redo are symmetrical operations. This is implemented from
+/// the undo perspective, but redo is the same operation with the
+/// corresponding parameters swapped.
+macro_rules! create_do {
+    ($name:ident, $undo_stack:ident, $redo_stack:ident, $event:expr) => {
+        pub fn $name(&mut self) {
+            // a set being explained is as good as taken
+            self.finish_explanation();
+            self.solution = None;
+
+            if let Some(prev) = self.$undo_stack.pop() {
+                let score = self.state.score;
+
+                // push the current state onto the redo stack
+                let current = match prev.snapshot {
+                    Snapshot::Game(_) => Snapshot::Game(self.state.clone()),
+                    Snapshot::Layout(_) => Snapshot::Layout(self.state.tableau.clone()),
+                };
+                let redo = UndoItem {
+                    snapshot: current,
+                    action_name: prev.action_name,
+                };
+                self.$redo_stack.push(redo);
+
+                // set the current state to the undo state
+                match prev.snapshot {
+                    Snapshot::Game(state) => self.state = state,
+                    Snapshot::Layout(tableau) => self.state.tableau = tableau,
+                }
+                // cards that left the board are always deselected
+                if !self.config.preserve_selection_on_undo {
+                    self.selected.clear();
+                }
+                self.prune_selection();
+                self.deal_highlight = None;
+                // time spent before undoing doesn't count
+                self.board_changed();
+                self.set_status(None);
+                self.redraw();
+
+                self.emit($event);
+                if self.state.score != score {
+                    self.emit(GameEvent::ScoreChanged);
+                }
+                self.undo_status_changed();
+            }
+        }
+    };
+}
+
+impl Controller {
+    fn push_undo(&mut self, snapshot: Snapshot, action_name: &'static str) {
+        // challenges are about speed, so there's no going back
+        if self.challenge.is_some() {
+            return;
+        }
+
+        let item = UndoItem {
+            snapshot,
+            action_name: action_name.to_string(),
+        };
+        self.undo_stack.push(item);
+        self.redo_stack.clear();
+        self.trim_undo_stacks();
+        self.undo_status_changed();
+    }
+
+    /// Limits how many actions can be undone (None is unlimited). The
+    /// stacks are trimmed right away if they're already too deep.
+    pub fn set_max_undo_depth(&mut self, depth: Option<usize>) {
+        self.config.set_max_undo_depth(depth.map(|n| n.max(1)));
+        self.trim_undo_stacks();
+        self.undo_status_changed();
+    }
+
+    /// Drops the oldest entries beyond the configured depth. The first
+    /// game snapshot is the starting point for restart, so it's kept
+    /// below the most recent entries.
+    fn trim_undo_stacks(&mut self) {
+        let max = match self.config.max_undo_depth {
+            Some(max) => max,
+            None => return,
+        };
+
+        let anchor = self.undo_stack.iter().position(|item| !item.is_cosmetic());
+        let cutoff = self.undo_stack.len().saturating_sub(max);
+        let mut ix = 0;
+        self.undo_stack.retain(|_| {
+            let keep = ix >= cutoff || Some(ix) == anchor;
+            ix += 1;
+            keep
+        });
+
+        // the next redo is at the end of the stack
+        let cutoff = self.redo_stack.len().saturating_sub(max);
+        self.redo_stack.drain(..cutoff);
+    }
+
+    fn register_undo(&mut self, action_name: &'static str) {
+        let snapshot = Snapshot::Game(self.state.clone());
+        self.push_undo(snapshot, action_name);
+    }
+
+    /// Records the tableau before a cosmetic rearrangement, if the
+    /// user wants those to be undoable.
+    fn register_cosmetic_undo(&mut self, action_name: &'static str) {
+        if self.config.cosmetic_undoable {
+            let snapshot = Snapshot::Layout(self.state.tableau.clone());
+            self.push_undo(snapshot, action_name);
+        }
+    }
+
+    fn reset_undo_stacks(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.undo_status_changed();
+    }
+
+    fn undo_status_changed(&self) {
+        self.emit(GameEvent::UndoChanged);
+    }
+
+    pub fn add_undo_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller) -> () + 'static,
+    {
+        self.add_event_observer(move |controller, event| {
+            if event == GameEvent::UndoChanged {
+                f(controller)
+            }
+        });
+    }
+
+    /// Restarting only makes sense once a game action has been taken.
+    pub fn can_restart(&self) -> bool {
+        self.undo_stack.iter().any(|item| !item.is_cosmetic())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn undo_action_name(&self) -> Option<&str> {
+        self.undo_stack.last().map(|item| item.action_name.as_str())
+    }
+
+    pub fn redo_action_name(&self) -> Option<&str> {
+        self.redo_stack.last().map(|item| item.action_name.as_str())
+    }
+
+    // pub fn undo(&mut self);
+    create_do!(undo, undo_stack, redo_stack, GameEvent::Undone);
+
+    // pub fn redo(&mut self);
+    create_do!(redo, redo_stack, undo_stack, GameEvent::Redone);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Session
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    /// Captures the game in progress, or `None` if there's nothing
+    /// worth resuming.
+    pub fn session(&self) -> Option<Session> {
+        if !self.can_restart() || self.is_finished() {
+            return None;
+        }
+
+        Some(self.capture_session())
+    }
+
+    fn capture_session(&self) -> Session {
+        Session {
+            config: self.config,
+            state: self.state.clone(),
+            undo_stack: self.undo_stack.clone(),
+            redo_stack: self.redo_stack.clone(),
+        }
+    }
+
+    pub fn save_session(&self) {
+        match self.session() {
+            Some(session) => session.save().unwrap_or_else(|err| {
+                log_error!("Could not save the game in progress: {}", err);
+            }),
+            None => Session::discard(),
+        }
+    }
+
+    /// Resumes a saved game. The saved variant and deck take precedence
+    /// over the current settings, since the game can't continue without them.
+    pub fn load_session(&mut self, session: Session) {
+        self.adopt_variant_and_deck(session.config.variant, session.config.deck);
+
+        let mut state = session.state;
+        // recover from a session saved with a short board
+        if state.card_count() < self.rules.initial_deal_size() {
+            state.fill_empty();
+        }
+
+        // start over as for any new game, but keep the saved history
+        self.new_game_with_state(Some(state));
+        self.undo_stack = session.undo_stack;
+        self.redo_stack = session.redo_stack;
+        self.undo_status_changed();
+
+        // the clock starts from zero, so the time would be too short
+        self.records_time = false;
+    }
+
+    /// A short code from which the current game's starting board can
+    /// be recreated.
+    pub fn board_share_code(&self) -> String {
+        let share = ShareCode {
+            seed: self.state.seed,
+            variant: self.config.variant,
+            deck: self.config.deck,
+        };
+        share.encode()
+    }
+
+    /// Starts the game described by a share code, switching variant
+    /// and deck if needed.
+    pub fn load_share_code(&mut self, code: &str) -> Result<(), ShareError> {
+        let share: ShareCode = code.parse()?;
+        self.adopt_variant_and_deck(share.variant, share.deck);
+
+        let state = GameState::with_config_seeded(self.config, share.seed);
+        self.new_game_with_state(Some(state));
+        Ok(())
+    }
+
+    /// Saves the current game to a named slot, returning the slot
+    /// used. Unless `overwrite` is set, a name that's already taken gets
+    /// a numbered suffix rather than replacing the earlier save.
+    pub fn save_slot(&self, name: &str, overwrite: bool) -> Result<String, SlotError> {
+        let slot = if overwrite {
+            session::slot_name(name)
+        } else {
+            session::unused_slot_name(name, &session::list_slots()?)
+        };
+
+        let slot = slot.ok_or(SlotError::BadName)?;
+        self.capture_session().save_slot(&slot)?;
+        Ok(slot)
+    }
+
+    /// Resumes the game saved in `slot`, switching to its variant and
+    /// deck if they differ from the current ones.
+    pub fn load_slot(&mut self, slot: &str) -> Result<(), SlotError> {
+        let session = Session::load_slot(slot)?;
+        self.load_session(session);
+        Ok(())
+    }
+
+    /// Saves the current game, along with its undo history, to `path`.
+    pub fn save_game(&self, path: &Path) -> ConfigResult<()> {
+        self.capture_session().save_to(path)
+    }
+
+    /// Resumes the game saved at `path`, as with `load_slot`.
+    pub fn load_game(&mut self, path: &Path) -> ConfigResult<()> {
+        let session = Session::load_from(path)?;
+        self.load_session(session);
+        Ok(())
+    }
+
+    /// Names of the saved game slots (empty if they can't be read).
+    pub fn list_slots() -> Vec<String> {
+        session::list_slots().unwrap_or_else(|err| {
+            log_error!("Could not list saved games: {}", err);
+            vec![]
+        })
+    }
+
+    /// Switches settings without starting a new game, for loading
+    /// games that were made with other settings.
+    fn adopt_variant_and_deck(&mut self, variant: config::Variant, deck: config::Deck) {
+        if variant != self.config.variant || deck != self.config.deck {
+            self.config.set_variant_and_deck(variant, deck);
+            self.rules = self.config.rules();
+            self.update_minimum_view_size();
+            self.settings_changed();
+        }
+    }
+
+    fn settings_changed(&self) {
+        for f in &self.settings_observers {
+            f(self)
+        }
+    }
+
+    pub fn add_settings_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller) -> () + 'static,
+    {
+        self.settings_observers.push(Box::new(f));
+    }
+
+    pub fn set_auto_save(&mut self, auto_save: bool) {
+        self.config.set_auto_save(auto_save);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Event Handling
+////////////////////////////////////////////////////////////////////////////////
+
+/// Ways the user can toggle a card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Mouse,
+    Key,
+}
+
+/// Clicks and hotkeys on one card closer together than this count once.
+const DOUBLE_TOGGLE_WINDOW: Duration = Duration::from_millis(150);
+
+impl Controller {
+    fn card_for_point(&self, x: f64, y: f64) -> Option<Card> {
+        if self.config.hit_padding > 0. {
+            return self.nearest_card_for_point(x, y);
+        }
+
+        // calculate the tableau row and column of the mouse location
+        let (columns, rows) = self.state.grid_size();
+        let cell_width = self.tableau_bounds.width() / columns as f64;
+        let cell_height = self.tableau_bounds.height() / rows as f64;
+
+        let col = ((x - self.tableau_bounds.x()) / cell_width) as i32;
+        let row = ((y - self.tableau_bounds.y()) / cell_height) as i32;
+
+        let col_valid = 0 <= col && col < columns as i32;
+        let row_valid = 0 <= row && row < rows as i32;
+
+        if col_valid && row_valid {
+            let cell_index = row as usize * columns + col as usize;
+            let cell = self.state.tableau[cell_index];
+            let cell_rect = self.cell_rects[cell_index];
+
+            if let Cell::Card(data) = cell {
+                let sloppiness = self.config.effective_sloppiness();
+                if data.point_in_rect(x, y, cell_rect, sloppiness) {
+                    return Some(data.card);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Hit tests against card rects grown by the configured padding.
+    /// The grown rects can overlap, so the card whose center is nearest
+    /// to the point wins.
+    fn nearest_card_for_point(&self, x: f64, y: f64) -> Option<Card> {
+        let sloppiness = self.config.effective_sloppiness();
+        let distance = |rect: &Rectangle| {
+            let (cx, cy) = rect.center();
+            (cx - x).hypot(cy - y)
+        };
+
+        self.state.cards_with_index()
+            .map(|(ix, data)| (data, self.cell_rects[ix]))
+            .filter(|(data, rect)| {
+                let padding = rect.width() * self.config.hit_padding;
+                data.point_in_rect(x, y, rect.inset(-padding, -padding), sloppiness)
+            })
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(data, _)| data.card)
+    }
+
+    pub fn set_hit_padding(&mut self, padding: f64) {
+        self.config.set_hit_padding(padding);
+    }
+
+    /// Toggles a card in response to a click or hotkey. A click and a
+    /// hotkey on the same card in quick succession are almost certainly
+    /// one intended action, so the second one is ignored.
+    fn toggle_from(&mut self, card: Card, input: Input) {
+        let now = Instant::now();
+
+        if let Some((last_card, last_input, time)) = self.last_toggle {
+            let simultaneous = now.duration_since(time) < DOUBLE_TOGGLE_WINDOW;
+            if simultaneous && last_card == card && last_input != input {
+                return;
+            }
+        }
+
+        self.last_toggle = Some((card, input, now));
+        self.key_toggled |= input == Input::Key;
+        self.toggle_selected(card);
+        self.restart_idle_clock();
+    }
+
+    fn set_exploded_cell(&mut self, cell: Option<usize>) {
+        if self.exploded_cell != cell {
+            // redisplay old cell
+            self.redraw_cell(self.exploded_cell);
+            self.exploded_cell = cell;
+            // redisplay new cell
+            self.redraw_cell(self.exploded_cell);
+        }
+    }
+
+    /// Reminds the user which key selects the card under the mouse.
+    fn update_tooltip(&self, card: Option<Card>) {
+        if let Some(ref view) = self.view {
+            // hotkeys only work while they're shown
+            let text = if self.hovering_deck {
+                Some(self.deck_tooltip())
+            } else {
+                card.filter(|_| self.config.show_hotkeys)
+                    .and_then(|card| self.state.hotkey_for_card(card))
+                    .map(|key| format!("Press {} to select", key))
+            };
+            view.set_tooltip_text(text.as_deref());
+        }
+    }
+
+    /// Describes the deck and the board, for the deck badge's tooltip.
+    fn deck_tooltip(&self) -> String {
+        let cards = self.state.cards();
+        format!(
+            "{} left in the deck\n{} in play\n{} available",
+            quantity(self.state.deck.remainder(), "card"),
+            quantity(cards.len(), "card"),
+            quantity(self.rules.count_sets(&cards), self.rules.name())
+        )
+    }
+
+    fn deck_cell(&self) -> Option<usize> {
+        self.state.cells_with_index()
+            .find(|(_, cell)| matches!(cell, Cell::Deck))
+            .map(|(ix, _)| ix)
+    }
+
+    fn deck_badge_contains(&self, x: f64, y: f64) -> bool {
+        self.deck_cell()
+            .and_then(|ix| self.cell_rects.get(ix))
+            .is_some_and(|rect| rect.contains_point(x, y))
+    }
+
+    fn set_hovering_deck(&mut self, hovering: bool) {
+        if self.hovering_deck != hovering {
+            self.hovering_deck = hovering;
+            // a clickable badge is ringed while the mouse is over it
+            if self.config.deck_button {
+                self.redraw_cell(self.deck_cell());
+            }
+        }
+    }
+
+    /// Clicking the deck badge (when enabled) is the same as choosing
+    /// Deal More Cards.
+    fn click_deck(&mut self) {
+        self.deal_more_cards();
+    }
+
+    /// Observers are told about messages meant for the player, such as
+    /// why no cards were dealt or how the game went.
+    pub fn add_message_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> () + 'static,
+    {
+        self.message_observers.push(Box::new(f));
+    }
+
+    fn announce(&self, message: String) {
+        for f in &self.message_observers {
+            f(&message)
+        }
+    }
+
+    pub fn set_deck_button(&mut self, deck_button: bool) {
+        self.config.set_deck_button(deck_button);
+        self.redraw_cell(self.deck_cell());
+    }
+
+    fn set_inside_clicked_card(&mut self, flag: bool) {
+        if self.inside_clicked_card != flag {
+            self.inside_clicked_card = flag;
+
+            if let Some(card) = self.clicked_card {
+                // we transitioned in or out of the clicked card, so
+                // we need to toggle its selection state
+                self.toggle_selected(card);
+            }
+        }
+    }
+
+    fn motion_notify(&mut self, _widget: &DrawingArea, event: &gdk::EventMotion) -> Inhibit {
+        let (x, y) = event.position();
+        let mouse_down_in_card = self.clicked_card.is_some();
+        let mut inside = false;
+
+        if let Some(card) = self.card_for_point(x, y) {
+            inside = Some(card) == self.clicked_card;
+            if !mouse_down_in_card || inside {
+                let ix = self.state.index_of_card(card);
+                self.set_hovering_deck(false);
+                self.set_exploded_cell(ix);
+                self.update_tooltip(Some(card));
+            }
+        } else {
+            self.set_hovering_deck(self.deck_badge_contains(x, y));
+            self.set_exploded_cell(None);
+            self.update_tooltip(None);
+        }
+
+        self.set_inside_clicked_card(inside);
+
+        Inhibit(false)
+    }
+
+    fn button_press(&mut self, _widget: &DrawingArea, event: &gdk::EventButton) -> Inhibit {
+        let single = event.event_type() == gdk::EventType::ButtonPress;
+        let primary = event.button() == 1;
+
+        if single && primary {
+            let (x, y) = event.position();
+            self.click_at(x, y);
+        }
+
+        Inhibit(false)
+    }
+
+    /// Handles a primary click at a point in the view.
+    fn click_at(&mut self, x: f64, y: f64) {
+        self.cancel_invalid_flash();
+
+        if let Some(card) = self.card_for_point(x, y) {
+            self.clicked_card = Some(card);
+            self.inside_clicked_card = true;
+            self.toggle_from(card, Input::Mouse);
+        } else if self.config.deck_button && self.deck_badge_contains(x, y) {
+            self.click_deck();
+        } else if self.config.click_empty_deselects {
+            // leave sets that are being explained or played back alone
+            if self.explanation.is_none() && self.solution.is_none() {
+                self.deselect_all();
+            }
+        }
+    }
+
+    fn button_release(&mut self, _widget: &DrawingArea, event: &gdk::EventButton) -> Inhibit {
+        if event.button() == 1 {
+            self.clicked_card = None;
+            self.inside_clicked_card = false;
+            self.check_for_set();
+        }
+
+        Inhibit(false)
+    }
+
+    fn key_press(&mut self, _widget: &DrawingArea, event: &gdk::EventKey) -> Inhibit {
+        if self.navigate_key(&event.keyval()) {
+            return Inhibit(true);
+        }
+
+        if let Some(byte) = event.keyval().to_u8() {
+            let letter = byte as char;
+
+            let ctrl_shift = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK;
+            if letter.eq_ignore_ascii_case(&'d') && event.state().contains(ctrl_shift) {
+                self.toggle_debug_overlay();
+            }
+
+            // only pay attention to lowercase letters with no modifiers,
+            // and only when the hotkeys are visible
+            let hotkeys = self.config.show_hotkeys;
+            if hotkeys && letter.is_alphabetic() && event.state().is_empty() {
+                if let Some(hotkey) = letter.to_lowercase().next() {
+                    if let Some(card) = self.state.card_for_key(hotkey) {
+                        self.toggle_from(card, Input::Key);
+                    }
+                }
+            }
+        }
+
+        // make sure we don't lose focus
+        let inhibit = event.keyval() == gdk::keys::constants::Tab;
+        Inhibit(inhibit)
+    }
+
+    fn key_release(&mut self, _widget: &DrawingArea, _event: &gdk::EventKey) -> Inhibit {
+        self.key_released();
+        Inhibit(false)
+    }
+
+    /// Checks the selection once a key that toggled a card comes up.
+    /// Other keys leave it alone, so that a hint from the keyboard only
+    /// highlights its cards, even when it shows the whole set.
+    fn key_released(&mut self) {
+        if self.key_toggled {
+            self.key_toggled = false;
+            self.check_for_set();
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Keyboard Focus
+////////////////////////////////////////////////////////////////////////////////
+
+/// A step across the tableau grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Controller {
+    /// Handles the arrow keys, which move the focus, and Space or
+    /// Enter, which toggle the focused card. Returns false for any
+    /// other key.
+    fn navigate_key(&mut self, key: &gdk::keys::Key) -> bool {
+        use gdk::keys::constants;
+
+        let direction = match key {
+            k if *k == constants::Up => Direction::Up,
+            k if *k == constants::Down => Direction::Down,
+            k if *k == constants::Left => Direction::Left,
+            k if *k == constants::Right => Direction::Right,
+            k if *k == constants::space || *k == constants::Return || *k == constants::KP_Enter => {
+                self.toggle_focused();
+                return true;
+            }
+            _ => return false,
+        };
+
+        self.move_focus(direction);
+        true
+    }
+
+    /// Moves the focus to the nearest card in `direction`, skipping
+    /// cells without one. At the edge of the tableau the focus stays
+    /// put. The first move lands on the first card instead.
+    fn move_focus(&mut self, direction: Direction) {
+        let is_card = |ix: usize| matches!(self.state.tableau.get(ix), Some(Cell::Card(_)));
+        let (columns, rows) = self.state.grid_size();
+
+        let next = match self.focused_cell {
+            None => (0..self.state.tableau.len()).find(|&ix| is_card(ix)),
+            Some(ix) => {
+                let (mut row, mut column) = (ix / columns, ix % columns);
+                let mut found = None;
+                loop {
+                    match direction {
+                        Direction::Up if row > 0 => row -= 1,
+                        Direction::Down if row + 1 < rows => row += 1,
+                        Direction::Left if column > 0 => column -= 1,
+                        Direction::Right if column + 1 < columns => column += 1,
+                        _ => break,
+                    }
+                    if is_card(row * columns + column) {
+                        found = Some(row * columns + column);
+                        break;
+                    }
+                }
+                found.or(Some(ix))
+            }
+        };
+
+        self.set_focused_cell(next);
+    }
+
+    fn set_focused_cell(&mut self, cell: Option<usize>) {
+        if self.focused_cell != cell {
+            self.redraw_cell(self.focused_cell);
+            self.focused_cell = cell;
+            self.redraw_cell(self.focused_cell);
+        }
+    }
+
+    fn toggle_focused(&mut self) {
+        let card = self.focused_cell.and_then(|ix| self.state.tableau.get(ix)?.card());
+        if let Some(card) = card {
+            self.toggle_from(card, Input::Key);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Solution
+////////////////////////////////////////////////////////////////////////////////
+
+/// Delay between selecting a set and taking it during playback.
+const SOLUTION_STEP: Duration = Duration::from_millis(800);
+
+impl Controller {
+    /// Searches for a way to clear the rest of the game and plays it
+    /// back one set at a time. Announces why not if there's nothing
+    /// to play back.
+    pub fn show_solution(&mut self) {
+        self.finish_explanation();
+
+        if self.config.variant != config::Variant::Set {
+            return self.announce("Solutions are only available for Set.".to_string());
+        } else if self.challenge.is_some() {
+            return self.announce("Solutions aren't available during a challenge.".to_string());
+        }
+
+        // the stock is dealt from the end
+        let mut deck = self.state.deck.remaining_cards();
+        deck.reverse();
+
+        let dealing = Dealing {
+            initial_deal: self.rules.initial_deal_size(),
+            deal_batch: self.config.deal_batch.unwrap_or(self.rules.set_size()),
+            replenish: self.config.replenish_policy,
+            max_board: self.rules.deal_order().len(),
+            guarantee_sets: self.state.guarantees_sets(),
+        };
+
+        match solve(&self.state.cards(), &deck, dealing) {
+            Some(mut steps) => {
+                steps.reverse();
+                let started = Instant::now();
+                self.solution = Some((started, steps));
+                self.records_time = false;
+                self.deselect_all();
+                self.schedule(SOLUTION_STEP, move |controller| controller.solution_step(started));
+            }
+            None => self.announce("No way to clear the board was found.".to_string()),
+        }
+    }
+
+    /// Alternates between selecting the next set and taking it.
+    fn solution_step(&mut self, started: Instant) {
+        // ignore steps from a playback that has since been stopped
+        let steps = match self.solution {
+            Some((time, ref mut steps)) if time == started => steps,
+            _ => return,
+        };
+
+        if self.selected.is_empty() {
+            let step = match steps.pop() {
+                Some(step) => step,
+                None => {
+                    self.solution = None;
+                    return;
+                }
+            };
+
+            // the board may need more cards before the set appears,
+            // possibly more than one deal's worth
+            let on_board = |state: &GameState| step.iter().all(|&card| state.index_of_card(card).is_some());
+            while !on_board(&self.state) && self.rules.stuck(&self.state.cards()) && self.can_deal() {
+                self.deal_more_cards();
+            }
+            if !on_board(&self.state) {
+                self.solution = None;
+                return;
+            }
+
+            self.selected = step.to_vec();
+            self.redraw();
+        } else if self.explanation.is_none() {
+            self.check_for_set();
+
+            // stop if the set wasn't taken after all
+            if self.explanation.is_none() && !self.selected.is_empty() {
+                self.deselect_all();
+                self.solution = None;
+                return;
+            }
+        }
+
+        self.schedule(SOLUTION_STEP, move |controller| controller.solution_step(started));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Rendering
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the space needed for `n` `items` separated by `spacing`. Assumes
+/// spacing is applied between items, at the beginning, and at the end.
+fn span(n: usize, item: f64, spacing: f64) -> f64 {
+    (item + spacing) * (n as f64) + spacing
+}
+
+/// Returns the size of a `(columns, rows)` tableau when cards are
+/// `card_width` wide.
+fn tableau_size(card_width: f64, (columns, rows): (usize, usize)) -> (f64, f64) {
+    let card_height = CARD_HEIGHT / CARD_WIDTH * card_width;
+    let spacing = card_width * SPACING_PERCENTAGE;
+    (span(columns, card_width, spacing), span(rows, card_height, spacing))
+}
+
+/// Returns the configured minimum view size. If none is configured,
+/// the minimum is derived from the number of cards the variant can
+/// deal: sparser tableaus can get by with smaller cards.
+fn minimum_view_size(config: &Config) -> (i32, i32) {
+    if let Some(size) = config.min_view_size {
+        return size;
+    }
+
+    let rules = config.rules();
+    let num_cards = rules.deal_order().len() as f64;
+    let card_width = MIN_CARD_WIDTH * f64::sqrt(num_cards / FULL_TABLEAU);
+    let (width, height) = tableau_size(card_width, rules.grid_size());
+    (width.ceil() as i32, height.ceil() as i32)
+}
+
+/// Returns a window size that frames the variant's tableau, for use
+/// when no size has been saved. Like the minimum, it scales with the
+/// number of cells the variant can deal.
+pub fn default_window_size(config: &Config) -> (i32, i32) {
+    let rules = config.rules();
+    let num_cards = rules.deal_order().len() as f64;
+    let card_width = DEFAULT_CARD_WIDTH * f64::sqrt(num_cards / FULL_TABLEAU);
+    let (width, height) = tableau_size(card_width, rules.grid_size());
+    (width.ceil() as i32, height.ceil() as i32)
+}
+
+/// Settings that affect how cards are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CardStyle {
+    pub scheme: ColorScheme,
+    pub sloppiness: f64,
+    pub show_hotkeys: bool,
+    pub tint_stale_cards: bool,
+    pub shadows: bool,
+    pub hover: HoverStyle,
+}
+
+impl CardStyle {
+    pub fn with_config(config: &Config) -> CardStyle {
+        CardStyle {
+            scheme: config.color_scheme,
+            sloppiness: config.effective_sloppiness(),
+            show_hotkeys: config.show_hotkeys,
+            tint_stale_cards: config.tint_stale_cards,
+            shadows: config.card_shadows && config.render_quality != RenderQuality::Fast,
+            hover: config.hover_style,
+        }
+    }
+}
+
+/// Everything needed to draw the tableau cells, independent of the
+/// widget, so the same drawing code can target off-screen surfaces.
+pub struct Board<'a> {
+    pub cells: &'a [Cell],
+    /// Where each cell is drawn
+    pub rects: &'a [Rectangle],
+    pub selected: &'a [Card],
+    /// Cards of a rejected selection, outlined in red
+    pub flashed: &'a [Card],
+    /// Counts and labels for the deck and score badges
+    pub deck_badge: (usize, &'a str),
+    pub score_badge: (usize, &'a str),
+    /// Time played and its label (None shows an empty cell instead)
+    pub timer: Option<(Duration, &'a str)>,
+    /// Cell beneath the mouse, marked according to the hover style
+    pub exploded: Option<usize>,
+    /// Cell with the keyboard focus
+    pub focused: Option<usize>,
+    /// Ring the deck badge, which can be clicked to deal
+    pub deck_hovered: bool,
+    /// Opacity of the deal highlight for each cell (if any)
+    pub highlights: Vec<Option<f64>>,
+    pub style: CardStyle,
+    /// Background and the colors that contrast with it
+    pub colors: BadgeColors,
+}
+
+/// Draws every cell of `board`: badges, placeholders, and cards.
+pub fn render_board(ctx: &Context, board: &Board) -> Result<(), cairo::Error> {
+    let style = board.style;
+    let colors = &board.colors;
+
+    let iter = board.cells.iter().zip(board.rects.iter());
+    for (ix, (&cell, &rect)) in iter.enumerate() {
+        match cell {
+            Cell::Deck => {
+                ctx.draw_badge(rect, board.deck_badge.0, board.deck_badge.1, colors)?;
+                if board.deck_hovered {
+                    ctx.draw_card_hover_ring(rect)?;
+                }
+                Ok(())
+            }
+            Cell::Score => ctx.draw_badge(rect, board.score_badge.0, board.score_badge.1, colors),
+            Cell::Placeholder => ctx.draw_card_placeholder(rect, colors),
+            Cell::Timer => match board.timer {
+                Some((elapsed, label)) => ctx.draw_timer(rect, elapsed, label, colors),
+                None => ctx.draw_card_placeholder(rect, colors),
+            },
+            Cell::Card(data) => {
+                let hovered = board.exploded == Some(ix);
+                // highlighting leaves the geometry alone, lifting the card with a shadow
+                let lifted = hovered && style.hover == HoverStyle::Highlight;
+
+                ctx.save()?;
+                ctx.with_pivot(rect.center(), || {
+                    if hovered && style.hover == HoverStyle::Explode {
+                        ctx.scale(EXPLODE, EXPLODE)
+                    }
+                    if style.sloppiness > 0. {
+                        ctx.rotate(data.rotation(style.sloppiness))
+                    }
+                });
+                // shadows rotate along with their cards
+                if style.shadows || lifted {
+                    ctx.draw_card_shadow(rect)?;
+                }
+                if board.flashed.contains(&data.card) {
+                    ctx.draw_card_selection(rect, INVALID_SELECTION_COLOR)?;
+                } else if board.selected.contains(&data.card) {
+                    ctx.draw_card_selection(rect, SELECTION_COLOR)?;
+                } else if lifted {
+                    ctx.draw_card_hover_ring(rect)?;
+                } else if let Some(alpha) = board.highlights.get(ix).copied().flatten() {
+                    ctx.draw_card_outline(rect, alpha)?;
+                }
+                let hotkey = data.hotkey.to_string();
+                let label = if style.show_hotkeys { Some(hotkey.as_str()) } else { None };
+                ctx.draw_card(data.card, rect, label, style.scheme)?;
+                if style.tint_stale_cards && data.deals_survived >= STALE_DEALS {
+                    ctx.draw_card_tint(rect, STALE_TINT)?;
+                }
+                if board.focused == Some(ix) {
+                    ctx.draw_focus_ring(rect)?;
+                }
+                ctx.restore()
+            }
+        }?;
+    }
+
+    Ok(())
+}
+
+impl Controller {
+    fn layout(&mut self, widget: &DrawingArea, allocation: &Allocation) {
+        self.scale = f64::from(widget.scale_factor());
+        self.layout_for_size(allocation.width(), allocation.height());
+    }
+
+    /// Lays out again for a new scale, such as when the window moves
+    /// to a screen with a different density. The size stays the same.
+    fn scale_changed(&mut self, widget: &DrawingArea) {
+        self.scale = f64::from(widget.scale_factor());
+        self.relayout();
+        self.redraw();
+    }
+
+    /// Lays out again at the current size.
+    fn relayout(&mut self) {
+        let (width, height) = self.view_size;
+        self.layout_for_size(width, height);
+    }
+
+    /// Lays out again if the tableau's grid has changed size, as it can
+    /// when a new game or a saved one uses different rules.
+    fn fit_grid(&mut self) {
+        if self.cell_rects.len() != self.state.tableau.len() {
+            // cell indices from the old grid mean nothing in the new one
+            self.exploded_cell = None;
+            self.focused_cell = None;
+            self.relayout();
+        }
+    }
+
+    fn layout_for_size(&mut self, w: i32, h: i32) {
+        // GTK can hand out empty allocations (e.g. at startup or when
+        // minimized). Clamp them so the layout math stays finite.
+        let (w, h) = (w.max(1), h.max(1));
+        self.view_size = (w, h);
+
+        let (columns, rows) = self.state.grid_size();
+        self.cell_rects.resize(columns * rows, zero_rect());
+
+        // figure out the tableau aspect ratio
+        let (tableau_width, tableau_height) = tableau_size(CARD_WIDTH, (columns, rows));
+        let tableau_aspect_ratio = tableau_width / tableau_height;
+
+        // figure out the view aspect ratio
+        let (view_width, view_height) = (f64::from(w), f64::from(h));
+        let view_aspect_ratio = view_width / view_height;
+
+        // now squeeze the tableau into the view
+        let effective_view_width = if view_aspect_ratio > tableau_aspect_ratio {
+            // height constrained...
+            view_height * tableau_aspect_ratio
+        } else {
+            view_width
+        };
+
+        let card_width = effective_view_width / span(columns, 1., SPACING_PERCENTAGE);
+        let card_height = CARD_HEIGHT / CARD_WIDTH * card_width;
+        let spacing = card_width * SPACING_PERCENTAGE;
+
+        // ... and center it
+        let offset_x = (view_width - span(columns, card_width, spacing)) / 2.;
+        let offset_y = (view_height - span(rows, card_height, spacing)) / 2.;
+
+        for y in 0..rows {
+            let dy = offset_y + span(y, card_height, spacing);
+            for x in 0..columns {
+                let dx = offset_x + span(x, card_width, spacing);
+                let rect = Rectangle::new(dx, dy, card_width, card_height);
+                // align edges to device pixels so outlines stay crisp
+                self.cell_rects[y * columns + x] = rect.round_to_scale(self.scale);
+            }
+        }
+
+        let bounds = Rectangle::new(
+            offset_x,
+            offset_y,
+            span(columns, card_width, spacing),
+            span(rows, card_height, spacing),
+        );
+
+        self.tableau_bounds = bounds.inset(spacing, spacing);
+    }
+
+    fn draw(&self, _widget: &DrawingArea, ctx: &Context) -> Inhibit {
+        self.draw_view(ctx);
+        Inhibit(false)
+    }
+
+    /// Everything the widget shows: the rendered board, then anything
+    /// drawn over the view alone.
+    fn draw_view(&self, ctx: &Context) {
+        if let Err(err) = self.render(ctx) {
+            log_error!("Could not draw the tableau: {}", err);
+        }
+
+        // previews and the debug overlay are drawn over the view only,
+        // so exported images never include them
+        if self.config.preview_deals {
+            if let Err(err) = self.render_deal_preview(ctx) {
+                log_error!("Could not draw the deal preview: {}", err);
+            }
+        }
+        if self.debug_overlay {
+            if let Err(err) = self.render_debug_overlay(ctx) {
+                log_error!("Could not draw the debug overlay: {}", err);
+            }
+        }
+    }
+
+    /// Ghosts the cards the next deal would add into the empty cells.
+    fn render_deal_preview(&self, ctx: &Context) -> Result<(), cairo::Error> {
+        // an endless challenge deck has nothing meaningful to preview
+        if self.challenge.is_some() {
+            return Ok(());
+        }
+
+        let scheme = self.config.color_scheme;
+        for (ix, card) in self.next_deal_cells() {
+            ctx.draw_card_ghost(card, self.cell_rects[ix], PREVIEW_ALPHA, scheme)?;
+        }
+        Ok(())
+    }
+
+    /// Outlines the tableau bounds and each cell rect (as used for hit
+    /// testing), labeled with the cell's tableau index.
+    fn render_debug_overlay(&self, ctx: &Context) -> Result<(), cairo::Error> {
+        ctx.draw_debug_rect(self.tableau_bounds, None)?;
+        for (ix, &rect) in self.cell_rects.iter().enumerate() {
+            ctx.draw_debug_rect(rect, Some(&ix.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn toggle_debug_overlay(&mut self) {
+        self.debug_overlay = !self.debug_overlay;
+        self.redraw();
+    }
+
+    /// Renders the tableau into any cairo context.
+    fn render(&self, ctx: &Context) -> Result<(), cairo::Error> {
+        let board = self.board();
+        ctx.set_antialias(self.config.render_quality.antialias());
+
+        // view background
+        if VISUALIZE_REDRAWS {
+            ctx.set_source_random_rgb()
+        } else {
+            let (r, g, b) = board.colors.background;
+            ctx.set_source_rgb(r, g, b)
+        }
+        ctx.paint()?;
+
+        // cards render on top of the grid
+        if self.config.show_grid {
+            for &rect in &self.cell_rects {
+                ctx.draw_cell_outline(rect, &board.colors)?;
+            }
+        }
+
+        render_board(ctx, &board)?;
+
+        if let Some(explanation) = &self.explanation {
+            let bounds = self.tableau_bounds;
+            let (_, rows) = self.state.grid_size();
+            let height = bounds.height() / rows as f64 * 0.25;
+            let rect = Rectangle::new(bounds.x(), bounds.max_y() - height, bounds.width(), height);
+            ctx.draw_caption(rect, &explanation.text)?;
+        }
+
+        Ok(())
+    }
+
+    /// Describes the tableau as it should currently be drawn.
+    fn board(&self) -> Board {
+        let labels = &self.config.badge_labels;
+        // during a challenge the deck is endless, so show the time left instead
+        let deck_badge = match &self.challenge {
+            Some(challenge) => {
+                let seconds = challenge.countdown.remaining().as_secs_f64().ceil() as usize;
+                (seconds, labels.seconds_left(seconds))
+            }
+            None => {
+                let remainder = self.state.deck.remainder();
+                (remainder, labels.cards_left(remainder))
+            }
+        };
+
+        Board {
+            cells: &self.state.tableau,
+            rects: &self.cell_rects,
+            selected: &self.selected,
+            flashed: self.flashed_cards(),
+            deck_badge,
+            score_badge: (self.state.score, labels.found),
+            timer: if self.config.show_timer { Some((self.game_time(), labels.elapsed)) } else { None },
+            exploded: self.exploded_cell,
+            focused: self.focused_cell,
+            deck_hovered: self.config.deck_button && self.hovering_deck,
+            highlights: (0..self.cell_rects.len()).map(|ix| self.deal_highlight_alpha(ix)).collect(),
+            style: CardStyle::with_config(&self.config),
+            colors: ensure_contrast(self.config.background),
+        }
+    }
+
+    /// Renders the tableau at its current size into a new image, with
+    /// as many pixels as the screen would use.
+    fn render_image(&self) -> Result<ImageSurface, cairo::Error> {
+        let (width, height) = self.view_size;
+        let pixels = |size: i32| (f64::from(size) * self.scale).round() as i32;
+        let surface = ImageSurface::create(Format::ARgb32, pixels(width), pixels(height))?;
+        surface.set_device_scale(self.scale, self.scale);
+        let ctx = Context::new(&surface)?;
+        self.render(&ctx)?;
+        drop(ctx);
+        Ok(surface)
+    }
+
+    fn redraw(&self) {
+        if let Some(view) = &self.view {
+            view.queue_draw();
+        }
+
+        // the deck tooltip describes the board, so keep it current
+        if self.hovering_deck {
+            self.update_tooltip(None);
+        }
+    }
+
+    fn redraw_in_rect(&self, rect: Rectangle) {
+        if let Some(view) = &self.view {
+            // cover every device pixel the rect touches, whatever the scale
+            let integral_rect = rect.round_out();
+            view.queue_draw_area(
+                integral_rect.x() as i32,
+                integral_rect.y() as i32,
+                integral_rect.width() as i32,
+                integral_rect.height() as i32,
+            );
+        }
+    }
+
+    fn redraw_cell(&self, cell_index: Option<usize>) {
+        if let Some(ix) = cell_index {
+            let rect = self.cell_rects[ix];
+            // leaves room for rotation, hover scaling, and card shadows
+            let padding = rect.width() * 0.2;
+            self.redraw_in_rect(rect.inset(-padding, -padding));
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Status Line
+////////////////////////////////////////////////////////////////////////////////
+
+impl Controller {
+    /// The game message to show in the status line, if any. Messages
+    /// are cleared whenever the board changes.
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn set_status(&mut self, message: Option<String>) {
+        if message != self.status {
+            self.status = message;
+            self.status_changed();
+        }
+    }
+
+    pub fn set_use_status_line(&mut self, use_status_line: bool) {
+        self.config.set_use_status_line(use_status_line);
+        self.status = None;
+        self.status_changed();
+    }
+
+    fn status_changed(&self) {
+        for f in &self.status_observers {
+            f(self)
+        }
+    }
+
+    pub fn add_status_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Controller) -> () + 'static,
+    {
+        self.status_observers.push(Box::new(f));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Openings
+////////////////////////////////////////////////////////////////////////////////
+
+/// An easy first lesson: the only Set on this board is three cards
+/// that differ in shading alone.
+const FIRST_LESSON: [usize; 12] = [17, 0, 41, 73, 1, 33, 68, 75, 2, 49, 77, 74];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OpeningError {
+    /// The board must hold at least one set's worth of cards, and no
+    /// more than the tableau can fit.
+    Size(usize),
+    /// The same card can't be dealt twice.
+    Repeated(Card),
+}
+
+impl fmt::Display for OpeningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpeningError::Size(n) =>
+                write!(f, "An opening can't be made from {} cards.", n),
+            OpeningError::Repeated(card) =>
+                write!(f, "{:?} appears more than once in the opening.", card),
+        }
+    }
+}
+
+impl error::Error for OpeningError {}
+
+impl Controller {
+    /// Starts a game on exactly `cards` (in deal order), for scripted
+    /// lessons. The rest of the deck is shuffled and dealt as usual.
+    pub fn load_opening(&mut self, cards: &[Card]) -> Result<(), OpeningError> {
+        let capacity = self.rules.deal_order().len();
+        if cards.len() < self.rules.set_size() || cards.len() > capacity {
+            return Err(OpeningError::Size(cards.len()));
+        }
+
+        if let Some(ix) = (1..cards.len()).find(|&ix| cards[..ix].contains(&cards[ix])) {
+            return Err(OpeningError::Repeated(cards[ix]));
+        }
+
+        let state = GameState::with_opening(self.config, cards);
+        self.new_game_with_state(Some(state));
+        Ok(())
+    }
+
+    /// Starts the first lesson, switching to Set with the full deck.
+    pub fn load_first_lesson(&mut self) {
+        self.adopt_variant_and_deck(config::Variant::Set, config::Deck::Full);
+
+        let cards: Vec<Card> = FIRST_LESSON.iter().cloned().map(Card::new).collect();
+        self.load_opening(&cards).expect("the first lesson is a valid opening");
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Board Snapshot
+////////////////////////////////////////////////////////////////////////////////
+
+/// Read-only view of a card on the tableau, for secondary displays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CardView {
+    /// Tableau cell index
+    pub index: usize,
+    pub card: Card,
+    pub selected: bool,
+    pub hotkey: char,
+}
+
+impl Controller {
+    /// The cards in play, in tableau order.
+    pub fn board_snapshot(&self) -> Vec<CardView> {
+        let mut views = Vec::with_capacity(self.state.card_count());
+        views.extend(self.state.cards_with_index().map(|(index, data)| CardView {
+            index,
+            card: data.card,
+            selected: self.is_selected(data.card),
+            hotkey: data.hotkey,
+        }));
+        views
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Export
+////////////////////////////////////////////////////////////////////////////////
+
+/// A scripted step for exported demos.
+#[derive(Clone, Copy, Debug)]
+pub enum DemoAction {
+    /// Reveal a hint
+    Hint,
+    /// Toggle a card, taking the selection if it completes a set
+    Select(Card),
+}
+
+impl Controller {
+    /// Renders the tableau at its current size into a PNG file.
+    fn export_png(&self, path: &Path) -> Result<(), IoError> {
+        let surface = self.render_image().map_err(IoError::Cairo)?;
+        let mut file = File::create(path).map_err(IoError::Io)?;
+        surface.write_to_png(&mut file)
+    }
+
+    /// Plays `actions` on a copy of the current game, writing a
+    /// numbered frame for the starting board and one after each step
+    /// (frame-000.png, frame-001.png, ...). Frames are rendered as
+    /// soon as each step completes, so the output doesn't depend on
+    /// timing. The live game is left untouched.
+    pub fn export_frames<P: AsRef<Path>>(&self, dir: P, actions: &[DemoAction]) -> Result<(), IoError> {
+        let dir = dir.as_ref();
+        let frame_path = |n: usize| dir.join(format!("frame-{:03}.png", n));
+
+        let mut demo = Controller::with_config(self.config, None);
+        demo.state = self.state.clone();
+        demo.selected = self.selected.clone();
+        demo.layout_for_size(self.view_size.0, self.view_size.1);
+        demo.export_png(&frame_path(0))?;
+
+        for (ix, &action) in actions.iter().enumerate() {
+            match action {
+                DemoAction::Hint => {
+                    demo.show_hint();
+                }
+                DemoAction::Select(card) => {
+                    demo.toggle_selected(card);
+                    demo.check_for_set();
+                }
+            }
+            demo.export_png(&frame_path(ix + 1))?;
+        }
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::card::CompleteSet;
+    use crate::rules::HintMode;
+    use core::find::FindSets;
+    use core::pair_iter::PairIter;
+
+    fn assert_no_dangling_selection(controller: &Controller) {
+        for &card in &controller.selected {
+            assert!(controller.state.index_of_card(card).is_some());
+        }
+    }
+
+    /// Deals until the tableau contains at least one set.
+    fn ensure_set(controller: &mut Controller) -> Vec<Card> {
+        while controller.rules.stuck(&controller.state.cards()) {
+            controller.deal_more_cards();
+        }
+
+        let set = controller.state.cards().find_first_set().unwrap();
+        let (a, b, c) = set.cards();
+        vec![a, b, c]
+    }
+
+    /// Collects the messages the controller announces from here on.
+    fn record_messages(controller: &mut Controller) -> Rc<RefCell<Vec<String>>> {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let log = messages.clone();
+        controller.add_message_observer(move |message| log.borrow_mut().push(message.to_string()));
+        messages
+    }
+
+    #[test]
+    fn check_game_events() {
+        let mut controller = Controller::headless(Config::new());
+        let set = ensure_set(&mut controller);
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let observed = events.clone();
+        controller.add_event_observer(move |_, event| observed.borrow_mut().push(event));
+
+        // a miss: only the last card of the set finishes the first two
+        let miss = controller.state.cards().into_iter()
+            .find(|card| !set.contains(card))
+            .unwrap();
+        for &card in &[set[0], set[1], miss] {
+            controller.toggle_selected(card);
+        }
+        controller.check_for_set();
+        controller.deselect_all();
+
+        // a hit, then a deal, then rewind and replay the hit
+        for &card in &set {
+            controller.toggle_selected(card);
+        }
+        controller.check_for_set();
+        controller.force_deal();
+        controller.undo();
+        controller.undo();
+        controller.redo();
+
+        use GameEvent::*;
+        assert_eq!(*events.borrow(), vec![
+            InvalidAttempt,
+            UndoChanged, BoardChanged, SetFound, ScoreChanged,
+            UndoChanged, BoardChanged, Dealt,
+            BoardChanged, Undone, UndoChanged,
+            BoardChanged, Undone, ScoreChanged, UndoChanged,
+            BoardChanged, Redone, ScoreChanged, UndoChanged,
+        ]);
+    }
+
+    #[test]
+    fn check_undo_never_leaves_dangling_selection() {
+        let mut controller = Controller::headless(Config::new());
+
+        for _ in 0..3 {
+            let set = ensure_set(&mut controller);
+            let before = (controller.state.cards(), controller.state.score);
+            for &card in &set {
+                controller.toggle_selected(card);
+            }
+            controller.check_for_set();
+            assert_no_dangling_selection(&controller);
+            let after = (controller.state.cards(), controller.state.score);
+            assert_ne!(after, before);
+
+            // select a freshly dealt card, then rewind past its deal
+            if let Some(card) = controller.state.cards().last().copied() {
+                controller.toggle_selected(card);
+            }
+            controller.undo();
+            assert_eq!((controller.state.cards(), controller.state.score), before);
+            assert_no_dangling_selection(&controller);
+
+            controller.redo();
+            assert_eq!((controller.state.cards(), controller.state.score), after);
+            assert_no_dangling_selection(&controller);
+        }
+    }
+
+    #[test]
+    fn check_for_set_ignores_absent_cards() {
+        let mut controller = Controller::headless(Config::new());
+        let cards = controller.state.cards();
+
+        // find a pair whose completing card isn't on the table
+        let (&a, &b) = cards.pairs()
+            .find(|&pair| !cards.contains(&pair.complete_set()))
+            .unwrap();
+        let absent = (a, b).complete_set();
+
+        controller.selected = vec![a, b, absent];
+        controller.check_for_set();
+
+        assert_eq!(controller.state.score, 0);
+        assert_eq!(controller.selected, vec![a, b]);
+        assert_no_dangling_selection(&controller);
+    }
+
+    #[test]
+    fn check_minimum_view_size() {
+        let mut config = Config::new();
+        let (set_width, set_height) = minimum_view_size(&config);
+
+        config.variant = config::Variant::SuperSet;
+        let (superset_width, superset_height) = minimum_view_size(&config);
+
+        // the sparser SuperSet tableau can be smaller
+        assert!(superset_width < set_width);
+        assert!(superset_height < set_height);
+
+        config.min_view_size = Some((640, 360));
+        assert_eq!(minimum_view_size(&config), (640, 360));
+    }
+
+    #[test]
+    fn check_default_window_size() {
+        let mut config = Config::new();
+        let (set_width, set_height) = default_window_size(&config);
+
+        // the default frames the board without letterboxing
+        let (tableau_width, tableau_height) = tableau_size(CARD_WIDTH, config.rules().grid_size());
+        let aspect_ratio = f64::from(set_width) / f64::from(set_height);
+        assert!((aspect_ratio - tableau_width / tableau_height).abs() < 0.01);
+
+        // and always leaves room above the minimum
+        let (min_width, min_height) = minimum_view_size(&config);
+        assert!(set_width > min_width && set_height > min_height);
+
+        config.variant = config::Variant::SuperSet;
+        let (superset_width, superset_height) = default_window_size(&config);
+        assert!(superset_width < set_width);
+        assert!(superset_height < set_height);
+    }
+
+    #[test]
+    fn check_hint_cooldown() {
+        let mut config = Config::new();
+        config.hint_cooldown = 60;
+        let mut controller = Controller::headless(config);
+        let set = ensure_set(&mut controller);
+
+        assert!(controller.can_hint());
+        controller.show_hint();
+        assert!(!controller.can_hint());
+
+        // finding a set ends the cooldown
+        controller.selected = set;
+        controller.check_for_set();
+        assert!(controller.can_hint());
+    }
+
+    #[test]
+    fn check_cosmetic_undo() {
+        let mut config = Config::new();
+        config.cosmetic_undoable = true;
+        let mut controller = Controller::headless(config);
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+
+        let score = controller.state.score;
+        let remainder = controller.state.deck.remainder();
+        let layout = |state: &GameState| -> Vec<(usize, Card, f64)> {
+            state.cards_with_index().map(|(ix, data)| (ix, data.card, data.angle)).collect()
+        };
+        let before = layout(&controller.state);
+
+        controller.register_cosmetic_undo("Rearrange");
+        controller.state.reroll_angles();
+        assert!(controller.can_restart());
+
+        // undoing the rearrangement leaves the score and deck alone
+        controller.undo();
+        assert_eq!(layout(&controller.state), before);
+        assert_eq!(controller.state.score, score);
+        assert_eq!(controller.state.deck.remainder(), remainder);
+        assert_eq!(controller.undo_action_name(), Some("Set"));
+
+        // cosmetic items alone aren't enough to restart
+        controller.new_game();
+        controller.register_cosmetic_undo("Rearrange");
+        assert!(controller.can_undo());
+        assert!(!controller.can_restart());
+    }
+
+    #[test]
+    fn check_shuffle_tableau() {
+        let mut controller = Controller::headless(Config::new());
+        let layout = |state: &GameState| -> Vec<(usize, Card, char, f64)> {
+            state.cards_with_index().map(|(ix, data)| (ix, data.card, data.hotkey, data.angle)).collect()
+        };
+        let before = layout(&controller.state);
+        let remainder = controller.state.deck.remainder();
+
+        // shuffling is undoable even when other rearranging isn't
+        assert!(!controller.config.cosmetic_undoable);
+        controller.shuffle_tableau();
+        assert_eq!(controller.undo_action_name(), Some("Shuffle"));
+        assert_eq!(controller.state.deck.remainder(), remainder);
+        assert_eq!(controller.state.card_count(), before.len());
+
+        controller.undo();
+        assert_eq!(layout(&controller.state), before);
+    }
+
+    #[test]
+    fn check_finished_game() {
+        let mut controller = Controller::headless(Config::new());
+        let messages = record_messages(&mut controller);
+
+        // play until the game is over
+        while !controller.is_finished() {
+            match controller.rules.hint(&controller.state.cards(), HintMode::Random) {
+                Some(_) => {
+                    let cards = controller.state.cards();
+                    controller.selected = controller.rules.find_all(&cards).remove(0);
+                    controller.check_for_set();
+                }
+                None => controller.deal_more_cards(),
+            }
+        }
+        assert!(messages.borrow().is_empty());
+
+        assert_eq!(controller.phase(), Phase::Finished);
+        controller.deal_more_cards();
+        assert_eq!(*messages.borrow(), vec![controller.result_summary()]);
+        assert_eq!(controller.scores.load().best_times().len(), 1);
+
+        // selection is disabled (the board may have been cleared entirely)
+        if let Some(&card) = controller.state.cards().first() {
+            controller.toggle_selected(card);
+            assert!(controller.selected.is_empty());
+        }
+
+        // undo rewinds back into play
+        controller.undo();
+        assert_eq!(controller.phase(), Phase::Playing);
+        let card = controller.state.cards()[0];
+        controller.toggle_selected(card);
+        assert_eq!(controller.selected, vec![card]);
+    }
+
+    /// Takes sets (dealing when stuck) until the game is over.
+    fn play_out(controller: &mut Controller) {
+        while !controller.is_finished() {
+            match controller.rules.find_all(&controller.state.cards()).pop() {
+                Some(set) => {
+                    controller.selected = set;
+                    controller.check_for_set();
+                }
+                None => controller.deal_more_cards(),
+            }
+        }
+    }
+
+    #[test]
+    fn check_best_times_need_a_whole_game() {
+        // a quick game only plays part of the deck
+        let config = Config { quick_game: Some(30), ..Config::new() };
+        let mut controller = Controller::headless(config);
+        play_out(&mut controller);
+        assert!(controller.scores.load().best_times().is_empty());
+
+        // a resumed game's clock restarts from zero
+        let mut controller = Controller::headless(Config::new());
+        controller.selected = ensure_set(&mut controller);
+        controller.check_for_set();
+        let mut resumed = Controller::headless(Config::new());
+        resumed.load_session(controller.session().unwrap());
+        play_out(&mut resumed);
+        assert!(resumed.scores.load().best_times().is_empty());
+
+        // without the guarantee, a full table can be stuck over a stock
+        let config = Config { guarantee_sets: false, ..Config::new() };
+        let cap: Vec<Card> = [0, 1, 3, 4, 9, 13, 14, 15, 19, 34, 38, 39, 40, 44, 49, 50, 52, 53]
+            .iter()
+            .map(|&ix| Card::new(ix))
+            .collect();
+        let mut controller = Controller::headless(config);
+        controller.new_game_with_state(Some(GameState::with_opening(config, &cap)));
+        assert!(controller.is_finished());
+        controller.check_for_finish();
+        assert!(controller.scores.load().best_times().is_empty());
+    }
+
+    #[test]
+    fn check_session_round_trip() {
+        let mut controller = Controller::headless(Config::new());
+        assert!(controller.session().is_none());
+
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+
+        let session = controller.session().unwrap();
+        let serialized = serde_yaml::to_string(&session).unwrap();
+        let restored: Session = serde_yaml::from_str(&serialized).unwrap();
+
+        // nothing from the game in progress carries over
+        let mut resumed = Controller::headless(Config::new());
+        resumed.start_challenge();
+        resumed.set_status(Some("stale".to_string()));
+        resumed.load_session(restored);
+        assert!(resumed.challenge.is_none());
+        assert_eq!(resumed.status(), None);
+
+        assert_eq!(resumed.state.cards(), controller.state.cards());
+        assert_eq!(resumed.state.score, controller.state.score);
+        assert_eq!(resumed.state.deck.remainder(), controller.state.deck.remainder());
+        assert_eq!(resumed.undo_action_name(), controller.undo_action_name());
+
+        // the resumed game can be rewound like the original
+        resumed.undo();
+        controller.undo();
+        assert_eq!(resumed.state.cards(), controller.state.cards());
+    }
+
+    #[test]
+    fn check_save_game_file() {
+        let mut controller = Controller::headless(Config::new());
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+
+        let path = env::temp_dir().join(format!("marmoset-{}.yml", std::process::id()));
+        controller.save_game(&path).unwrap();
+        let mut resumed = Controller::headless(Config::new());
+        let loaded = resumed.load_game(&path);
+        std::fs::remove_file(&path).unwrap();
+        loaded.unwrap();
+
+        assert_eq!(resumed.state.cards(), controller.state.cards());
+        assert_eq!(resumed.state.score, controller.state.score);
+
+        // the sloppy layout comes back exactly as it was
+        let angles = |controller: &Controller| -> Vec<f64> {
+            controller.state.tableau.iter()
+                .filter_map(|cell| match *cell {
+                    Cell::Card(data) => Some(data.angle),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(angles(&resumed), angles(&controller));
+
+        // a missing file leaves the game alone
+        assert!(resumed.load_game(&path).is_err());
+        assert_eq!(resumed.state.score, controller.state.score);
+    }
+
+    #[test]
+    fn check_challenge_stays_playable() {
+        let mut controller = Controller::headless(Config::new());
+        controller.start_challenge();
+
+        // play well past the size of a single deck
+        for round in 1..=60 {
+            let cards = controller.state.cards();
+            assert!(!controller.rules.stuck(&cards));
+
+            controller.selected = controller.rules.find_all(&cards).remove(0);
+            controller.check_for_set();
+            assert_eq!(controller.state.score, round);
+            assert_eq!(controller.phase(), Phase::Playing);
+        }
+
+        // challenges can't be undone
+        assert!(!controller.can_undo());
+    }
+
+    #[test]
+    fn check_game_clock() {
+        let mut controller = Controller::headless(Config::new());
+        assert!(controller.game_clock.is_running());
+
+        while !controller.is_finished() {
+            let cards = controller.state.cards();
+            match controller.rules.find_all(&cards).pop() {
+                Some(set) => {
+                    controller.selected = set;
+                    controller.check_for_set();
+                }
+                None => {
+                    controller.deal_more_cards();
+                }
+            }
+        }
+
+        // the clock stops with the game
+        assert!(!controller.game_clock.is_running());
+        let time = controller.game_time();
+        assert_eq!(controller.game_time(), time);
+
+        // and continues if an undo resumes it
+        controller.undo();
+        assert!(controller.game_clock.is_running());
+        assert!(controller.game_time() >= time);
+
+        controller.new_game();
+        assert!(controller.game_clock.is_running());
+        assert!(controller.game_time() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn check_expired_challenge() {
+        let mut controller = Controller::headless(Config::new());
+        controller.start_challenge();
+        controller.state.score = 4;
+        controller.challenge = Some(Challenge {
+            countdown: Countdown::start(Duration::from_secs(0)),
+            previous_best: Some(6),
+        });
+
+        assert!(controller.is_finished());
+        assert_eq!(
+            controller.result_summary(),
+            "You found 4 Sets in 0 seconds. Your best is 6."
+        );
+
+        // a new game leaves challenge mode behind
+        controller.new_game();
+        assert_eq!(controller.phase(), Phase::Playing);
+    }
+
+    #[test]
+    fn check_share_code_recreates_board() {
+        let mut controller = Controller::headless(Config::new());
+        let code = controller.board_share_code();
+        let cards = controller.state.cards();
+
+        // play a bit, then start over from the code
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+        controller.load_share_code(&code).unwrap();
+
+        assert_eq!(controller.state.cards(), cards);
+        assert_eq!(controller.state.score, 0);
+        assert_eq!(controller.board_share_code(), code);
+
+        assert!(controller.load_share_code("not a code").is_err());
+        assert_eq!(controller.state.cards(), cards);
+    }
+
+    #[test]
+    fn check_board_snapshot() {
+        let mut controller = Controller::headless(Config::new());
+        let selected = controller.state.cards()[1];
+        controller.toggle_selected(selected);
+
+        let snapshot = controller.board_snapshot();
+        assert_eq!(snapshot.len(), controller.state.card_count());
+
+        for view in &snapshot {
+            assert_eq!(controller.state.index_of_card(view.card), Some(view.index));
+            assert_eq!(controller.state.card_for_key(view.hotkey), Some(view.card));
+            assert_eq!(view.selected, view.card == selected);
+        }
+    }
+
+    #[test]
+    fn check_degenerate_layout() {
+        let mut controller = Controller::headless(Config::new());
+
+        for &(w, h) in &[(0, 0), (1, 1), (0, 700), (1200, 0), (-5, -5)] {
+            controller.layout_for_size(w, h);
+
+            let rects = controller.cell_rects.iter().chain(Some(&controller.tableau_bounds));
+            for rect in rects {
+                let values = [rect.x(), rect.y(), rect.width(), rect.height()];
+                assert!(values.iter().all(|v| v.is_finite()), "{:?} at {}x{}", values, w, h);
+                assert!(rect.width() >= 0. && rect.height() >= 0.);
+            }
+
+            // hit testing must not panic either
+            controller.card_for_point(0., 0.);
+        }
+    }
+
+    #[test]
+    fn check_on_pace() {
+        let secs = Duration::from_secs;
+        let limit = secs(60);
+
+        // 5 sets in 20 seconds projects to 15
+        assert!(on_pace(5, Some(14), secs(20), limit));
+        assert!(!on_pace(5, Some(15), secs(20), limit));
+        assert!(on_pace(1, None, secs(20), limit));
+
+        // no verdict without any sets or during the warmup
+        assert!(!on_pace(0, None, secs(20), limit));
+        assert!(!on_pace(2, Some(1), secs(1), limit));
+
+        // not playing a challenge
+        let controller = Controller::headless(Config::new());
+        assert!(!controller.on_pace_for_best());
+    }
+
+    #[test]
+    fn check_would_be_valid() {
+        let controller = Controller::headless(Config::new());
+        let cards = controller.state.cards();
+
+        if let Some(set) = controller.rules.find_all(&cards).first() {
+            assert!(controller.would_be_valid(set));
+            assert!(!controller.would_be_valid(&set[..2]));
+        }
+
+        assert!(!controller.would_be_valid(&cards));
+        assert!(controller.selected.is_empty());
+    }
+
+    #[test]
+    fn check_max_undo_depth() {
+        let mut controller = Controller::headless(Config::new());
+        let initial = controller.state.deck.remaining_cards();
+
+        for _ in 0..5 {
+            controller.register_undo("Deal More Cards");
+            controller.state.deal(3);
+        }
+
+        controller.undo();
+        controller.undo();
+        assert_eq!(controller.undo_stack.len(), 3);
+        assert_eq!(controller.redo_stack.len(), 2);
+
+        // trims the oldest undo and redo entries, but keeps the start
+        controller.set_max_undo_depth(Some(1));
+        assert_eq!(controller.undo_stack.len(), 2);
+        assert_eq!(controller.redo_stack.len(), 1);
+
+        let fired = Rc::new(std::cell::Cell::new(false));
+        let observed = fired.clone();
+        controller.add_undo_observer(move |_| observed.set(true));
+        controller.set_max_undo_depth(Some(0));
+        assert!(fired.get());
+        assert_eq!(controller.config.max_undo_depth, Some(1));
+
+        controller.register_undo("Deal More Cards");
+        controller.state.deal(3);
+        assert_eq!(controller.undo_stack.len(), 2);
+        assert!(!controller.can_redo());
+
+        controller.restart();
+        assert_eq!(controller.state.deck.remaining_cards(), initial);
+
+        // unlimited keeps everything
+        controller.set_max_undo_depth(None);
+        for _ in 0..5 {
+            controller.register_undo("Deal More Cards");
+        }
+        assert_eq!(controller.undo_stack.len(), 5);
+    }
+
+    #[test]
+    fn check_explain_sets() {
+        let mut config = Config::new();
+        config.explain_sets = true;
+        let mut controller = Controller::headless(config);
+
+        let cards = controller.state.cards();
+        let set = match controller.rules.find_all(&cards).into_iter().next() {
+            Some(set) => set,
+            None => return,
+        };
+
+        // the set stays on the board while it's explained
+        controller.selected = set.clone();
+        controller.check_for_set();
+        assert!(controller.explanation.is_some());
+        assert_eq!(controller.state.cards(), cards);
+        assert_eq!(controller.selected, set);
+
+        // further selections wait for the removal
+        let other = *cards.iter().find(|card| !set.contains(card)).unwrap();
+        controller.toggle_selected(other);
+        assert_eq!(controller.selected, set);
+
+        // undo restores the board from before the set was found
+        controller.undo();
+        assert!(controller.explanation.is_none());
+        assert_eq!(controller.state.cards(), cards);
+        assert_eq!(controller.state.score, 0);
+        assert!(controller.selected.is_empty());
+
+        controller.selected = set.clone();
+        controller.check_for_set();
+        controller.finish_explanation();
+        assert_eq!(controller.state.score, 1);
+        assert!(set.iter().all(|&card| controller.state.index_of_card(card).is_none()));
+    }
+
+    #[test]
+    fn check_badge_labels() {
+        let labels = config::BadgeLabels::default();
+        assert_eq!(labels.cards_left(1), "card left");
+        assert_eq!(labels.cards_left(0), "cards left");
+        assert_eq!(labels.seconds_left(1), "second left");
+        assert_eq!(labels.seconds_left(30), "seconds left");
+    }
+
+    #[test]
+    fn check_show_solution() {
+        let mut config = Config::new();
+        config.variant = config::Variant::SuperSet;
+        let mut controller = Controller::headless(config);
+        let messages = record_messages(&mut controller);
+        controller.show_solution();
+        assert_eq!(messages.borrow().len(), 1);
+
+        let mut controller = Controller::headless(Config::new());
+        let messages = record_messages(&mut controller);
+        controller.show_solution();
+        if !messages.borrow().is_empty() {
+            // not every game can be cleared
+            assert!(controller.solution.is_none());
+            return;
+        }
+
+        // drive the playback by hand, since nothing is scheduled headless
+        while let Some((started, _)) = controller.solution {
+            controller.solution_step(started);
+        }
+
+        assert_eq!(controller.state.card_count(), 0);
+        assert!(controller.state.deck.is_empty());
+
+        // the playback's time isn't the player's
+        assert!(controller.is_finished());
+        assert!(controller.scores.load().best_times().is_empty());
+    }
+
+    /// Plays back solutions to a few seeded games, checking that each
+    /// one clears the board. Returns the games solved and the deals
+    /// made along the way.
+    fn play_solutions(config: Config) -> (usize, usize) {
+        let mut controller = Controller::headless(config);
+        let messages = record_messages(&mut controller);
+        let (mut solved, mut deals) = (0, 0);
+
+        for seed in 0..10 {
+            controller.new_game_with_state(Some(GameState::with_config_seeded(config, seed)));
+            controller.show_solution();
+            if messages.borrow_mut().drain(..).next().is_some() {
+                continue;
+            }
+
+            while let Some((started, _)) = controller.solution {
+                controller.solution_step(started);
+            }
+
+            assert_eq!(controller.state.card_count(), 0);
+            assert!(controller.state.deck.is_empty());
+            solved += 1;
+            deals += controller.stats.deals;
+        }
+
+        (solved, deals)
+    }
+
+    #[test]
+    fn check_show_solution_with_larger_batches() {
+        // the whole solution plays out, batches included
+        let config = Config { deal_batch: Some(6), ..Config::new() };
+        let (_, deals) = play_solutions(config);
+        assert!(deals > 0);
+    }
+
+    #[test]
+    fn check_show_solution_with_replenish_policies() {
+        for &replenish_policy in &[ReplenishPolicy::Never, ReplenishPolicy::AlwaysToInitial] {
+            let config = Config { replenish_policy, ..Config::new() };
+            let (solved, _) = play_solutions(config);
+            assert!(solved > 0);
+        }
+    }
+
+    #[test]
+    fn check_deal_more_tops_up() {
+        let config = Config { replenish_policy: ReplenishPolicy::Never, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        let messages = record_messages(&mut controller);
+        let initial = controller.rules.initial_deal_size();
+
+        // without replenishing, taking a set shrinks the board
+        while controller.state.card_count() >= initial || controller.rules.stuck(&controller.state.cards()) {
+            for card in ensure_set(&mut controller) {
+                controller.toggle_selected(card);
+            }
+            controller.check_for_set();
+        }
+
+        controller.deal_more_cards();
+        assert!(messages.borrow().is_empty());
+        assert_eq!(controller.state.card_count(), initial);
+
+        // once it's back to size, a board with a set is left alone
+        if !controller.rules.stuck(&controller.state.cards()) {
+            controller.deal_more_cards();
+            assert_eq!(messages.borrow().len(), 1);
+            assert_eq!(controller.state.card_count(), initial);
+        }
+    }
+
+    #[test]
+    fn check_simultaneous_toggles() {
+        let mut controller = Controller::headless(Config::new());
+        let cards = controller.state.cards();
+        let (card, other) = (cards[0], cards[1]);
+
+        // a click and a hotkey at once select the card a single time
+        controller.toggle_from(card, Input::Mouse);
+        controller.toggle_from(card, Input::Key);
+        assert_eq!(controller.selected, [card]);
+
+        // the same goes the other way around
+        controller.toggle_from(other, Input::Key);
+        controller.toggle_from(other, Input::Mouse);
+        assert_eq!(controller.selected, [card, other]);
+
+        // repeats through the same path still toggle
+        controller.toggle_from(other, Input::Mouse);
+        assert_eq!(controller.selected, [card]);
+
+        // as does the other path once the moment has passed
+        let earlier = Instant::now() - DOUBLE_TOGGLE_WINDOW;
+        controller.last_toggle = Some((card, Input::Key, earlier));
+        controller.toggle_from(card, Input::Mouse);
+        assert!(controller.selected.is_empty());
+    }
+
+    #[test]
+    fn check_escalating_hints() {
+        let mut config = Config::new();
+        config.escalating_hints = true;
+        let mut controller = Controller::headless(config);
+        controller.new_game_with_state(Some(GameState::with_config_seeded(config, 7)));
+        ensure_set(&mut controller);
+
+        // each hint reveals one more card of the same set
+        let mut revealed = vec![];
+        for count in 1..=3 {
+            controller.show_hint();
+            assert_eq!(controller.selected.len(), count);
+            assert!(controller.selected.starts_with(&revealed));
+            revealed = controller.selected.clone();
+        }
+        assert!(controller.would_be_valid(&revealed));
+
+        // and stops once the whole set is showing
+        controller.show_hint();
+        assert_eq!(controller.selected, revealed);
+
+        // the whole sequence counts as one hint
+        assert_eq!(controller.assists.hints, 1);
+        assert_eq!(controller.stats.hints, 1);
+
+        // a new board starts over
+        controller.check_for_set();
+        ensure_set(&mut controller);
+        controller.show_hint();
+        assert_eq!(controller.selected.len(), 1);
+        assert_eq!(controller.assists.hints, 2);
+    }
+
+    #[test]
+    fn check_force_deal() {
+        let mut controller = Controller::headless(Config::new());
+        let messages = record_messages(&mut controller);
+        let capacity = controller.rules.deal_order().len();
+
+        // deals whether or not there's a set on the board
+        while controller.state.card_count() < capacity {
+            let before = controller.state.card_count();
+            controller.force_deal();
+            assert_eq!(controller.state.card_count(), before + 3);
+        }
+        assert_eq!(controller.undo_stack.len(), 2);
+        assert!(messages.borrow().is_empty());
+
+        // but only while there's room
+        let remainder = controller.state.deck.remainder();
+        controller.force_deal();
+        assert_eq!(*messages.borrow(), vec!["There's no room for more cards.".to_string()]);
+        assert_eq!(controller.state.deck.remainder(), remainder);
+
+        controller.undo();
+        assert_eq!(controller.state.card_count(), capacity - 3);
+    }
+
+    #[test]
+    fn check_deal_batch() {
+        let capacity = Config::new().rules().deal_order().len();
+
+        // a whole batch is dealt at once
+        let config = Config { deal_batch: Some(6), ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.force_deal();
+        assert_eq!(controller.state.card_count(), capacity);
+        assert_eq!(controller.undo_stack.len(), 1);
+
+        // odd sizes are rounded down to whole sets
+        let config = Config { deal_batch: Some(5), ..Config::new() };
+        let controller = Controller::headless(config);
+        assert_eq!(controller.deal_batch(), 3);
+
+        // and batches never overflow the tableau
+        let config = Config { deal_batch: Some(9), ..Config::new() };
+        let mut controller = Controller::headless(config);
+        assert_eq!(controller.deal_batch(), 6);
+        controller.force_deal();
+        assert_eq!(controller.state.card_count(), capacity);
+    }
+
+    #[test]
+    fn check_reaction_times() {
+        let mut controller = Controller::headless(Config::new());
+        assert_eq!(controller.average_reaction(), None);
+
+        let found = Duration::from_secs(4);
+        for _ in 0..2 {
+            // pretend each board sat unsolved for a while
+            let set = ensure_set(&mut controller);
+            controller.reactions.solvable_since = Some(Instant::now() - found);
+            controller.selected = set;
+            controller.check_for_set();
+        }
+
+        let average = controller.average_reaction().unwrap();
+        assert!(average >= found && average < found + Duration::from_secs(1));
+        assert_eq!(controller.reactions.samples.len(), 2);
+
+        // undone sets no longer count, and redone ones have no time
+        while controller.state.score > 1 {
+            controller.undo();
+        }
+        assert_eq!(controller.reactions.samples.len(), 1);
+        controller.redo();
+        assert_eq!(controller.state.score, 2);
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+        assert_eq!(controller.reactions.samples.len(), 3);
+        assert_eq!(controller.reactions.samples[1], None);
+
+        // a new game starts over
+        controller.new_game();
+        assert_eq!(controller.average_reaction(), None);
+    }
+
+    #[test]
+    fn check_efficiency() {
+        let mut config = Config::new();
+        config.show_efficiency = true;
+        let mut controller = Controller::headless(config);
+        assert_eq!(controller.efficiency(), None);
+
+        // one set found unaided
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+        assert_eq!(controller.efficiency(), Some(1.0));
+
+        // one found with a hint, after dealing early
+        ensure_set(&mut controller);
+        controller.force_deal();
+        controller.show_hint();
+        assert_eq!(controller.assists.hints, 1);
+        assert_eq!(controller.assists.early_deals, 1);
+        let third = (controller.selected[0], controller.selected[1]).complete_set();
+        controller.selected.push(third);
+        controller.check_for_set();
+        assert_eq!(controller.state.score, 2);
+        assert_eq!(controller.efficiency(), Some(0.5));
+        let summary = controller.result_summary();
+        assert!(summary.ends_with("Your efficiency was 50% (2 moves, 1 hint, and 1 early deal)."));
+
+        // a new game starts over
+        controller.new_game();
+        assert_eq!(controller.efficiency(), None);
+    }
+
+    #[test]
+    fn check_invalid_flash() {
+        let mut controller = Controller::headless(Config::new());
+        let set = ensure_set(&mut controller);
+        let miss = controller.state.cards().into_iter()
+            .find(|card| !set.contains(card))
+            .unwrap();
+
+        // the whole selection flashes, but the last card is dropped right away
+        controller.selected = vec![set[0], set[1], miss];
+        controller.check_for_set();
+        assert_eq!(controller.selected, vec![set[0], set[1]]);
+        assert_eq!(controller.flashed_cards(), &[set[0], set[1], miss]);
+        assert_eq!(controller.board().flashed, &[set[0], set[1], miss]);
+
+        // a stale timeout leaves a newer flash alone
+        let (start, _) = controller.invalid_flash.clone().unwrap();
+        controller.end_invalid_flash(start - Duration::from_millis(1));
+        assert!(controller.invalid_flash.is_some());
+        controller.end_invalid_flash(start);
+        assert!(controller.flashed_cards().is_empty());
+
+        // clicking again cancels the flash and carries on as usual
+        controller.selected.push(miss);
+        controller.check_for_set();
+        controller.toggle_selected(set[2]);
+        assert!(controller.flashed_cards().is_empty());
+        assert_eq!(controller.selected, vec![set[0], set[1], set[2]]);
+    }
+
+    #[test]
+    fn check_hint_level() {
+        let config = Config { hint_level: HintLevel::Full, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        ensure_set(&mut controller);
+
+        // the whole set is selected, ready to be taken
+        controller.show_hint();
+        assert_eq!(controller.selected.len(), 3);
+        controller.check_for_set();
+        assert_eq!(controller.state.score, 1);
+
+        controller.config.hint_level = HintLevel::Single;
+        ensure_set(&mut controller);
+        controller.show_hint();
+        assert_eq!(controller.selected.len(), 1);
+    }
+
+    #[test]
+    fn check_hint_survives_key_release() {
+        let config = Config { hint_level: HintLevel::Full, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        ensure_set(&mut controller);
+
+        // releasing the hint accelerator doesn't take the hinted set
+        controller.show_hint();
+        controller.key_released();
+        assert_eq!(controller.selected.len(), 3);
+        assert_eq!(controller.state.score, 0);
+
+        // nor does the last step of an escalating hint
+        controller.config.escalating_hints = true;
+        controller.deselect_all();
+        for _ in 0..3 {
+            controller.show_hint();
+            controller.key_released();
+        }
+        assert_eq!(controller.selected.len(), 3);
+        assert_eq!(controller.state.score, 0);
+
+        // but completing the set with a hotkey does
+        let card = controller.selected[2];
+        controller.toggle_selected(card);
+        controller.toggle_from(card, Input::Key);
+        controller.key_released();
+        assert_eq!(controller.state.score, 1);
+    }
+
+    #[test]
+    fn check_auto_deal() {
+        let config = Config { auto_deal: true, ..Config::new() };
+        let mut controller = Controller::headless(config);
+
+        // the player never has to ask for more cards
+        let mut before = vec![];
+        let mut taken = 0;
+        while !controller.is_finished() {
+            before = controller.state.cards();
+            assert!(!controller.rules.stuck(&before));
+            controller.selected = controller.rules.find_all(&before).remove(0);
+            controller.check_for_set();
+            taken += 1;
+
+            // any deals it needed are part of the take
+            assert_eq!(controller.undo_stack.len(), taken);
+        }
+        assert!(controller.state.deck.is_empty());
+        assert_eq!(controller.stats().deals, 0);
+
+        // so one undo puts back the board from before the last take
+        controller.undo();
+        assert_eq!(controller.state.cards(), before);
+    }
+
+    #[test]
+    fn check_game_stats() {
+        let mut controller = Controller::headless(Config::new());
+        assert_eq!(controller.stats(), GameStats::default());
+
+        let set = ensure_set(&mut controller);
+        let deals = controller.stats().deals;
+
+        // a miss, then the set itself
+        controller.selected = vec![set[0], set[1]];
+        let miss = controller.state.cards().into_iter()
+            .find(|card| !set.contains(card))
+            .unwrap();
+        controller.selected.push(miss);
+        controller.check_for_set();
+        controller.selected = set;
+        controller.check_for_set();
+
+        // a hint, and cards dealt on request
+        ensure_set(&mut controller);
+        controller.show_hint();
+        controller.force_deal();
+
+        let stats = controller.stats();
+        assert_eq!(stats.sets_found, 1);
+        assert_eq!(stats.invalid_selections, 1);
+        assert_eq!(stats.hints, 1);
+        assert!(stats.deals > deals);
+
+        // undo leaves the tallies alone, a new game clears them
+        controller.undo();
+        assert_eq!(controller.stats(), stats);
+        controller.new_game();
+        assert_eq!(controller.stats(), GameStats::default());
+    }
+
+    #[test]
+    fn check_available_set_count() {
+        let mut controller = Controller::headless(Config::new());
+        let count = |c: &Controller| c.rules.count_sets(&c.state.cards());
+        assert_eq!(controller.available_set_count(), count(&controller));
+
+        let notified = Rc::new(RefCell::new(0));
+        let observed = notified.clone();
+        controller.add_event_observer(move |_, event| {
+            if event == GameEvent::BoardChanged {
+                *observed.borrow_mut() += 1;
+            }
+        });
+
+        // taking a set, dealing, and undoing each recount and notify
+        let set = ensure_set(&mut controller);
+        let mut seen = *notified.borrow();
+        let mut check_notified = |controller: &Controller| {
+            assert!(*notified.borrow() > seen);
+            assert_eq!(controller.available_set_count(), count(controller));
+            seen = *notified.borrow();
+        };
+
+        controller.selected = set;
+        controller.check_for_set();
+        check_notified(&controller);
+        controller.force_deal();
+        check_notified(&controller);
+        controller.undo();
+        check_notified(&controller);
+    }
+
+    #[test]
+    fn check_load_opening() {
+        let mut controller = Controller::headless(Config::new());
+        let mut opening = controller.state.deck.remaining_cards();
+        opening.truncate(9);
+
+        controller.load_opening(&opening).unwrap();
+        let mut board = controller.state.cards();
+        board.sort();
+        let mut expected = opening.clone();
+        expected.sort();
+        assert_eq!(board, expected);
+        assert_eq!(controller.state.score, 0);
+
+        // the deck is exactly the complement
+        let mut all = controller.state.deck.remaining_cards();
+        all.extend(board);
+        all.sort();
+        assert_eq!(all, core::deck::cards());
+
+        // bad openings leave the game alone
+        let capacity = controller.rules.deal_order().len();
+        let too_many = controller.state.deck.remaining_cards()[..capacity + 1].to_vec();
+        assert_eq!(controller.load_opening(&too_many), Err(OpeningError::Size(capacity + 1)));
+        assert_eq!(controller.load_opening(&opening[..2]), Err(OpeningError::Size(2)));
+        let repeated = [opening[0], opening[1], opening[0]];
+        assert_eq!(controller.load_opening(&repeated), Err(OpeningError::Repeated(opening[0])));
+        assert_eq!(controller.state.card_count(), 9);
+    }
+
+    #[test]
+    fn check_first_lesson() {
+        let config = Config { variant: config::Variant::SuperSet, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.load_first_lesson();
+
+        assert_eq!(controller.config.variant, config::Variant::Set);
+        let cards = controller.state.cards();
+        assert_eq!(cards.len(), FIRST_LESSON.len());
+        assert_eq!(controller.rules.count_sets(&cards), 1);
+    }
+
+    #[test]
+    fn check_status_line() {
+        let mut controller = Controller::headless(Config::new());
+        let changes = Rc::new(std::cell::Cell::new(0));
+        let counter = changes.clone();
+        controller.add_status_observer(move |_| counter.set(counter.get() + 1));
+
+        let message = Some("There are 2 Sets available.".to_string());
+        controller.set_status(message.clone());
+        assert_eq!(controller.status(), message.as_deref());
+        assert_eq!(changes.get(), 1);
+
+        // repeating a message isn't a change
+        controller.set_status(message);
+        assert_eq!(changes.get(), 1);
+
+        // messages are stale once the board changes
+        let set = ensure_set(&mut controller);
+        controller.selected = set;
+        controller.check_for_set();
+        assert_eq!(controller.status(), None);
+        assert_eq!(changes.get(), 2);
+    }
+
+    #[test]
+    fn check_hit_padding() {
+        let config = Config { tidy_layout: true, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.layout_for_size(1200, 700);
+
+        // the first deal always fills these neighboring cells
+        let (left, right) = (controller.cell_rects[10], controller.cell_rects[11]);
+        let cards = (controller.state.tableau[10].card(), controller.state.tableau[11].card());
+        let gap = right.x() - left.max_x();
+        let y = left.center().1;
+        let near_left = left.max_x() + gap * 0.25;
+        let near_right = right.x() - gap * 0.25;
+
+        // the gap between cards is dead space by default
+        assert_eq!(controller.card_for_point(near_left, y), None);
+        assert_eq!(controller.card_for_point(near_right, y), None);
+
+        // padded regions overlap in the gap, so the nearer card wins
+        controller.config.hit_padding = 0.2;
+        assert_eq!(controller.card_for_point(near_left, y), cards.0);
+        assert_eq!(controller.card_for_point(near_right, y), cards.1);
+
+        // points on a card still hit that card
+        let (cx, cy) = left.center();
+        assert_eq!(controller.card_for_point(cx, cy), cards.0);
+
+        // but the padding only reaches so far
+        let far = left.x() - left.width() * 0.5;
+        assert_eq!(controller.card_for_point(far, y), None);
+    }
+
+    #[test]
+    fn check_keyboard_focus() {
+        use crate::cell::RenderData;
+
+        let mut controller = Controller::headless(Config::new());
+        let cards = controller.state.deck.remaining_cards();
+        let tableau = &mut controller.state.tableau;
+        for cell in tableau.iter_mut().filter(|cell| cell.card().is_some()) {
+            *cell = Cell::Placeholder;
+        }
+        for (&ix, &card) in [1, 3, 18].iter().zip(cards.iter()) {
+            tableau[ix] = Cell::Card(RenderData::with_card_and_hotkey(card, 'a'));
+        }
+
+        // the first move lands on the first card
+        controller.move_focus(Direction::Down);
+        assert_eq!(controller.focused_cell, Some(1));
+        assert_eq!(controller.board().focused, Some(1));
+
+        // empty cells and badges are skipped, and the edges clamp
+        let moves = [
+            (Direction::Up, 1),
+            (Direction::Right, 3),
+            (Direction::Right, 3),
+            (Direction::Down, 18),
+            (Direction::Down, 18),
+            (Direction::Left, 18),
+        ];
+        for &(direction, expected) in &moves {
+            controller.move_focus(direction);
+            assert_eq!(controller.focused_cell, Some(expected), "{:?}", direction);
+        }
+
+        // the focused card toggles
+        controller.toggle_focused();
+        assert_eq!(controller.selected, vec![cards[2]]);
+        controller.toggle_focused();
+        assert!(controller.selected.is_empty());
+    }
+
+    #[test]
+    fn check_click_empty_deselects() {
+        let config = Config { tidy_layout: true, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.layout_for_size(1200, 700);
+
+        // the gap between two dealt cells hits nothing
+        let (left, right) = (controller.cell_rects[10], controller.cell_rects[11]);
+        let (x, y) = ((left.max_x() + right.x()) / 2., left.center().1);
+        let (cx, cy) = left.center();
+        assert_eq!(controller.card_for_point(x, y), None);
+
+        // by default, a partial selection survives a missed click
+        controller.click_at(cx, cy);
+        assert_eq!(controller.selected.len(), 1);
+        controller.click_at(x, y);
+        assert_eq!(controller.selected.len(), 1);
+
+        // with the option set, the miss clears it
+        controller.config.click_empty_deselects = true;
+        controller.click_at(x, y);
+        assert!(controller.selected.is_empty());
+
+        // missing with nothing selected changes nothing
+        controller.click_at(x, y);
+        assert!(controller.selected.is_empty());
+    }
+
+    #[test]
+    fn check_render_image_matches_the_view() {
+        let config = Config { show_grid: true, render_quality: RenderQuality::High, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.debug_overlay = false;
+        controller.layout_for_size(600, 350);
+        controller.exploded_cell = controller.state.cards_with_index().map(|(ix, _)| ix).next();
+        let selected = controller.state.cards()[2];
+        controller.toggle_selected(selected);
+        // keep the timer from ticking over between the two renders
+        controller.game_clock.pause();
+
+        let mut offscreen = controller.render_image().unwrap();
+
+        // draw the way the widget does
+        let (width, height) = controller.view_size;
+        let mut view = ImageSurface::create(Format::ARgb32, width, height).unwrap();
+        let ctx = Context::new(&view).unwrap();
+        controller.draw_view(&ctx);
+        drop(ctx);
+
+        // compare pixel for pixel
+        let offscreen = offscreen.data().unwrap().to_vec();
+        let view = view.data().unwrap().to_vec();
+        assert!(offscreen == view, "off-screen rendering differs from the view");
+    }
+
+    #[test]
+    fn check_deck_tooltip() {
+        let mut controller = Controller::headless(Config::new());
+        controller.layout_for_size(1200, 700);
+
+        // the deck badge is not a card, so hovering or clicking it selects nothing
+        let (x, y) = controller.cell_rects[0].center();
+        assert!(controller.deck_badge_contains(x, y));
+        assert_eq!(controller.card_for_point(x, y), None);
+        controller.click_at(x, y);
+        assert!(controller.selected.is_empty());
+
+        let (cx, cy) = controller.cell_rects[10].center();
+        assert!(!controller.deck_badge_contains(cx, cy));
+
+        let sets = controller.rules.count_sets(&controller.state.cards());
+        let expected = format!("69 cards left in the deck\n12 cards in play\n{} available", quantity(sets, "Set"));
+        assert_eq!(controller.deck_tooltip(), expected);
+
+        // the tooltip follows the board
+        ensure_set(&mut controller);
+        controller.force_deal();
+        let remainder = controller.state.deck.remainder();
+        assert!(controller.deck_tooltip().starts_with(&format!("{} cards left", remainder)));
+    }
+
+    #[test]
+    fn check_deck_button() {
+        let config = Config { tidy_layout: true, ..Config::new() };
+        let mut controller = Controller::headless(config);
+        controller.layout_for_size(1200, 700);
+        ensure_set(&mut controller);
+
+        let messages = record_messages(&mut controller);
+
+        let deck = controller.cell_rects[controller.deck_cell().unwrap()];
+        let (x, y) = deck.center();
+        let cards = controller.state.card_count();
+
+        // the badge is static by default
+        controller.click_at(x, y);
+        assert!(messages.borrow().is_empty());
+
+        // as a button, it asks for more cards (and there's a set, so none come)
+        controller.set_deck_button(true);
+        controller.click_at(x, y);
+        assert_eq!(controller.state.card_count(), cards);
+        assert_eq!(messages.borrow().len(), 1);
+        assert!(messages.borrow()[0].ends_with("available."));
+
+        // cards still win where a padded card overlaps the badge
+        controller.config.hit_padding = 0.3;
+        let (x, y) = (x, deck.max_y() - 2.);
+        assert!(controller.deck_badge_contains(x, y));
+        let card = controller.card_for_point(x, y).unwrap();
+        controller.click_at(x, y);
+        assert_eq!(controller.selected, [card]);
+        assert_eq!(messages.borrow().len(), 1);
+    }
+
+    #[test]
+    fn check_hover_styles() {
+        let mut controller = Controller::headless(Config::new());
+        controller.layout_for_size(600, 350);
+        let hovered = controller.state.cards_with_index().map(|(ix, _)| ix).next();
+        let pixels = |controller: &Controller| controller.render_image().unwrap().data().unwrap().to_vec();
+        let plain = pixels(&controller);
+
+        controller.exploded_cell = hovered;
+        let mut images = vec![];
+        for &style in &[HoverStyle::Explode, HoverStyle::Highlight, HoverStyle::None] {
+            controller.config.hover_style = style;
+            images.push(pixels(&controller));
+        }
+
+        // each style looks different, and None leaves the card as it was
+        assert!(images[0] != images[1]);
+        assert!(images[0] != plain && images[1] != plain);
+        assert!(images[2] == plain);
+    }
+
+    /// Config saves made so far by an in-memory config.
+    fn saves(config: &Config) -> usize {
+        match config.saving.store {
+            config::ConfigStore::Memory { writes } => writes,
+            config::ConfigStore::File => panic!("the config would have been written to a file"),
+        }
+    }
+
+    #[test]
+    fn check_apply_theme() {
+        let mut controller = Controller::headless(Config::new());
+        let theme = Theme {
+            color_scheme: ColorScheme::Classic,
+            background: (0.2, 0.2, 0.25),
+            sloppiness: 0.0,
+            card_shadows: true,
+            hover_style: HoverStyle::Highlight,
+        };
+        assert!(controller.config.theme() != theme);
+
+        let before = saves(&controller.config);
+        controller.apply_theme(theme);
+        assert_eq!(saves(&controller.config), before + 1);
+
+        let current = &controller.config;
+        assert_eq!(current.color_scheme, ColorScheme::Classic);
+        assert_eq!(current.background, (0.2, 0.2, 0.25));
+        assert_eq!(controller.board().colors, ensure_contrast(theme.background));
+        assert_eq!(current.effective_sloppiness(), 0.0);
+        assert!(current.tidy_layout);
+        assert!(current.card_shadows);
+        assert_eq!(current.hover_style, HoverStyle::Highlight);
+        assert_eq!(current.theme(), theme);
+
+        // reapplying the current theme doesn't write anything
+        controller.apply_theme(theme);
+        assert_eq!(saves(&controller.config), before + 1);
+    }
+
+    #[test]
+    fn check_deferred_config_saves() {
+        let mut config = Config::new();
+        config.saving.store = config::ConfigStore::Memory { writes: 0 };
+        config.set_deferred(true);
+        let before = saves(&config);
+
+        for _ in 0..5 {
+            config.set_show_grid(true);
+            config.set_show_grid(false);
+        }
+        config.set_sloppiness(0.5);
+        assert_eq!(saves(&config), before);
+
+        // the whole burst is a single write
+        config.flush();
+        assert_eq!(saves(&config), before + 1);
+        config.flush();
+        assert_eq!(saves(&config), before + 1);
+
+        // turning deferral off writes pending changes and saves immediately
+        config.set_card_shadows(true);
+        config.set_deferred(false);
+        assert_eq!(saves(&config), before + 2);
+        config.set_card_shadows(false);
+        assert_eq!(saves(&config), before + 3);
+    }
+
+    #[test]
+    fn check_adopted_variant_saves_are_deferred() {
+        let mut controller = Controller::headless(Config::new());
+        controller.config.set_deferred(true);
+
+        controller.adopt_variant_and_deck(config::Variant::SuperSet, config::Deck::Simplified);
+        assert_eq!(controller.config.variant, config::Variant::SuperSet);
+        assert_eq!(saves(&controller.config), 0);
+
+        controller.config.flush();
+        assert_eq!(saves(&controller.config), 1);
+    }
+
+    #[test]
+    fn check_preserve_selection_on_undo() {
+        for &preserve in &[false, true] {
+            let config = Config { preserve_selection_on_undo: preserve, ..Config::new() };
+            let mut controller = Controller::headless(config);
+            let kept = controller.state.cards()[0];
+            controller.toggle_selected(kept);
+
+            let before = controller.state.cards();
+            controller.force_deal();
+            let dealt = controller.state.cards().into_iter().find(|c| !before.contains(c)).unwrap();
+            controller.toggle_selected(dealt);
+            assert_eq!(controller.selected, [kept, dealt]);
+
+            // undoing the deal takes away the dealt card, which is never kept
+            controller.undo();
+            let expected = if preserve { vec![kept] } else { vec![] };
+            assert_eq!(controller.selected, expected);
+
+            controller.redo();
+            assert_eq!(controller.selected, expected);
+        }
+    }
+
+    #[test]
+    fn check_peek_next_deal() {
+        let mut controller = Controller::headless(Config::new());
+        let preview = controller.peek_next_deal();
+        assert_eq!(preview.len(), 3);
+        assert!(preview.iter().all(|card| !controller.state.cards().contains(card)));
+
+        // the table holds 12 cards, so no guarantee alters the deal
+        controller.force_deal();
+        assert!(preview.iter().all(|card| controller.state.cards().contains(card)));
+
+        // drawing the preview leaves the game alone
+        let cards = controller.state.cards();
+        controller.layout_for_size(1000, 800);
+        controller.set_preview_deals(true);
+        let surface = ImageSurface::create(Format::ARgb32, 1000, 800).unwrap();
+        let ctx = Context::new(&surface).unwrap();
+        controller.render_deal_preview(&ctx).unwrap();
+        assert_eq!(controller.state.cards(), cards);
+    }
+
+    #[test]
+    fn check_idle_nudge() {
+        let mut controller = Controller::headless(Config::new());
+        ensure_set(&mut controller);
+
+        // nudges are off by default
+        controller.nudge(controller.last_activity);
+        assert!(controller.selected.is_empty());
+
+        controller.set_nudge_seconds(30);
+        let stale = controller.last_activity;
+        let card = controller.state.cards()[0];
+        controller.toggle_from(card, Input::Mouse);
+        controller.toggle_from(card, Input::Mouse);
+
+        // activity replaces the pending nudge
+        controller.nudge(stale);
+        assert!(controller.selected.is_empty());
+
+        // an idle player gets a hint, which counts as help
+        controller.nudge(controller.last_activity);
+        assert_eq!(controller.selected.len(), 2);
+        assert_eq!(controller.assists.hints, 1);
+
+        // but not while a solution plays back
+        controller.deselect_all();
+        controller.solution = Some((Instant::now(), vec![]));
+        controller.nudge(controller.last_activity);
+        assert!(controller.selected.is_empty());
+    }
+
+    #[test]
+    fn check_debug_overlay() {
+        let mut controller = Controller::headless(Config::new());
+        controller.debug_overlay = false;
+        controller.layout_for_size(600, 350);
+        let (x, y) = controller.cell_rects[0].center();
+        let hit = controller.card_for_point(x, y);
+        let mut before = controller.render_image().unwrap();
+
+        controller.toggle_debug_overlay();
+        assert!(controller.debug_overlay);
+
+        // hit testing and exported images ignore the overlay
+        assert_eq!(controller.card_for_point(x, y), hit);
+        let mut after = controller.render_image().unwrap();
+        assert!(before.data().unwrap().to_vec() == after.data().unwrap().to_vec());
+
+        // but it draws over the view
+        let ctx = Context::new(&after).unwrap();
+        controller.render_debug_overlay(&ctx).unwrap();
+        drop(ctx);
+        assert!(before.data().unwrap().to_vec() != after.data().unwrap().to_vec());
     }
 }
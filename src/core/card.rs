@@ -73,6 +73,28 @@ pub enum Color { A, B, C }
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Shading { Solid, Striped, Outlined }
 
+impl Shape {
+    /// Human readable name, suitable for UI and logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Oval => "oval",
+            Shape::Squiggle => "squiggle",
+            Shape::Diamond => "diamond",
+        }
+    }
+}
+
+impl Shading {
+    /// Human readable name, suitable for UI and logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shading::Solid => "solid",
+            Shading::Striped => "striped",
+            Shading::Outlined => "outlined",
+        }
+    }
+}
+
 impl Card {
     /// Extracts the byte corresponding to the given `Feature`. Since
     /// the bytes represent ternary digits, the returned value will
@@ -109,6 +131,23 @@ impl Card {
             _ => Shading::Outlined,
         }
     }
+
+    /// The inverse of the feature getters. Panics unless `count` is in
+    /// the interval [1,3].
+    pub fn from_features(count: u8, shape: Shape, color: Color, shading: Shading) -> Card {
+        assert!((1..=3).contains(&count), "card count out of range: {}", count);
+
+        let trits = [
+            (Feature::Count, (count - 1) as u32),
+            (Feature::Shape, shape as u32),
+            (Feature::Color, color as u32),
+            (Feature::Shading, shading as u32),
+        ];
+
+        let value = trits.iter()
+            .fold(0, |value, &(feature, trit)| value | trit << (feature as u32 * 8));
+        Card(value)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -123,6 +162,86 @@ impl fmt::Debug for Card {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Card: Parsing
+////////////////////////////////////////////////////////////////////////////////
+
+use std::error;
+use std::str::FromStr;
+
+/// The canonical text form of a card is its index, a decimal number
+/// from 0 to 80. This matches serialization and `genpng` filenames.
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Card, ParseCardError> {
+        let s = s.trim();
+        let index = s.parse::<usize>()
+            .map_err(|_| ParseCardError::Malformed(s.to_string()))?;
+
+        if index < DECK_SIZE {
+            Ok(Card::new(index))
+        } else {
+            Err(ParseCardError::OutOfRange(index))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseCardError {
+    /// The text wasn't a decimal number.
+    Malformed(String),
+    /// The number was too large to be a card index.
+    OutOfRange(usize),
+}
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseCardError::Malformed(ref s) =>
+                write!(f, "\"{}\" isn't a card index (a number from 0 to 80).", s),
+            ParseCardError::OutOfRange(index) =>
+                write!(f, "{} is out of range. Card indices go from 0 to 80.", index),
+        }
+    }
+}
+
+impl error::Error for ParseCardError {}
+
+////////////////////////////////////////////////////////////////////////////////
+// Card: Serialize/Deserialize
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::deck::DECK_SIZE;
+use serde::de::{self, Deserialize, Deserializer, Unexpected};
+use serde::ser::{Serialize, Serializer};
+
+/// Cards are serialized by index, which is stable and compact.
+impl Serialize for Card {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(self.index() as u8)
+    }
+}
+
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D>(deserializer: D) -> Result<Card, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let index = u8::deserialize(deserializer)? as usize;
+
+        if index < DECK_SIZE {
+            Ok(Card::new(index))
+        } else {
+            let unexpected = Unexpected::Unsigned(index as u64);
+            Err(de::Error::invalid_value(unexpected, &"a card index below 81"))
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Set
 ////////////////////////////////////////////////////////////////////////////////
@@ -207,6 +326,27 @@ impl ToSuperSet for (Card, Card, Card, Card) {
     }
 }
 
+/// Returns every card that completes a `SuperSet` with `a`, `b`, and
+/// `c`. Each way of pairing the fourth card yields at most one
+/// candidate: if `d` pairs with `c`, then `(c, d)` must complete to the
+/// same card `t` as `(a, b)`, so `d` is the card completing `(c, t)`.
+pub fn superset_completions(a: Card, b: Card, c: Card) -> Vec<Card> {
+    let pairings = [(a, b, c), (a, c, b), (b, c, a)];
+    let mut completions = Vec::with_capacity(pairings.len());
+
+    for &(x, y, z) in &pairings {
+        let target = (x, y).complete_set();
+        let d = (z, target).complete_set();
+
+        // if `x`, `y`, and `z` form a `Set`, then `d` is just `z` again
+        if d != a && d != b && d != c && !completions.contains(&d) {
+            completions.push(d);
+        }
+    }
+
+    completions
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // CompleteSet
 ////////////////////////////////////////////////////////////////////////////////
@@ -275,6 +415,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_from_features() {
+        for card in cards() {
+            let rebuilt = Card::from_features(card.count(), card.shape(), card.color(), card.shading());
+            assert_eq!(rebuilt, card);
+        }
+
+        let card = Card::from_features(3, Shape::Diamond, Color::B, Shading::Solid);
+        assert_eq!(card.count(), 3);
+        assert_eq!(card.shape(), Shape::Diamond);
+        assert_eq!(card.color(), Color::B);
+        assert_eq!(card.shading(), Shading::Solid);
+    }
+
+    #[test]
+    fn check_card_serialization() {
+        for card in cards() {
+            let yaml = serde_yaml::to_string(&card).unwrap();
+            assert_eq!(yaml.trim(), card.index().to_string());
+
+            let parsed: Card = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(parsed, card);
+        }
+
+        let err = serde_yaml::from_str::<Card>("81").unwrap_err();
+        assert!(err.to_string().contains("a card index below 81"));
+        assert!(serde_yaml::from_str::<Card>("-1").is_err());
+    }
+
+    #[test]
+    fn check_card_parsing() {
+        for card in cards() {
+            assert_eq!(card.index().to_string().parse::<Card>(), Ok(card));
+        }
+
+        assert_eq!(" 7 ".parse::<Card>(), Ok(Card::new(7)));
+        assert_eq!("81".parse::<Card>(), Err(ParseCardError::OutOfRange(81)));
+        assert_eq!("-1".parse::<Card>(), Err(ParseCardError::Malformed("-1".to_string())));
+        assert_eq!("".parse::<Card>(), Err(ParseCardError::Malformed(String::new())));
+    }
+
     #[test]
     fn check_set_completion() {
         let cards = cards();
@@ -289,4 +470,37 @@ mod tests {
         // each set is encountered thrice
         assert_eq!(set_count, 1080 * 3)
     }
+
+    #[test]
+    fn check_superset_completions() {
+        let cards = cards();
+
+        for (&a, &b) in cards.pairs().take(200) {
+            for &c in cards.iter().filter(|&&c| c != a && c != b).step_by(7) {
+                let completions = superset_completions(a, b, c);
+
+                for &d in &completions {
+                    assert!((a, b, c, d).to_superset().is_some());
+                }
+
+                // compare against a brute force search
+                let expected = cards.iter()
+                    .filter(|&&d| d != a && d != b && d != c)
+                    .filter(|&&d| (a, b, c, d).to_superset().is_some())
+                    .count();
+                assert_eq!(completions.len(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn check_feature_names() {
+        assert_eq!(Shape::Oval.name(), "oval");
+        assert_eq!(Shape::Squiggle.name(), "squiggle");
+        assert_eq!(Shape::Diamond.name(), "diamond");
+
+        assert_eq!(Shading::Solid.name(), "solid");
+        assert_eq!(Shading::Striped.name(), "striped");
+        assert_eq!(Shading::Outlined.name(), "outlined");
+    }
 }
@@ -14,27 +14,129 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::card::*;
-use crate::find::FindSets;
+use crate::find::{FindSets, FindSuperSets};
 use crate::pair_iter::PairIter;
 use crate::shuffle::Shuffle;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use std::cmp;
 
 pub const DECK_SIZE: usize = 81;
 
+/// Iterates over all the cards in a Set deck, in index order.
+pub fn all_cards() -> impl Iterator<Item = Card> {
+    (0..DECK_SIZE).map(Card::new)
+}
+
 /// Returns a vector containing all the cards in a Set deck.
 pub fn cards() -> Vec<Card> {
-    (0..DECK_SIZE).map(Card::new).collect()
+    all_cards().collect()
+}
+
+/// Returns all 1080 `Set`s in the deck. The cards in each `Set` are in
+/// index order, and the `Set`s are sorted by their indices, so the
+/// list is the same from run to run.
+pub fn all_sets() -> Vec<[Card; 3]> {
+    let mut sets: Vec<[Card; 3]> = cards()
+        .find_all_sets()
+        .iter()
+        .map(|set| {
+            let (a, b, c) = set.cards();
+            let mut triple = [a, b, c];
+            triple.sort_by_key(|card| card.index());
+            triple
+        })
+        .collect();
+
+    sets.sort_by_key(|triple| triple.map(Card::index));
+    sets
+}
+
+/// Returns all 63180 `SuperSet`s in the deck, ordered as in `all_sets`.
+pub fn all_supersets() -> Vec<[Card; 4]> {
+    let mut supersets: Vec<[Card; 4]> = cards()
+        .find_all_supersets()
+        .iter()
+        .map(|superset| {
+            let ((a, b), (c, d)) = (superset.left(), superset.right());
+            let mut quad = [a, b, c, d];
+            quad.sort_by_key(|card| card.index());
+            quad
+        })
+        .collect();
+
+    supersets.sort_by_key(|quad| quad.map(Card::index));
+    supersets
+}
+
+/// When the tableau is refilled after a set is taken. By default, cards
+/// are only dealt if the board drops below the initial deal, so a board
+/// that was expanded to get unstuck shrinks back down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplenishPolicy {
+    /// Deal one set's worth if fewer than the initial deal remain
+    IfBelowInitial,
+    /// Only deal when asked to
+    Never,
+    /// Deal as many cards as it takes to get back to the initial deal
+    AlwaysToInitial,
+}
+
+/// Games saved before decks kept their generator carry on unseeded.
+fn entropy_rng() -> ChaCha8Rng {
+    ChaCha8Rng::from_entropy()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Deck {
+    stock: Vec<Card>,
+    /// Drives every shuffle after the first, including doctored deals,
+    /// so a seeded deck stays reproducible all the way through
+    #[serde(default = "entropy_rng")]
+    rng: ChaCha8Rng,
 }
 
-#[derive(Default, Clone)]
-pub struct Deck { stock: Vec<Card> }
+impl Default for Deck {
+    fn default() -> Deck {
+        Deck::from_cards(vec![])
+    }
+}
 
 impl Deck {
     /// Returns a shuffled `Deck`.
     pub fn new() -> Deck {
+        Deck::shuffled(cards(), entropy_rng())
+    }
+
+    /// Returns a `Deck` shuffled into the order determined by `seed`.
+    /// The generator is fixed (unlike `StdRng`), so a seed means the
+    /// same deck across releases.
+    pub fn new_seeded(seed: u64) -> Deck {
+        Deck::shuffled(cards(), ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Returns a `Deck` holding exactly `cards`, unshuffled. The last
+    /// card is drawn first.
+    pub fn from_cards(cards: Vec<Card>) -> Deck {
+        Deck { stock: cards, rng: entropy_rng() }
+    }
+
+    /// Like `from_cards`, but with later shuffles (e.g. in doctored
+    /// deals) determined by `seed`.
+    pub fn from_cards_seeded(cards: Vec<Card>, seed: u64) -> Deck {
+        Deck { stock: cards, rng: ChaCha8Rng::seed_from_u64(seed) }
+    }
+
+    /// Returns a shuffled `Deck` of every card that isn't in `in_play`.
+    pub fn excluding(in_play: &[Card]) -> Deck {
         let mut cards = cards();
-        cards.shuffle();
-        Deck { stock: cards }
+        cards.retain(|card| !in_play.contains(card));
+        Deck::shuffled(cards, entropy_rng())
+    }
+
+    fn shuffled(mut cards: Vec<Card>, mut rng: ChaCha8Rng) -> Deck {
+        cards.shuffle_with(&mut rng);
+        Deck { stock: cards, rng }
     }
 
     /// Removes all cards from the deck that do not have a solid
@@ -43,6 +145,13 @@ impl Deck {
         self.stock.retain(|card| card.shading() == Shading::Solid);
     }
 
+    /// Keeps only the next `n` cards to be drawn. The stock is already
+    /// shuffled, so this leaves a random subset for a shorter game.
+    pub fn subset(&mut self, n: usize) {
+        let excess = self.remainder().saturating_sub(n);
+        self.stock.drain(..excess);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.stock.is_empty()
     }
@@ -51,11 +160,38 @@ impl Deck {
         self.stock.len()
     }
 
+    /// Returns a copy of the cards remaining in the stock. The order
+    /// is preserved, so the last card is the next one drawn.
+    pub fn remaining_cards(&self) -> Vec<Card> {
+        self.stock.clone()
+    }
+
+    /// The cards remaining in the stock, with the next draw at the end.
+    pub fn cards(&self) -> &[Card] {
+        &self.stock
+    }
+
     pub fn draw(&mut self, n: usize) -> Vec<Card> {
         let r = self.remainder();
         let x = cmp::min(n, r);
         self.stock.split_off(r - x)
     }
+
+    /// Returns the cards that `draw(n)` would, without drawing them.
+    pub fn peek(&self, n: usize) -> Vec<Card> {
+        let r = self.remainder();
+        let x = cmp::min(n, r);
+        self.stock[r - x..].to_vec()
+    }
+
+    /// Replaces the stock with a freshly shuffled deck of every card
+    /// that isn't already in play. Useful for modes that never run out.
+    pub fn restock(&mut self, in_play: &[Card]) {
+        let mut cards = cards();
+        cards.retain(|card| !in_play.contains(card));
+        cards.shuffle_with(&mut self.rng);
+        self.stock = cards;
+    }
 }
 
 impl Deck {
@@ -75,15 +211,15 @@ impl Deck {
     /// cards.* There are 3 cases we need to handle:
     ///
     /// 1) Two cards from the `Set` are on the table, and one is in the
-    /// stock. We need to make sure that the one card in the stock is in
-    /// the next draw.
+    ///    stock. We need to make sure that the one card in the stock is in
+    ///    the next draw.
     ///
     /// 2) One card from the `Set` is on the table, and two are in the
-    /// stock. We need to make sure that both cards in the stock are in
-    /// the next draw.
+    ///    stock. We need to make sure that both cards in the stock are in
+    ///    the next draw.
     ///
     /// 3) All three cards in the `Set` are in the stock. We need to put
-    /// those three cards into the next draw.
+    ///    those three cards into the next draw.
     ///
     /// *NOTE: It's possible that 21 cards always contain 2 or more
     /// sets. As far as I know, that's an open question.
@@ -114,7 +250,7 @@ impl Deck {
         // shuffle the cards in the hand so we don't favor cards at
         // the front of the layout
         let mut hand = hand.to_owned();
-        hand.shuffle();
+        hand.shuffle_with(&mut self.rng);
 
         for c in hand.pairs().map(|pair| pair.complete_set()) {
             if let Some(ix) = self.stock.iter().position(|&obj| obj == c) {
@@ -126,7 +262,7 @@ impl Deck {
                 let mut draw = self.draw(3);
 
                 // shuffle to randomize the position of the found card
-                draw.shuffle();
+                draw.shuffle_with(&mut self.rng);
                 return Some(draw);
             }
         }
@@ -147,7 +283,7 @@ impl Deck {
             result.append(&mut self.draw(1));
 
             // randomize the order
-            result.shuffle();
+            result.shuffle_with(&mut self.rng);
             Some(result)
         } else {
             None
@@ -163,6 +299,134 @@ impl Deck {
             None
         }
     }
+
+    /// Any 10 cards contain a `SuperSet` (see the `count` example), so
+    /// the next 4 cards can only leave the table stuck when fewer than
+    /// 6 are in play. When they would, we doctor the deck with the
+    /// fewest stock cards that complete a `SuperSet`:
+    ///
+    /// 1) Three cards are on the table, so one card from the stock
+    ///    completes them.
+    ///
+    /// 2) Two cards are on the table, and two are in the stock.
+    ///
+    /// 3) One card is on the table, and three are in the stock.
+    ///
+    /// 4) All four cards are in the stock.
+    ///
+    /// Returns None if no `SuperSet` can be made from the table and
+    /// the stock combined.
+    ///
+    pub fn draw_guaranteeing_superset(&mut self, hand: &[Card]) -> Option<Vec<Card>> {
+        assert!(self.stock.len() >= 4);
+
+        // Check to see if simply drawing the next 4 cards is okay.
+        let mut draw = self.draw(4);
+        let mut test = hand.to_owned();
+        test.append(&mut draw.clone());
+
+        if test.contains_superset() {
+            return Some(draw);
+        } else {
+            // return the draw to the stock so we can doctor the deck
+            self.stock.append(&mut draw);
+        }
+
+        // shuffle the cards in the hand so we don't favor cards at
+        // the front of the layout
+        let mut hand = hand.to_owned();
+        hand.shuffle_with(&mut self.rng);
+
+        let needed = self.superset_from_three_in_hand(&hand)
+            .or_else(|| self.superset_from_two_in_hand(&hand))
+            .or_else(|| self.superset_from_one_in_hand(&hand))
+            .or_else(|| self.superset_from_stock())?;
+
+        // pull the needed cards and top up the draw normally
+        self.stock.retain(|card| !needed.contains(card));
+        let mut result = self.draw(4 - needed.len());
+        result.extend(needed);
+
+        // randomize the positions of the found cards
+        result.shuffle_with(&mut self.rng);
+        Some(result)
+    }
+
+    /// Draws `n` cards to join `table`. With `guarantee` on, a draw of
+    /// one set's worth is doctored when the guarantee applies: 3-card
+    /// sets once the table has 15 cards and the stock 6, and 4-card
+    /// sets whenever the stock has 4. Larger batches are drawn a set's
+    /// worth at a time, so the guarantee covers the draw that tops up
+    /// the table. Falls back to the naive draw if the deck can't help.
+    pub fn deal(&mut self, table: &[Card], n: usize, set_size: usize, guarantee: bool) -> Vec<Card> {
+        if n > set_size && n.is_multiple_of(set_size) {
+            let mut table = table.to_owned();
+            let mut dealt = Vec::with_capacity(n);
+            for _ in 0..n / set_size {
+                let draw = self.deal(&table, set_size, set_size, guarantee);
+                table.extend(&draw);
+                dealt.extend(draw);
+            }
+            return dealt;
+        }
+
+        let guarantee = guarantee && n == set_size;
+        let doctored = match set_size {
+            3 if guarantee && table.len() == 15 && self.remainder() >= 6 => {
+                self.draw_guaranteeing_set(table)
+            }
+            4 if guarantee && self.remainder() >= 4 => {
+                self.draw_guaranteeing_superset(table)
+            }
+            _ => None,
+        };
+
+        doctored.unwrap_or_else(|| self.draw(n))
+    }
+
+    /// The stock card that makes a `SuperSet` with `a`, `b`, and `c`.
+    fn superset_completion_in_stock(&self, (a, b, c): (Card, Card, Card)) -> Option<Card> {
+        superset_completions(a, b, c)
+            .into_iter()
+            .find(|card| self.stock.contains(card))
+    }
+
+    fn superset_from_three_in_hand(&self, hand: &[Card]) -> Option<Vec<Card>> {
+        hand.pairs()
+            .flat_map(|(&a, &b)| {
+                hand.iter()
+                    .filter(move |&&c| c != a && c != b)
+                    .map(move |&c| (a, b, c))
+            })
+            .find_map(|triple| self.superset_completion_in_stock(triple))
+            .map(|d| vec![d])
+    }
+
+    fn superset_from_two_in_hand(&self, hand: &[Card]) -> Option<Vec<Card>> {
+        hand.pairs()
+            .flat_map(|(&a, &b)| self.stock.iter().map(move |&c| (a, b, c)))
+            .find_map(|(a, b, c)| {
+                self.superset_completion_in_stock((a, b, c))
+                    .map(|d| vec![c, d])
+            })
+    }
+
+    fn superset_from_one_in_hand(&self, hand: &[Card]) -> Option<Vec<Card>> {
+        hand.iter()
+            .flat_map(|&a| self.stock.pairs().map(move |(&b, &c)| (a, b, c)))
+            .find_map(|(a, b, c)| {
+                self.superset_completion_in_stock((a, b, c))
+                    .map(|d| vec![b, c, d])
+            })
+    }
+
+    fn superset_from_stock(&self) -> Option<Vec<Card>> {
+        self.stock.find_first_superset().map(|superset| {
+            let (a, b) = superset.left();
+            let (c, d) = superset.right();
+            vec![a, b, c, d]
+        })
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -174,6 +438,14 @@ mod tests {
     use super::*;
     use crate::card::Card;
     use crate::find::{FindSets, FindSuperSets};
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn check_all_cards() {
+        assert_eq!(all_cards().count(), DECK_SIZE);
+        assert!(all_cards().enumerate().all(|(ix, card)| card.index() == ix));
+        assert_eq!(all_cards().collect::<Vec<_>>(), cards());
+    }
 
     #[test]
     fn count_sets() {
@@ -182,6 +454,26 @@ mod tests {
         assert_eq!(cards().count_sets(), 1080);
     }
 
+    #[test]
+    fn check_all_sets() {
+        let sets = all_sets();
+        assert_eq!(sets.len(), 1080);
+        assert!(sets.iter().all(|&[a, b, c]| (a, b, c).to_set().is_some()));
+        assert!(sets.iter().all(|t| t[0].index() < t[1].index() && t[1].index() < t[2].index()));
+
+        // sorted, hence distinct
+        let indices: Vec<_> = sets.iter().map(|t| t.map(Card::index)).collect();
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn check_all_supersets() {
+        let supersets = all_supersets();
+        assert_eq!(supersets.len(), 63180);
+        let indices: Vec<_> = supersets.iter().map(|q| q.map(Card::index)).collect();
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
     #[test]
     fn count_supersets() {
         let supersets = cards().find_all_supersets();
@@ -189,6 +481,50 @@ mod tests {
         assert_eq!(cards().count_supersets(), 63180);
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_count_supersets() {
+        use crate::find::ParFindSuperSets;
+
+        let cards = cards();
+        assert_eq!(cards.par_count_supersets(), 63180);
+
+        // same supersets in the same order as the serial search
+        let serial = cards.find_all_supersets();
+        let parallel = cards.par_find_all_supersets();
+        assert_eq!(parallel.len(), serial.len());
+        assert!(serial.iter().zip(&parallel).all(|(a, b)| a.left() == b.left() && a.right() == b.right()));
+    }
+
+    #[test]
+    fn check_new_seeded() {
+        let deck = Deck::new_seeded(42);
+        assert_eq!(deck.remaining_cards(), Deck::new_seeded(42).remaining_cards());
+        assert_ne!(deck.remaining_cards(), Deck::new_seeded(43).remaining_cards());
+
+        // pinned, so that a dependency update can't change what a
+        // shared seed deals
+        let top: Vec<usize> = deck.peek(6).iter().map(|card| card.index()).collect();
+        assert_eq!(top, [23, 11, 22, 49, 76, 55]);
+
+        // still a full deck, just in a repeatable order
+        let mut stock = deck.remaining_cards();
+        stock.sort_by_key(|card| card.index());
+        assert_eq!(stock, cards());
+    }
+
+    #[test]
+    fn check_from_cards() {
+        let stock = [5, 17, 42, 80].as_cards();
+        let mut deck = Deck::from_cards(stock.clone());
+        assert_eq!(deck.cards(), &stock[..]);
+
+        // drawn from the end, in order
+        assert_eq!(deck.draw(2), [42, 80].as_cards());
+        assert_eq!(deck.cards(), &[5, 17].as_cards()[..]);
+        assert_eq!(deck.cards(), &deck.remaining_cards()[..]);
+    }
+
     #[test]
     fn check_draw_cards() {
         let mut deck = Deck::new();
@@ -207,6 +543,51 @@ mod tests {
         assert!(deal.is_empty());
     }
 
+    #[test]
+    fn check_peek() {
+        let mut deck = Deck::new();
+        let peeked = deck.peek(12);
+        assert_eq!(deck.remainder(), 81);
+        assert_eq!(deck.draw(12), peeked);
+
+        let r = deck.remainder();
+        assert_eq!(deck.peek(r + 10).len(), r);
+        deck.draw(r);
+        assert!(deck.peek(3).is_empty());
+    }
+
+    #[test]
+    fn check_subset() {
+        let mut deck = Deck::new();
+        let top = deck.clone().draw(30);
+
+        deck.subset(30);
+        assert_eq!(deck.remainder(), 30);
+        // the same cards are dealt, there are just fewer of them
+        assert_eq!(deck.draw(30), top);
+
+        let mut deck = Deck::new();
+        deck.simplify();
+        deck.subset(45);
+        assert_eq!(deck.remainder(), 27);
+    }
+
+    #[test]
+    fn check_remaining_cards() {
+        let mut deck = Deck::new();
+        assert_eq!(deck.remaining_cards().len(), deck.remainder());
+
+        let before = deck.remaining_cards();
+        let deal = deck.draw(12);
+        let after = deck.remaining_cards();
+        assert_eq!(after.len(), deck.remainder());
+        assert!(deal.iter().all(|card| !after.contains(card)));
+
+        // drawing takes from the end of the stock
+        assert_eq!(&before[..after.len()], &after[..]);
+        assert_eq!(&before[after.len()..], &deal[..]);
+    }
+
     trait AsCards {
         fn as_cards(&self) -> Vec<Card>;
     }
@@ -243,7 +624,7 @@ mod tests {
         assert!(!hand.contains_set());
         assert!(!stock.contains_set());
 
-        let mut deck = Deck { stock };
+        let mut deck = Deck::from_cards(stock);
         match deck.fix_one_card(&hand) {
             None => panic!("Could not guarantee set!"),
             Some(mut draw) => {
@@ -266,7 +647,7 @@ mod tests {
         assert!(!hand.contains_set());
         assert!(!stock.contains_set());
 
-        let mut deck = Deck { stock };
+        let mut deck = Deck::from_cards(stock);
         match deck.fix_two_cards(&hand) {
             None => panic!("Could not guarantee set!"),
             Some(mut draw) => {
@@ -285,7 +666,7 @@ mod tests {
 
         let stock = [34, A, B, C, 64, 72].as_cards(); // three cards from the set in the stock
 
-        let mut deck = Deck { stock };
+        let mut deck = Deck::from_cards(stock);
         match deck.fix_three_cards() {
             None => panic!("Could not guarantee set!"),
             Some(mut draw) => {
@@ -325,7 +706,7 @@ mod tests {
         // making sure that we can find sets
         for &x in &rest {
             stock[0] = x;
-            let mut deck = Deck { stock: stock.clone() };
+            let mut deck = Deck::from_cards(stock.clone());
 
             match deck.draw_guaranteeing_set(&hand) {
                 None => panic!("Could not guarantee set!"),
@@ -338,4 +719,217 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn check_seeded_guarantee() {
+        // 15 cards without a set, over a stock whose next 3 can't fix them
+        let cap = [0,1,3,4,9,13,14,15,19,34,38,39,40,44,49,50,52,53,60,74].as_cards();
+        let (hand, top) = cap.split_at(15);
+        let mut stock: Vec<Card> = all_cards().filter(|card| !cap.contains(card)).take(20).collect();
+        stock.extend(top);
+
+        let mut naive = hand.to_vec();
+        naive.extend(&stock[stock.len() - 3..]);
+        assert!(!naive.contains_set());
+
+        let doctored = |seed| {
+            let mut deck = Deck::from_cards_seeded(stock.clone(), seed);
+            let draw = deck.draw_guaranteeing_set(hand).unwrap();
+            (draw, deck.remaining_cards())
+        };
+
+        // the same seed doctors the deck the same way every time
+        for seed in 0..20 {
+            assert_eq!(doctored(seed), doctored(seed));
+        }
+        assert!((1..20).any(|seed| doctored(seed) != doctored(0)));
+    }
+
+    #[test]
+    fn check_deal_batches() {
+        // a 12 card table over 6 stock cards, none of which make a set
+        let cap = [0,1,3,4,9,13,14,15,19,34,38,39,40,44,49,50,52,53,60,74].as_cards();
+        let (table, top) = (&cap[..12], &cap[12..18]);
+        let mut stock: Vec<Card> = all_cards().filter(|card| !cap.contains(card)).take(20).collect();
+        stock.extend(top);
+
+        let mut deck = Deck::from_cards_seeded(stock.clone(), 5);
+        let draw = deck.deal(table, 6, 3, false);
+        assert_eq!(draw.len(), 6);
+        assert!(draw.iter().all(|card| top.contains(card)));
+
+        // the second half of the batch lands on 15 cards, so it's doctored
+        let mut deck = Deck::from_cards_seeded(stock, 5);
+        let mut draw = deck.deal(table, 6, 3, true);
+        assert_eq!(draw.len(), 6);
+        assert_eq!(deck.remainder(), 20);
+        draw.extend(table);
+        assert!(draw.contains_set());
+    }
+
+    /// Deals a random hand of 15 cards with no sets, or None if the
+    /// greedy search paints itself into a corner.
+    fn set_free_hand(pool: &mut Vec<Card>) -> Option<Vec<Card>> {
+        let mut hand: Vec<Card> = vec![];
+        let mut ix = 0;
+
+        while hand.len() < 15 && ix < pool.len() {
+            let card = pool[ix];
+            if hand.pairs().all(|pair| pair.complete_set() != card) {
+                hand.push(pool.remove(ix));
+            } else {
+                ix += 1;
+            }
+        }
+
+        if hand.len() == 15 { Some(hand) } else { None }
+    }
+
+    #[test]
+    fn check_guarantee_randomized() {
+        use crate::shuffle::Shuffle;
+        use rand::Rng;
+
+        // seeded so that any failure can be reproduced
+        let mut rng = StdRng::seed_from_u64(0x5e7);
+
+        for round in 0..500 {
+            let mut pool = cards();
+            pool.shuffle_with(&mut rng);
+
+            // mostly set-free hands, since those exercise the fixers
+            let hand = if round % 4 == 0 {
+                pool.split_off(DECK_SIZE - 15)
+            } else {
+                match set_free_hand(&mut pool) {
+                    Some(hand) => hand,
+                    None => continue,
+                }
+            };
+
+            let stock_size = rng.gen_range(6..=12);
+            let stock = pool.split_off(pool.len() - stock_size);
+
+            let mut everything = hand.clone();
+            everything.extend(&stock);
+
+            let mut deck = Deck::from_cards(stock.clone());
+            match deck.draw_guaranteeing_set(&hand) {
+                None => assert!(!everything.contains_set(), "round {}", round),
+                Some(draw) => {
+                    assert_eq!(draw.len(), 3, "round {}", round);
+                    assert!(draw.iter().all(|card| stock.contains(card)), "round {}", round);
+
+                    // the draw leaves the stock without losing any cards
+                    assert_eq!(deck.remainder(), stock_size - 3, "round {}", round);
+                    assert!(deck.stock.iter().all(|card| stock.contains(card) && !draw.contains(card)));
+
+                    let mut test = hand.clone();
+                    test.extend(&draw);
+                    assert!(test.contains_set(), "round {}", round);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_superset_fixers() {
+        // these cards are a superset
+        let quad = [0, 1, 3, 4].as_cards();
+        assert!(quad.contains_superset());
+
+        // 4 more cards, leaving (0, 1, 3, 4) as the only superset
+        let others = [80, 71, 62, 26].as_cards();
+        let mut cards = quad.clone();
+        cards.extend(&others);
+        assert_eq!(cards.count_supersets(), 1);
+
+        // split the superset between the hand and the stock in each way
+        for in_hand in 0..4 {
+            let hand = quad[..in_hand].to_vec();
+            let needed = quad[in_hand..].to_vec();
+
+            // the naive draw would be `others`, which doesn't help
+            let mut stock = needed.clone();
+            stock.extend(&others);
+
+            let mut deck = Deck::from_cards(stock);
+            match deck.draw_guaranteeing_superset(&hand) {
+                None => panic!("Could not guarantee superset!"),
+                Some(draw) => {
+                    assert_eq!(draw.len(), 4);
+                    assert!(needed.iter().all(|card| draw.contains(card)), "{} in hand", in_hand);
+
+                    let mut test = hand.clone();
+                    test.extend(&draw);
+                    assert!(test.contains_superset(), "{} in hand", in_hand);
+
+                    // the rest of the draw is topped up from the end
+                    let kept = &others[..others.len() - (4 - needed.len())];
+                    assert_eq!(deck.stock, kept);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_superset_guarantee_randomized() {
+        use crate::shuffle::Shuffle;
+        use rand::Rng;
+
+        // seeded so that any failure can be reproduced
+        let mut rng = StdRng::seed_from_u64(0x5e75e7);
+
+        for round in 0..500 {
+            let mut pool = cards();
+            pool.shuffle_with(&mut rng);
+
+            // small hands, since only those can be stuck after a draw
+            let mut hand: Vec<Card> = vec![];
+            let hand_size = rng.gen_range(0..=5);
+            while hand.len() < hand_size {
+                hand.push(pool.pop().unwrap());
+                if hand.contains_superset() {
+                    hand.pop();
+                }
+            }
+
+            // small stocks, so that some rounds can't be fixed at all
+            let stock_size = rng.gen_range(4..=8);
+            let stock = pool.split_off(pool.len() - stock_size);
+
+            let mut everything = hand.clone();
+            everything.extend(&stock);
+
+            let mut deck = Deck::from_cards(stock.clone());
+            match deck.draw_guaranteeing_superset(&hand) {
+                None => assert!(!everything.contains_superset(), "round {}", round),
+                Some(draw) => {
+                    assert_eq!(draw.len(), 4, "round {}", round);
+                    assert!(draw.iter().all(|card| stock.contains(card)), "round {}", round);
+
+                    // the draw leaves the stock without losing any cards
+                    assert_eq!(deck.remainder(), stock_size - 4, "round {}", round);
+                    assert!(deck.stock.iter().all(|card| stock.contains(card) && !draw.contains(card)));
+
+                    let mut test = hand.clone();
+                    test.extend(&draw);
+                    assert!(test.contains_superset(), "round {}", round);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_restock() {
+        let mut deck = Deck::new();
+        let in_play = deck.draw(12);
+        deck.draw(60);
+
+        deck.restock(&in_play);
+        assert_eq!(deck.remainder(), DECK_SIZE - in_play.len());
+
+        let stock = deck.draw(DECK_SIZE);
+        assert!(stock.iter().all(|card| !in_play.contains(card)));
+    }
 }
@@ -70,20 +70,32 @@ impl ForEach<Set> for [Card] {
 impl ForEach<SuperSet> for [Card] {
     fn foreach<F>(&self, mut f: F) where F: FnMut(SuperSet) -> Iteration {
         for a in 3..self.len() {
-            for b in 2..a {
-                for c in 1..b {
-                    for d in 0..c {
-                        let quad = (self[a], self[b], self[c], self[d]);
-                        if let Some(superset) = quad.to_superset() {
-                            if f(superset) == Break {
-                                return;
-                            }
-                        }
+            if foreach_superset_from(self, a, &mut f) == Break {
+                return;
+            }
+        }
+    }
+}
+
+/// Visits the SuperSets whose highest indexed card is `cards[a]`.
+/// Splitting the search on the outermost index lets it be partitioned.
+fn foreach_superset_from<F>(cards: &[Card], a: usize, f: &mut F) -> Iteration
+    where F: FnMut(SuperSet) -> Iteration
+{
+    for b in 2..a {
+        for c in 1..b {
+            for d in 0..c {
+                let quad = (cards[a], cards[b], cards[c], cards[d]);
+                if let Some(superset) = quad.to_superset() {
+                    if f(superset) == Break {
+                        return Break;
                     }
                 }
             }
         }
     }
+
+    Continue
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -96,6 +108,8 @@ pub trait FindSets {
     fn find_all_sets(&self) -> Vec<Set>;
     fn count_sets(&self) -> usize;
     fn contains_set(&self) -> bool;
+    fn iter_sets(&self) -> Sets<'_>;
+    fn find_sets_containing(&self, card: Card) -> Vec<Set>;
 }
 
 impl FindSets for [Card] {
@@ -114,6 +128,29 @@ impl FindSets for [Card] {
     fn contains_set(&self) -> bool {
         ForEach::<Set>::contains_any(self)
     }
+
+    fn iter_sets(&self) -> Sets<'_> {
+        Sets { cards: self, combos: Combinations::new(self.len()) }
+    }
+
+    /// Returns the `Set`s that include `card`, which comes first in
+    /// each one. Empty if `card` isn't present.
+    fn find_sets_containing(&self, card: Card) -> Vec<Set> {
+        let mut sets = Vec::new();
+        if !self.contains(&card) {
+            return sets;
+        }
+
+        for (ix, &b) in self.iter().enumerate().filter(|&(_, &b)| b != card) {
+            // each set turns up twice, so only keep it for the earlier card
+            let c = (card, b).complete_set();
+            if self[ix + 1..].contains(&c) {
+                sets.extend((card, b, c).to_set());
+            }
+        }
+
+        sets
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -126,6 +163,7 @@ pub trait FindSuperSets {
     fn find_all_supersets(&self) -> Vec<SuperSet>;
     fn count_supersets(&self) -> usize;
     fn contains_superset(&self) -> bool;
+    fn iter_supersets(&self) -> SuperSets<'_>;
 }
 
 impl FindSuperSets for [Card] {
@@ -144,4 +182,196 @@ impl FindSuperSets for [Card] {
     fn contains_superset(&self) -> bool {
         ForEach::<SuperSet>::contains_any(self)
     }
+
+    fn iter_supersets(&self) -> SuperSets<'_> {
+        SuperSets { cards: self, combos: Combinations::new(self.len()) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lazy Iteration
+////////////////////////////////////////////////////////////////////////////////
+
+/// Steps through the same index combinations as the `ForEach` loops,
+/// in the same order: `[a, b, c, ...]` with `a > b > c > ...`, and the
+/// last index changing fastest.
+struct Combinations<const K: usize> {
+    len: usize,
+    next: Option<[usize; K]>,
+}
+
+impl<const K: usize> Combinations<K> {
+    fn new(len: usize) -> Combinations<K> {
+        let mut first = [0; K];
+        for (i, ix) in first.iter_mut().enumerate() {
+            *ix = K - 1 - i;
+        }
+
+        let next = if len >= K { Some(first) } else { None };
+        Combinations { len, next }
+    }
+}
+
+impl<const K: usize> Iterator for Combinations<K> {
+    type Item = [usize; K];
+
+    fn next(&mut self) -> Option<[usize; K]> {
+        let current = self.next?;
+        let mut next = current;
+
+        // bump the last index that has room, then reset the ones after it
+        let bumped = (0..K).rev().find(|&i| {
+            let limit = if i == 0 { self.len } else { next[i - 1] };
+            next[i] + 1 < limit
+        });
+
+        self.next = bumped.map(|i| {
+            next[i] += 1;
+            for j in i + 1..K {
+                next[j] = K - 1 - j;
+            }
+            next
+        });
+
+        Some(current)
+    }
+}
+
+/// Lazily finds the `Set`s in a slice of cards. See `FindSets::iter_sets`.
+pub struct Sets<'a> {
+    cards: &'a [Card],
+    combos: Combinations<3>,
+}
+
+impl<'a> Iterator for Sets<'a> {
+    type Item = Set;
+
+    fn next(&mut self) -> Option<Set> {
+        let cards = self.cards;
+        self.combos.find_map(|[a, b, c]| (cards[a], cards[b], cards[c]).to_set())
+    }
+}
+
+/// Lazily finds the `SuperSet`s in a slice of cards. See
+/// `FindSuperSets::iter_supersets`.
+pub struct SuperSets<'a> {
+    cards: &'a [Card],
+    combos: Combinations<4>,
+}
+
+impl<'a> Iterator for SuperSets<'a> {
+    type Item = SuperSet;
+
+    fn next(&mut self) -> Option<SuperSet> {
+        let cards = self.cards;
+        self.combos.find_map(|[a, b, c, d]| (cards[a], cards[b], cards[c], cards[d]).to_superset())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ParFindSuperSets
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Parallel versions of the `FindSuperSets` searches. Work is split
+/// across threads by the outermost card index.
+#[cfg(feature = "rayon")]
+pub trait ParFindSuperSets {
+    fn par_find_all_supersets(&self) -> Vec<SuperSet>;
+    fn par_count_supersets(&self) -> usize;
+}
+
+#[cfg(feature = "rayon")]
+impl ParFindSuperSets for [Card] {
+    fn par_find_all_supersets(&self) -> Vec<SuperSet> {
+        (3..self.len())
+            .into_par_iter()
+            .flat_map_iter(|a| {
+                let mut all = Vec::new();
+                foreach_superset_from(self, a, &mut |x| { all.push(x); Continue });
+                all
+            })
+            .collect()
+    }
+
+    fn par_count_supersets(&self) -> usize {
+        (3..self.len())
+            .into_par_iter()
+            .map(|a| {
+                let mut num = 0;
+                foreach_superset_from(self, a, &mut |_| { num += 1; Continue });
+                num
+            })
+            .sum()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::cards;
+
+    #[test]
+    fn check_combinations() {
+        let combos: Vec<[usize; 3]> = Combinations::new(5).collect();
+        assert_eq!(combos.len(), 10);
+        assert_eq!(combos[..4], [[2, 1, 0], [3, 1, 0], [3, 2, 0], [3, 2, 1]]);
+        assert_eq!(combos.last(), Some(&[4, 3, 2]));
+
+        assert_eq!(Combinations::<3>::new(2).count(), 0);
+        assert_eq!(Combinations::<3>::new(3).count(), 1);
+    }
+
+    #[test]
+    fn check_iter_sets() {
+        let cards = cards();
+        assert_eq!(cards.iter_sets().count(), 1080);
+
+        // same sets in the same order as the eager search
+        let eager = cards.find_all_sets();
+        assert!(cards.iter_sets().zip(&eager).all(|(a, b)| a.cards() == b.cards()));
+
+        let first = cards[..12].iter_sets().next().map(|set| set.cards());
+        assert_eq!(first, cards[..12].find_first_set().map(|set| set.cards()));
+        assert_eq!(cards[..2].iter_sets().count(), 0);
+    }
+
+    #[test]
+    fn check_find_sets_containing() {
+        let deck = cards();
+        // every card is in 40 sets: one for each of the other 80 cards, twice over
+        assert!(deck.iter().all(|&card| deck.find_sets_containing(card).len() == 40));
+
+        let table = &deck[..15];
+        for &card in table {
+            let sets = table.find_sets_containing(card);
+            assert!(sets.iter().all(|set| set.cards().0 == card));
+
+            let expected = table.find_all_sets().into_iter()
+                .filter(|set| {
+                    let (a, b, c) = set.cards();
+                    a == card || b == card || c == card
+                })
+                .count();
+            assert_eq!(sets.len(), expected);
+        }
+
+        assert!(table.find_sets_containing(deck[80]).is_empty());
+    }
+
+    #[test]
+    fn check_iter_supersets() {
+        let cards = &cards()[..20];
+        assert_eq!(cards.iter_supersets().count(), cards.count_supersets());
+
+        let eager = cards.find_all_supersets();
+        assert!(cards.iter_supersets().zip(&eager)
+                .all(|(a, b)| a.left() == b.left() && a.right() == b.right()));
+    }
 }
@@ -25,6 +25,11 @@ pub trait RectangleExt {
     fn inset(&self, dx: f64, dy: f64) -> Rectangle;
     fn offset(&self, dx: f64, dy: f64) -> Rectangle;
     fn round(&self) -> Rectangle;
+    /// Rounds to the nearest device pixel, where each unit spans
+    /// `scale` device pixels.
+    fn round_to_scale(&self, scale: f64) -> Rectangle;
+    /// The smallest integral rectangle that covers this one.
+    fn round_out(&self) -> Rectangle;
     fn contains_point(&self, x: f64, y: f64) -> bool;
 }
 
@@ -74,8 +79,50 @@ impl RectangleExt for Rectangle {
         )
     }
 
+    #[inline]
+    fn round_to_scale(&self, scale: f64) -> Rectangle {
+        let snap = |value: f64| f64::round(value * scale) / scale;
+        Rectangle::new(snap(self.x()), snap(self.y()), snap(self.width()), snap(self.height()))
+    }
+
+    #[inline]
+    fn round_out(&self) -> Rectangle {
+        let (x, y) = (f64::floor(self.x()), f64::floor(self.y()));
+        Rectangle::new(x, y, f64::ceil(self.max_x()) - x, f64::ceil(self.max_y()) - y)
+    }
+
     #[inline]
     fn contains_point(&self, x: f64, y: f64) -> bool {
         x >= self.x() && x <= self.max_x() && y >= self.y() && y <= self.max_y()
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(rect: Rectangle) -> (f64, f64, f64, f64) {
+        (rect.x(), rect.y(), rect.width(), rect.height())
+    }
+
+    #[test]
+    fn check_round_to_scale() {
+        let rect = Rectangle::new(10.3, 20.8, 100.2, 50.6);
+        assert_eq!(parts(rect.round_to_scale(1.)), parts(rect.round()));
+        assert_eq!(parts(rect.round_to_scale(2.)), (10.5, 21., 100., 50.5));
+    }
+
+    #[test]
+    fn check_round_out() {
+        let rect = Rectangle::new(10.6, 20.2, 5.5, 5.);
+        assert_eq!(parts(rect.round_out()), (10., 20., 7., 6.));
+
+        // integral rectangles are left alone
+        let exact = Rectangle::new(1., 2., 3., 4.);
+        assert_eq!(parts(exact.round_out()), parts(exact));
+    }
+}
@@ -17,23 +17,43 @@
 
 use crate::card::{Card, Color, Shading, Shape};
 use crate::geometry::RectangleExt;
+use crate::utils::clamp_float;
 use cairo::{Context, Error, Rectangle};
 use rand::{thread_rng, Rng};
 use std::f64;
 use std::f64::consts::{FRAC_PI_2, PI};
+use std::time::Duration;
 
 const CORNER_RADIUS_PERCENTAGE: f64 = 0.08;
 const BADGE_BACKGROUND_GRAY: f64 = 0.68;
 const CARD_LABEL_GRAY: f64 = 0.75;
 const PLACEHOLDER_GRAY: f64 = 0.75;
 const TABLEAU_BACKGROUND_GRAY: f64 = 0.8;
+const CELL_OUTLINE_GRAY: f64 = 0.74;
 const MOCK_STRIPE_TRANSLUCENCY: f64 = 0.4;
+const CAPTION_TRANSLUCENCY: f64 = 0.7;
+const SHADOW_OFFSET_PERCENTAGE: f64 = 0.03;
+const SHADOW_LAYERS: usize = 4;
+const SHADOW_LAYER_ALPHA: f64 = 0.05;
+const HOVER_RING_COLOR: (f64, f64, f64) = (0.25, 0.5, 0.9);
+const FOCUS_RING_COLOR: (f64, f64, f64) = (0.95, 0.55, 0.1);
+/// outline of selected cards
+pub const SELECTION_COLOR: (f64, f64, f64) = (0., 0., 0.);
+/// outline flashed around a selection that isn't a set
+pub const INVALID_SELECTION_COLOR: (f64, f64, f64) = (0.85, 0.1, 0.1);
+const DEBUG_COLOR: (f64, f64, f64) = (1., 0., 1.);
+const DEBUG_FONT_SIZE: f64 = 12.;
 
 #[inline]
 pub fn card_corner_radius(rect: Rectangle) -> f64 {
     CORNER_RADIUS_PERCENTAGE * rect.height()
 }
 
+/// Width of the selection outline, half of which is hidden beneath the card.
+fn selection_width(rect: Rectangle) -> f64 {
+    (rect.height() * 0.035).round() * 2.
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // ColorScheme
 ////////////////////////////////////////////////////////////////////////////////
@@ -42,11 +62,14 @@ pub fn card_corner_radius(rect: Rectangle) -> f64 {
 pub enum ColorScheme {
     CMYK,
     Classic,
+    /// Hand picked colors for A, B, and C, with channels in [0,255]
+    Custom { a: (u8, u8, u8), b: (u8, u8, u8), c: (u8, u8, u8) },
 }
 
 impl ColorScheme {
-    pub fn card_color(self, card: Card) -> (f64, f64, f64) {
-        let (r, g, b) = match self {
+    /// The color of `card` under this scheme, with channels in [0,255].
+    pub fn card_rgb(self, card: Card) -> (u8, u8, u8) {
+        match self {
             // This scheme is intended to be friendlier to those with
             // color vision deficiencies
             ColorScheme::CMYK => match card.color() {
@@ -60,16 +83,243 @@ impl ColorScheme {
                 Color::B => (130, 0, 140), // purple
                 Color::C => (240, 0, 0),   // red
             },
-        };
 
+            ColorScheme::Custom { a, b, c } => match card.color() {
+                Color::A => a,
+                Color::B => b,
+                Color::C => c,
+            },
+        }
+    }
+
+    /// The color of `card` under this scheme, with channels in [0,1].
+    pub fn card_color(self, card: Card) -> (f64, f64, f64) {
+        let (r, g, b) = self.card_rgb(card);
         (r as f64 / 255., g as f64 / 255., b as f64 / 255.)
     }
+
+    /// Human readable name of a color under this scheme. These must
+    /// stay in sync with the values in `card_rgb`.
+    pub fn color_name(self, color: Color) -> &'static str {
+        match self {
+            ColorScheme::CMYK => match color {
+                Color::A => "cyan",
+                Color::B => "magenta",
+                Color::C => "yellow",
+            },
+
+            ColorScheme::Classic => match color {
+                Color::A => "green",
+                Color::B => "purple",
+                Color::C => "red",
+            },
+
+            // there's no telling what the colors look like
+            ColorScheme::Custom { .. } => match color {
+                Color::A => "custom A",
+                Color::B => "custom B",
+                Color::C => "custom C",
+            },
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Color Vision Deficiency Simulation
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Deficiency {
+    /// Simulation matrices for linear RGB from Machado, Oliveira and
+    /// Fernandes (2009) at full severity.
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Deficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Deficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Approximates how an sRGB color appears to someone with the given
+/// color vision deficiency. Grays are (very nearly) unaffected.
+pub fn simulate_cvd(rgb: (f64, f64, f64), kind: Deficiency) -> (f64, f64, f64) {
+    let linear = [rgb.0, rgb.1, rgb.2].map(srgb_to_linear);
+    let m = kind.matrix();
+
+    let channel = |row: [f64; 3]| {
+        let c: f64 = row.iter().zip(&linear).map(|(a, b)| a * b).sum();
+        linear_to_srgb(clamp_float(c, (0., 1.)))
+    };
+
+    (channel(m[0]), channel(m[1]), channel(m[2]))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Contrast
+////////////////////////////////////////////////////////////////////////////////
+
+/// Colors for the tableau background and the elements drawn directly
+/// on it, which (unlike cards) have no background of their own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BadgeColors {
+    pub background: (f64, f64, f64),
+    /// Badges and the labels beneath them (counts use the background)
+    pub badge: f64,
+    pub placeholder: f64,
+    pub cell_outline: f64,
+}
+
+impl Default for BadgeColors {
+    /// The colors of the standard gray tableau.
+    fn default() -> BadgeColors {
+        let gray = TABLEAU_BACKGROUND_GRAY;
+        ensure_contrast((gray, gray, gray))
+    }
+}
+
+/// Relative luminance of an sRGB color, as defined by WCAG 2.
+pub fn relative_luminance((r, g, b): (f64, f64, f64)) -> f64 {
+    0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b)
+}
+
+/// WCAG contrast ratio between two colors, from 1 (none) to 21.
+pub fn contrast_ratio(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Contrast the standard gray tableau gives `gray`.
+fn standard_contrast(gray: f64) -> f64 {
+    let bg = TABLEAU_BACKGROUND_GRAY;
+    contrast_ratio((bg, bg, bg), (gray, gray, gray))
+}
+
+/// Finds the gray with exactly `ratio` contrast against a background
+/// of the given luminance. Darker grays are preferred, as on the
+/// standard tableau, unless the background is too dark for one.
+fn contrasting_gray(luminance: f64, ratio: f64) -> f64 {
+    let darker = (luminance + 0.05) / ratio - 0.05;
+    let lighter = (luminance + 0.05) * ratio - 0.05;
+    // a gray's luminance is its linear value on every channel
+    linear_to_srgb(if darker >= 0. { darker } else { lighter.min(1.) })
+}
+
+/// Derives grays for the badges, placeholders, and cell outlines that
+/// keep their standard contrast against `bg`. The fixed grays only
+/// work because they assume the standard 0.8 gray background; on a
+/// light or dark one they'd vanish.
+pub fn ensure_contrast(bg: (f64, f64, f64)) -> BadgeColors {
+    let luminance = relative_luminance(bg);
+    let derive = |gray| contrasting_gray(luminance, standard_contrast(gray));
+
+    BadgeColors {
+        background: bg,
+        badge: derive(BADGE_BACKGROUND_GRAY),
+        placeholder: derive(PLACEHOLDER_GRAY),
+        cell_outline: derive(CELL_OUTLINE_GRAY),
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // ContextExt
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Sets the font size to `size`, or smaller if needed for `text` to
+/// fit within `max_width`.
+fn fit_font_size(ctx: &Context, text: &str, size: f64, max_width: f64) -> Result<(), Error> {
+    ctx.set_font_size(size);
+    let width = ctx.text_extents(text)?.width();
+    if width > max_width && max_width > 0. {
+        ctx.set_font_size(size * max_width / width);
+    }
+    Ok(())
+}
+
+/// Formats a game clock as MM:SS. Minutes keep counting past 59.
+pub fn clock_text(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Draws `text` in a pill shaped badge, with `label` beneath it.
+fn draw_badge_text(
+    ctx: &Context,
+    rect: Rectangle,
+    text: &str,
+    label: &str,
+    colors: &BadgeColors,
+) -> Result<(), Error> {
+    let badge_height = rect.height() * (2. / 3.);
+    let label_height = rect.height() - badge_height;
+
+    let padding = rect.width() * 0.2;
+
+    let badge_rect = Rectangle::new(rect.x(), rect.y(), rect.width(), badge_height)
+        .inset(padding, padding / 8.);
+
+    // draw badge background
+    ctx.set_source_gray(colors.badge);
+    ctx.rounded_rect(badge_rect.round(), f64::INFINITY);
+    ctx.fill()?;
+
+    // draw the label (same gray as badge background)
+    ctx.set_font_size(label_height * 0.9);
+    let extents = ctx.text_extents(label)?;
+    let x = rect.x() + (rect.width() - extents.width()) / 2.;
+    let y = rect.max_y() - (label_height - extents.height()) / 3.;
+
+    ctx.move_to(x, y);
+    ctx.show_text(label)?;
+
+    // draw the text, shrinking it to fit inside the badge
+    let max_width = badge_rect.width() - badge_rect.height() / 2.;
+    fit_font_size(ctx, text, badge_height * 0.75, max_width)?;
+    let extents = ctx.text_extents(text)?;
+    let x = rect.x() + (rect.width() - extents.width()) / 2. - extents.x_bearing();
+    let y = badge_rect.max_y() - (badge_rect.height() - extents.height()) / 2.;
+
+    let (r, g, b) = colors.background;
+    ctx.move_to(x, y);
+    ctx.set_source_rgb(r, g, b);
+    ctx.show_text(text)?;
+    Ok(())
+}
+
 pub trait ContextExt {
     /// Perform transform operations around a pivot point.
     fn with_pivot<F>(&self, pivot: (f64, f64), f: F)
@@ -83,15 +333,43 @@ pub trait ContextExt {
     fn diamond_in_rect(&self, rect: Rectangle);
     fn squiggle_in_rect(&self, rect: Rectangle);
 
-    fn draw_badge(&self, rect: Rectangle, count: usize, label: &str) -> Result<(), Error>;
+    fn draw_badge(
+        &self,
+        rect: Rectangle,
+        count: usize,
+        label: &str,
+        colors: &BadgeColors,
+    ) -> Result<(), Error>;
+    fn draw_timer(
+        &self,
+        rect: Rectangle,
+        elapsed: Duration,
+        label: &str,
+        colors: &BadgeColors,
+    ) -> Result<(), Error>;
     fn draw_card_background(
         &self,
         rect: Rectangle,
         label: Option<&str>,
         gray: f64,
     ) -> Result<(), Error>;
-    fn draw_card_placeholder(&self, rect: Rectangle) -> Result<(), Error>;
-    fn draw_card_selection(&self, rect: Rectangle) -> Result<(), Error>;
+    fn draw_card_placeholder(&self, rect: Rectangle, colors: &BadgeColors) -> Result<(), Error>;
+    fn draw_cell_outline(&self, rect: Rectangle, colors: &BadgeColors) -> Result<(), Error>;
+    fn draw_card_selection(&self, rect: Rectangle, color: (f64, f64, f64)) -> Result<(), Error>;
+    fn draw_card_outline(&self, rect: Rectangle, alpha: f64) -> Result<(), Error>;
+    fn draw_card_hover_ring(&self, rect: Rectangle) -> Result<(), Error>;
+    fn draw_focus_ring(&self, rect: Rectangle) -> Result<(), Error>;
+    fn draw_card_tint(&self, rect: Rectangle, alpha: f64) -> Result<(), Error>;
+    fn draw_card_ghost(
+        &self,
+        card: Card,
+        rect: Rectangle,
+        alpha: f64,
+        scheme: ColorScheme,
+    ) -> Result<(), Error>;
+    fn draw_card_shadow(&self, rect: Rectangle) -> Result<(), Error>;
+    fn draw_caption(&self, rect: Rectangle, text: &str) -> Result<(), Error>;
+    fn draw_debug_rect(&self, rect: Rectangle, label: Option<&str>) -> Result<(), Error>;
     fn draw_card(
         &self,
         card: Card,
@@ -232,40 +510,24 @@ impl ContextExt for Context {
         self.close_path();
     }
 
-    fn draw_badge(&self, rect: Rectangle, count: usize, label: &str) -> Result<(), Error> {
-        let badge_height = rect.height() * (2. / 3.);
-        let label_height = rect.height() - badge_height;
-        let count_string = count.to_string();
-
-        let padding = rect.width() * 0.2;
-
-        let badge_rect = Rectangle::new(rect.x(), rect.y(), rect.width(), badge_height)
-            .inset(padding, padding / 8.);
-
-        // draw badge background
-        self.set_source_gray(BADGE_BACKGROUND_GRAY);
-        self.rounded_rect(badge_rect.round(), f64::INFINITY);
-        self.fill()?;
-
-        // draw the label (same gray as badge background)
-        self.set_font_size(label_height * 0.9);
-        let extents = self.text_extents(label)?;
-        let x = rect.x() + (rect.width() - extents.width()) / 2.;
-        let y = rect.max_y() - (label_height - extents.height()) / 3.;
-
-        self.move_to(x, y);
-        self.show_text(label)?;
-
-        // draw count
-        self.set_font_size(badge_height * 0.75);
-        let extents = self.text_extents(&count_string)?;
-        let x = rect.x() + (rect.width() - extents.width()) / 2. - extents.x_bearing();
-        let y = badge_rect.max_y() - (badge_rect.height() - extents.height()) / 2.;
+    fn draw_badge(
+        &self,
+        rect: Rectangle,
+        count: usize,
+        label: &str,
+        colors: &BadgeColors,
+    ) -> Result<(), Error> {
+        draw_badge_text(self, rect, &count.to_string(), label, colors)
+    }
 
-        self.move_to(x, y);
-        self.set_source_gray(TABLEAU_BACKGROUND_GRAY);
-        self.show_text(&count_string)?;
-        Ok(())
+    fn draw_timer(
+        &self,
+        rect: Rectangle,
+        elapsed: Duration,
+        label: &str,
+        colors: &BadgeColors,
+    ) -> Result<(), Error> {
+        draw_badge_text(self, rect, &clock_text(elapsed), label, colors)
     }
 
     fn draw_card_background(
@@ -290,19 +552,132 @@ impl ContextExt for Context {
         Ok(())
     }
 
-    fn draw_card_placeholder(&self, rect: Rectangle) -> Result<(), Error> {
-        self.draw_card_background(rect, None, PLACEHOLDER_GRAY)
+    fn draw_card_placeholder(&self, rect: Rectangle, colors: &BadgeColors) -> Result<(), Error> {
+        self.draw_card_background(rect, None, colors.placeholder)
     }
 
-    fn draw_card_selection(&self, rect: Rectangle) -> Result<(), Error> {
-        let height = rect.height();
-        let corner_radius = card_corner_radius(rect);
-        let selection_width = (height * 0.035).round() * 2.;
+    /// Faintly marks the bounds of a tableau cell.
+    fn draw_cell_outline(&self, rect: Rectangle, colors: &BadgeColors) -> Result<(), Error> {
+        self.rounded_rect(rect, card_corner_radius(rect));
+        self.set_source_gray(colors.cell_outline);
+        self.set_line_width(1.);
+        self.stroke()
+    }
 
-        self.rounded_rect(rect, corner_radius);
-        self.set_source_gray(0.);
-        self.set_line_width(selection_width);
+    /// Draws the selection outline in the given color.
+    fn draw_card_selection(&self, rect: Rectangle, color: (f64, f64, f64)) -> Result<(), Error> {
+        let (r, g, b) = color;
+        self.rounded_rect(rect, card_corner_radius(rect));
+        self.set_source_rgb(r, g, b);
+        self.set_line_width(selection_width(rect));
+        self.stroke()
+    }
+
+    /// Draws the selection outline with the given opacity.
+    fn draw_card_outline(&self, rect: Rectangle, alpha: f64) -> Result<(), Error> {
+        let (r, g, b) = SELECTION_COLOR;
+        self.rounded_rect(rect, card_corner_radius(rect));
+        self.set_source_rgba(r, g, b, alpha);
+        self.set_line_width(selection_width(rect));
+        self.stroke()
+    }
+
+    /// Rings the card beneath the mouse in an accent color. The ring is
+    /// half as wide as the selection outline, so the two are distinct.
+    fn draw_card_hover_ring(&self, rect: Rectangle) -> Result<(), Error> {
+        let (r, g, b) = HOVER_RING_COLOR;
+        let ring_width = (rect.height() * 0.035).round();
+
+        self.rounded_rect(rect, card_corner_radius(rect));
+        self.set_source_rgb(r, g, b);
+        self.set_line_width(ring_width);
+        self.stroke()
+    }
+
+    /// Marks the card with the keyboard focus using a dashed ring just
+    /// outside the selection outline, so it shows on selected cards too.
+    fn draw_focus_ring(&self, rect: Rectangle) -> Result<(), Error> {
+        let (r, g, b) = FOCUS_RING_COLOR;
+        let ring_width = (rect.height() * 0.035).round();
+        let outset = selection_width(rect) / 2. + ring_width;
+
+        self.rounded_rect(rect.inset(-outset, -outset), card_corner_radius(rect) + outset);
+        self.set_source_rgb(r, g, b);
+        self.set_line_width(ring_width);
+        self.set_dash(&[ring_width * 2., ring_width], 0.);
+        let result = self.stroke();
+        self.set_dash(&[], 0.);
+        result
+    }
+
+    /// Darkens a card that has already been drawn.
+    fn draw_card_tint(&self, rect: Rectangle, alpha: f64) -> Result<(), Error> {
+        self.rounded_rect(rect, card_corner_radius(rect));
+        self.set_source_rgba(0., 0., 0., alpha);
+        self.fill()
+    }
+
+    /// Draws a faint, translucent copy of `card`.
+    fn draw_card_ghost(
+        &self,
+        card: Card,
+        rect: Rectangle,
+        alpha: f64,
+        scheme: ColorScheme,
+    ) -> Result<(), Error> {
+        self.push_group();
+        self.draw_card(card, rect, None, scheme)?;
+        self.pop_group_to_source()?;
+        self.paint_with_alpha(alpha)
+    }
+
+    /// Approximates a soft shadow beneath a card by stacking translucent
+    /// rounded rects that spread a little further each layer. The shadow
+    /// extends at most twice the offset beyond `rect`.
+    fn draw_card_shadow(&self, rect: Rectangle) -> Result<(), Error> {
+        let offset = rect.height() * SHADOW_OFFSET_PERCENTAGE;
+        let radius = card_corner_radius(rect);
+        let shifted = Rectangle::new(rect.x() + offset, rect.y() + offset, rect.width(), rect.height());
+
+        self.set_source_rgba(0., 0., 0., SHADOW_LAYER_ALPHA);
+        for layer in 0..SHADOW_LAYERS {
+            let spread = offset * layer as f64 / SHADOW_LAYERS as f64;
+            self.rounded_rect(shifted.inset(-spread, -spread), radius + spread);
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    /// Draws `text` centered on a translucent banner filling `rect`.
+    fn draw_caption(&self, rect: Rectangle, text: &str) -> Result<(), Error> {
+        self.set_source_rgba(0., 0., 0., CAPTION_TRANSLUCENCY);
+        self.rounded_rect(rect.round(), rect.height() / 4.);
+        self.fill()?;
+
+        self.set_font_size(rect.height() * 0.5);
+        let extents = self.text_extents(text)?;
+        let x = rect.x() + (rect.width() - extents.width()) / 2. - extents.x_bearing();
+        let y = rect.max_y() - (rect.height() - extents.height()) / 2.;
+
+        self.move_to(x, y);
+        self.set_source_gray(1.0);
+        self.show_text(text)?;
+        Ok(())
+    }
+
+    /// Outlines `rect` with a hairline, labeled in its top left corner.
+    fn draw_debug_rect(&self, rect: Rectangle, label: Option<&str>) -> Result<(), Error> {
+        let (r, g, b) = DEBUG_COLOR;
+        self.set_source_rgb(r, g, b);
+        self.set_line_width(1.);
+        self.rectangle(rect.x(), rect.y(), rect.width(), rect.height());
         self.stroke()?;
+
+        if let Some(label) = label {
+            self.set_font_size(DEBUG_FONT_SIZE);
+            self.move_to(rect.x() + 2., rect.y() + DEBUG_FONT_SIZE);
+            self.show_text(label)?;
+        }
         Ok(())
     }
 
@@ -391,3 +766,166 @@ impl ContextExt for Context {
         Ok(())
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::cards;
+    use cairo::{Format, ImageSurface};
+
+    #[test]
+    fn check_clock_text() {
+        assert_eq!(clock_text(Duration::from_secs(0)), "00:00");
+        assert_eq!(clock_text(Duration::from_millis(59_999)), "00:59");
+        assert_eq!(clock_text(Duration::from_secs(61)), "01:01");
+        assert_eq!(clock_text(Duration::from_secs(100 * 60 + 5)), "100:05");
+    }
+
+    #[test]
+    fn check_fit_font_size() {
+        let surface = ImageSurface::create(Format::ARgb32, 200, 100).unwrap();
+        let ctx = Context::new(&surface).unwrap();
+        let max_width = 60.;
+
+        // short counts keep the requested size
+        fit_font_size(&ctx, "7", 45., max_width).unwrap();
+        let fitted = ctx.text_extents("7").unwrap().width();
+        ctx.set_font_size(45.);
+        assert_eq!(fitted, ctx.text_extents("7").unwrap().width());
+
+        // four digits would overflow at that size, so they shrink
+        assert!(ctx.text_extents("1234").unwrap().width() > max_width);
+        fit_font_size(&ctx, "1234", 45., max_width).unwrap();
+        assert!(ctx.text_extents("1234").unwrap().width() <= max_width + 0.5);
+
+        let rect = Rectangle::new(0., 0., 90., 60.);
+        ctx.draw_badge(rect, 1234, "found", &BadgeColors::default()).unwrap();
+    }
+
+    #[test]
+    fn check_color_names() {
+        use crate::card::Color::*;
+
+        let cmyk: Vec<_> = [A, B, C].iter().map(|&c| ColorScheme::CMYK.color_name(c)).collect();
+        assert_eq!(cmyk, ["cyan", "magenta", "yellow"]);
+
+        let classic: Vec<_> = [A, B, C].iter().map(|&c| ColorScheme::Classic.color_name(c)).collect();
+        assert_eq!(classic, ["green", "purple", "red"]);
+    }
+
+    #[test]
+    fn check_ensure_contrast() {
+        let standard = BadgeColors::default();
+        assert!((standard.badge - BADGE_BACKGROUND_GRAY).abs() < 1e-9);
+        assert!((standard.placeholder - PLACEHOLDER_GRAY).abs() < 1e-9);
+        assert!((standard.cell_outline - CELL_OUTLINE_GRAY).abs() < 1e-9);
+
+        let gray = |g: f64| (g, g, g);
+        let backgrounds = [
+            (0., 0., 0.),
+            (0.1, 0.1, 0.3),
+            (0.5, 0.2, 0.2),
+            (1., 1., 0.8),
+            (1., 1., 1.),
+        ];
+
+        for &bg in &backgrounds {
+            let colors = ensure_contrast(bg);
+            let derived = [
+                (colors.badge, BADGE_BACKGROUND_GRAY),
+                (colors.placeholder, PLACEHOLDER_GRAY),
+                (colors.cell_outline, CELL_OUTLINE_GRAY),
+            ];
+
+            for &(derived, standard) in &derived {
+                assert!((0. ..=1.).contains(&derived));
+                let ratio = contrast_ratio(bg, gray(derived));
+                assert!(ratio >= standard_contrast(standard) - 1e-9);
+            }
+        }
+
+        // badges go light on dark backgrounds and dark on light ones
+        assert!(ensure_contrast(gray(0.)).badge > 0.1);
+        assert!(ensure_contrast(gray(1.)).badge < 0.9);
+    }
+
+    #[test]
+    fn check_card_rgb() {
+        let card = cards().into_iter().find(|card| card.color() == Color::B).unwrap();
+        assert_eq!(ColorScheme::CMYK.card_rgb(card), (192, 0, 192));
+        assert_eq!(ColorScheme::Classic.card_rgb(card), (130, 0, 140));
+
+        // the unit values are the same colors
+        for card in cards() {
+            let (r, g, b) = ColorScheme::Classic.card_rgb(card);
+            let expected = (r as f64 / 255., g as f64 / 255., b as f64 / 255.);
+            assert_eq!(ColorScheme::Classic.card_color(card), expected);
+        }
+    }
+
+    #[test]
+    fn check_custom_scheme() {
+        let scheme = ColorScheme::Custom { a: (0, 0, 255), b: (255, 128, 0), c: (40, 40, 40) };
+        for card in cards() {
+            let expected = match card.color() {
+                Color::A => (0, 0, 255),
+                Color::B => (255, 128, 0),
+                Color::C => (40, 40, 40),
+            };
+            assert_eq!(scheme.card_rgb(card), expected);
+        }
+
+        // survives a trip through the config file
+        let yaml = serde_yaml::to_string(&scheme).unwrap();
+        assert_eq!(serde_yaml::from_str::<ColorScheme>(&yaml).unwrap(), scheme);
+        assert_eq!(serde_yaml::from_str::<ColorScheme>("CMYK").unwrap(), ColorScheme::CMYK);
+    }
+
+    fn distance(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+    }
+
+    /// Smallest distance between any two card colors under a deficiency.
+    fn min_separation(scheme: ColorScheme, kind: Deficiency) -> f64 {
+        let colors: Vec<_> = [Color::A, Color::B, Color::C].iter()
+            .map(|&color| cards().into_iter().find(|card| card.color() == color).unwrap())
+            .map(|card| simulate_cvd(scheme.card_color(card), kind))
+            .collect();
+
+        distance(colors[0], colors[1])
+            .min(distance(colors[0], colors[2]))
+            .min(distance(colors[1], colors[2]))
+    }
+
+    #[test]
+    fn check_simulate_cvd() {
+        use self::Deficiency::*;
+
+        // pure red under protanopia, straight from the published matrix
+        let (r, g, b) = simulate_cvd((1., 0., 0.), Protanopia);
+        assert!((r - linear_to_srgb(0.152286)).abs() < 1e-9);
+        assert!((g - linear_to_srgb(0.114503)).abs() < 1e-9);
+        assert_eq!(b, 0.);
+
+        for &kind in &[Protanopia, Deuteranopia, Tritanopia] {
+            // grays are preserved
+            for &gray in &[0., 0.25, 0.5, 1.] {
+                let simulated = simulate_cvd((gray, gray, gray), kind);
+                assert!(distance(simulated, (gray, gray, gray)) < 1e-3);
+            }
+
+            // the CMYK colors remain clearly distinguishable
+            assert!(min_separation(ColorScheme::CMYK, kind) > 0.3);
+        }
+
+        // ...and hold up better than the classic red and green
+        for &kind in &[Protanopia, Deuteranopia] {
+            assert!(min_separation(ColorScheme::CMYK, kind) >
+                    min_separation(ColorScheme::Classic, kind));
+        }
+    }
+}
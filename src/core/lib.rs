@@ -13,21 +13,42 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+#[cfg(feature = "rendering")]
 extern crate cairo;
 extern crate num_traits;
 extern crate rand;
+extern crate rand_chacha;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+extern crate serde;
 #[macro_use] extern crate serde_derive;
+#[cfg(feature = "wasm")]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
 
 // model
 pub mod card;
 pub mod deck;
 pub mod find;
+pub mod lookup;
 pub mod pair_iter;
+pub mod session;
 pub mod shuffle;
+pub mod solve;
 
 // rendering
+#[cfg(feature = "rendering")]
 pub mod geometry;
+#[cfg(feature = "rendering")]
 pub mod graphics;
 
 // misc
+pub mod log;
 pub mod utils;
+
+// bindings
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use crate::lookup::SetLookup;
@@ -0,0 +1,124 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Minimal leveled logging to stderr.
+//!
+//! Set `MARMOSET_LOG` to `off`, `error`, `warn`, `info`, or `debug` to
+//! choose how much is printed. Warnings and errors are printed by default.
+
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Name of the environment variable that selects the log level.
+pub const LOG_VAR: &str = "MARMOSET_LOG";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level { Error, Warn, Info, Debug }
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parses a log level setting. Returns None if logging is off, and
+/// falls back to the default for unrecognized settings.
+pub fn parse_level(setting: &str) -> Option<Level> {
+    match setting.trim().to_lowercase().as_str() {
+        "off" | "none" => None,
+        "error" => Some(Level::Error),
+        "info" => Some(Level::Info),
+        "debug" => Some(Level::Debug),
+        _ => Some(Level::Warn),
+    }
+}
+
+/// The most verbose level that gets printed (read once from the environment).
+pub fn max_level() -> Option<Level> {
+    static MAX_LEVEL: OnceLock<Option<Level>> = OnceLock::new();
+
+    *MAX_LEVEL.get_or_init(|| match env::var(LOG_VAR) {
+        Ok(setting) => parse_level(&setting),
+        Err(_) => Some(Level::Warn),
+    })
+}
+
+pub fn enabled(level: Level) -> bool {
+    max_level().is_some_and(|max| level <= max)
+}
+
+/// Prints a message if `level` is enabled. Use the `log_*!` macros instead.
+pub fn log(level: Level, args: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("marmoset [{}] {}", level, args);
+    }
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Error, format_args!($($arg)*)) }
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Warn, format_args!($($arg)*)) }
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Info, format_args!($($arg)*)) }
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::log::log($crate::log::Level::Debug, format_args!($($arg)*)) }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_level() {
+        assert_eq!(parse_level("off"), None);
+        assert_eq!(parse_level("error"), Some(Level::Error));
+        assert_eq!(parse_level(" Debug\n"), Some(Level::Debug));
+        assert_eq!(parse_level("info"), Some(Level::Info));
+
+        // anything else gets the default
+        assert_eq!(parse_level("warn"), Some(Level::Warn));
+        assert_eq!(parse_level("verbose"), Some(Level::Warn));
+    }
+
+    #[test]
+    fn check_level_order() {
+        // more verbose levels compare greater
+        assert!(Level::Error < Level::Warn);
+        assert!(Level::Warn < Level::Info);
+        assert!(Level::Info < Level::Debug);
+        assert_eq!(Level::Warn.to_string(), "warn");
+    }
+}
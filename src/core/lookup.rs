@@ -0,0 +1,108 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Lookup table for completing Sets by card index.
+//!
+//! There is an isomorphism between a `Card` and its index, so code
+//! that needs to test many combinations can work with indices and
+//! consult this table instead of packing and unpacking cards. The
+//! table is immutable once built, so a single instance can be shared
+//! freely between threads.
+
+use crate::card::CompleteSet;
+use crate::deck::cards;
+use crate::pair_iter::PairIter;
+
+const DECK_SIZE: usize = 81;
+
+pub struct SetLookup {
+    table: [[u8; DECK_SIZE]; DECK_SIZE],
+}
+
+impl SetLookup {
+    pub fn new() -> SetLookup {
+        let cards = cards();
+        let mut table = [[0; DECK_SIZE]; DECK_SIZE];
+
+        for (&a, &b) in (0..DECK_SIZE).collect::<Vec<_>>().pairs() {
+            let c = (cards[a], cards[b]).complete_set().index() as u8;
+            table[a][b] = c;
+            // `complete_set()` is commutative
+            table[b][a] = c;
+        }
+
+        // a card completes a Set with itself
+        for (ix, row) in table.iter_mut().enumerate() {
+            row[ix] = ix as u8;
+        }
+
+        SetLookup { table }
+    }
+
+    /// Returns the index of the card that completes the Set formed
+    /// with the cards at indices `a` and `b`.
+    #[inline]
+    pub fn complete(&self, a: usize, b: usize) -> usize {
+        self.table[a][b] as usize
+    }
+
+    /// Returns true if the cards at the given indices form a Set.
+    #[inline]
+    pub fn is_set(&self, a: usize, b: usize, c: usize) -> bool {
+        self.complete(a, b) == c
+    }
+}
+
+impl Default for SetLookup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::ToSet;
+
+    #[test]
+    fn check_lookup_matches_to_set() {
+        let lookup = SetLookup::new();
+        let cards = cards();
+
+        // a deterministic sample of triples spread across the deck
+        for a in (0..DECK_SIZE).step_by(5) {
+            for b in (0..DECK_SIZE).step_by(3) {
+                for c in (0..DECK_SIZE).step_by(7) {
+                    if a == b || b == c || a == c {
+                        continue;
+                    }
+
+                    let triple = (cards[a], cards[b], cards[c]);
+                    assert_eq!(lookup.is_set(a, b, c), triple.to_set().is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_lookup_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SetLookup>();
+    }
+}
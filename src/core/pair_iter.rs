@@ -82,7 +82,7 @@ mod tests {
         let two = [0, 1];
         assert_eq!(two.pairs().count(), 1);
 
-        if let Some((&a, &b)) = two.pairs().nth(0) {
+        if let Some((&a, &b)) = two.pairs().next() {
             assert_eq!((a, b), (1, 0));
         } else {
             panic!();
@@ -0,0 +1,281 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A headless game of classic Set, for front ends that do their own drawing.
+//!
+//! The session deals 12 cards, replaces a taken `Set` while fewer than
+//! 12 cards remain, and deals 3 more on request. The deck order comes
+//! from a seed, so the same seed always plays out the same way.
+
+use crate::card::{Card, ToSet};
+use crate::deck::Deck;
+use crate::find::FindSets;
+
+const INITIAL_DEAL: usize = 12;
+const DEAL_SIZE: usize = 3;
+
+/// The result of selecting a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pick {
+    Selected,
+    Deselected,
+    /// The selection completed a `Set`, which was taken.
+    Set,
+    /// The selection was 3 cards that aren't a `Set`, so it was cleared.
+    NotSet,
+}
+
+/// Everything a front end needs to draw the game.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub tableau: Vec<Card>,
+    pub selected: Vec<Card>,
+    pub score: usize,
+    pub remaining: usize,
+    pub sets_on_table: usize,
+    pub over: bool,
+}
+
+pub struct GameSession {
+    deck: Deck,
+    tableau: Vec<Card>,
+    selected: Vec<Card>,
+    score: usize,
+}
+
+impl GameSession {
+    /// Starts a game whose deck order is determined by `seed`.
+    pub fn new(seed: u64) -> GameSession {
+        let mut deck = Deck::new_seeded(seed);
+        let tableau = deck.draw(INITIAL_DEAL);
+        GameSession { deck, tableau, selected: vec![], score: 0 }
+    }
+
+    pub fn tableau(&self) -> &[Card] {
+        &self.tableau
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// The game is over once the deck is empty and no `Set` remains.
+    pub fn is_over(&self) -> bool {
+        self.deck.is_empty() && !self.tableau.contains_set()
+    }
+
+    /// Toggles the card at `index` in the tableau. Returns None if
+    /// there's no card there.
+    pub fn select(&mut self, index: usize) -> Option<Pick> {
+        let card = *self.tableau.get(index)?;
+
+        if let Some(ix) = self.selected.iter().position(|&c| c == card) {
+            self.selected.remove(ix);
+            return Some(Pick::Deselected);
+        }
+
+        self.selected.push(card);
+        if self.selected.len() < DEAL_SIZE {
+            return Some(Pick::Selected);
+        }
+
+        let (a, b, c) = (self.selected[0], self.selected[1], self.selected[2]);
+        self.selected.clear();
+
+        if (a, b, c).to_set().is_some() {
+            self.take(&[a, b, c]);
+            Some(Pick::Set)
+        } else {
+            Some(Pick::NotSet)
+        }
+    }
+
+    /// Deals 3 more cards. Returns false if the deck is empty.
+    pub fn deal(&mut self) -> bool {
+        if self.deck.is_empty() {
+            return false;
+        }
+
+        // doctor the deck so that 18 cards always contain a `Set`
+        let mut cards = self.deck.deal(&self.tableau, DEAL_SIZE, DEAL_SIZE, true);
+        self.tableau.append(&mut cards);
+        true
+    }
+
+    /// Returns a card from a `Set` on the table, if there is one. The
+    /// first `Set` found is used, so repeated hints agree.
+    pub fn hint(&self) -> Option<Card> {
+        self.tableau.find_first_set().map(|set| set.cards().0)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tableau: self.tableau.clone(),
+            selected: self.selected.clone(),
+            score: self.score,
+            remaining: self.deck.remainder(),
+            sets_on_table: self.tableau.count_sets(),
+            over: self.is_over(),
+        }
+    }
+
+    /// Removes a taken `Set`. While the table is short, replacements
+    /// go into the same positions so the layout doesn't shift.
+    fn take(&mut self, cards: &[Card]) {
+        self.score += 1;
+
+        let replace = self.tableau.len() <= INITIAL_DEAL;
+        let mut replacements = if replace { self.deck.draw(DEAL_SIZE) } else { vec![] };
+
+        for &card in cards {
+            let ix = self.tableau.iter().position(|&c| c == card).unwrap();
+            match replacements.pop() {
+                Some(new_card) => self.tableau[ix] = new_card,
+                None => { self.tableau.remove(ix); }
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tableau indices of the first `Set` on the table.
+    fn find_set(session: &GameSession) -> Vec<usize> {
+        let (a, b, c) = session.tableau().find_first_set().unwrap().cards();
+        [a, b, c].iter()
+            .map(|card| session.tableau().iter().position(|c| c == card).unwrap())
+            .collect()
+    }
+
+    /// Deals until there's a `Set` on the table.
+    fn ensure_set(session: &mut GameSession) {
+        while !session.tableau().contains_set() {
+            assert!(session.deal());
+        }
+    }
+
+    #[test]
+    fn check_seeded_sessions_match() {
+        let a = GameSession::new(7);
+        let b = GameSession::new(7);
+        assert_eq!(a.snapshot(), b.snapshot());
+        assert_eq!(a.tableau().len(), INITIAL_DEAL);
+        assert_eq!(a.snapshot().remaining, 81 - INITIAL_DEAL);
+    }
+
+    #[test]
+    fn check_seeded_sessions_play_out_the_same() {
+        let play = |seed| {
+            let mut session = GameSession::new(seed);
+            let mut snapshots = vec![session.snapshot()];
+            while !session.is_over() {
+                if session.tableau().contains_set() {
+                    for ix in find_set(&session) {
+                        session.select(ix);
+                    }
+                } else {
+                    assert!(session.deal());
+                }
+                snapshots.push(session.snapshot());
+            }
+            snapshots
+        };
+
+        assert_eq!(play(8), play(8));
+        assert_ne!(play(8), play(9));
+    }
+
+    #[test]
+    fn check_select() {
+        let mut session = GameSession::new(1);
+        ensure_set(&mut session);
+        let cards = session.tableau().len();
+        let set = find_set(&session);
+
+        assert_eq!(session.select(set[0]), Some(Pick::Selected));
+        assert_eq!(session.select(set[0]), Some(Pick::Deselected));
+        assert_eq!(session.select(cards), None);
+
+        for &ix in &set[..2] {
+            assert_eq!(session.select(ix), Some(Pick::Selected));
+        }
+        assert_eq!(session.snapshot().selected.len(), 2);
+        assert_eq!(session.select(set[2]), Some(Pick::Set));
+
+        // a short table is topped up in place, anything more shrinks
+        let expected = if cards <= INITIAL_DEAL { cards } else { cards - DEAL_SIZE };
+        assert_eq!(session.score(), 1);
+        assert_eq!(session.tableau().len(), expected);
+        assert!(session.snapshot().selected.is_empty());
+    }
+
+    #[test]
+    fn check_not_a_set() {
+        let mut session = GameSession::new(2);
+        let miss = (0..session.tableau().len())
+            .flat_map(|i| (i + 1..session.tableau().len()).map(move |j| (i, j)))
+            .find_map(|(i, j)| {
+                let t = session.tableau();
+                (j + 1..t.len())
+                    .find(|&k| (t[i], t[j], t[k]).to_set().is_none())
+                    .map(|k| [i, j, k])
+            })
+            .unwrap();
+
+        session.select(miss[0]);
+        session.select(miss[1]);
+        assert_eq!(session.select(miss[2]), Some(Pick::NotSet));
+        assert_eq!(session.score(), 0);
+        assert!(session.snapshot().selected.is_empty());
+    }
+
+    #[test]
+    fn check_deal_and_hint() {
+        let mut session = GameSession::new(3);
+        assert!(session.deal());
+        assert_eq!(session.tableau().len(), INITIAL_DEAL + DEAL_SIZE);
+
+        ensure_set(&mut session);
+        let hint = session.hint().unwrap();
+        assert!(session.tableau().contains(&hint));
+        assert_eq!(session.hint(), Some(hint));
+    }
+
+    #[test]
+    fn check_play_to_the_end() {
+        let mut session = GameSession::new(4);
+        while !session.is_over() {
+            if session.tableau().contains_set() {
+                for ix in find_set(&session) {
+                    // indices are stable until the third card is taken
+                    session.select(ix);
+                }
+            } else {
+                assert!(session.deal());
+            }
+        }
+
+        let snapshot = session.snapshot();
+        assert_eq!(snapshot.remaining, 0);
+        assert_eq!(snapshot.score * 3 + snapshot.tableau.len(), 81);
+        assert!(!session.deal());
+    }
+}
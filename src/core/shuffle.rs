@@ -23,11 +23,17 @@ use rand::{thread_rng, Rng};
 
 pub trait Shuffle {
     fn shuffle(&mut self);
+    /// Shuffle using the given random number generator. A seeded
+    /// generator produces a repeatable order.
+    fn shuffle_with<R: Rng + ?Sized>(&mut self, rng: &mut R);
 }
 
 impl<T> Shuffle for [T] {
     fn shuffle(&mut self) {
-        let mut rng = thread_rng();
+        self.shuffle_with(&mut thread_rng());
+    }
+
+    fn shuffle_with<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         let n = self.len();
 
         for i in (1..n).rev() {
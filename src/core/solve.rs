@@ -0,0 +1,300 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Search for a sequence of `Set`s that clears the board.
+//!
+//! The search follows the dealing rules of the game, as set out in
+//! `Dealing`: after a `Set` is taken, the board is replenished as the
+//! policy says, and another batch is dealt whenever the board is stuck. When 15 stuck cards would be topped up with 3 cards
+//! that still contain no `Set`, the game doctors the deck instead. The
+//! search abandons those lines, so that every deal in a solution
+//! happens exactly as it would in play.
+
+use crate::card::Card;
+use crate::deck::ReplenishPolicy;
+use crate::find::FindSets;
+use std::collections::HashSet;
+
+const SET_SIZE: usize = 3;
+
+/// Positions examined before the search gives up.
+const SEARCH_BUDGET: usize = 20_000;
+
+/// How the game deals, which the search has to follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dealing {
+    /// Cards dealt at the start, which replenishing works towards
+    pub initial_deal: usize,
+    /// Cards dealt when the board is stuck, in whole sets
+    pub deal_batch: usize,
+    pub replenish: ReplenishPolicy,
+    /// Cards the tableau has room for
+    pub max_board: usize,
+    /// Whether the deck is doctored so that 18 cards contain a `Set`
+    pub guarantee_sets: bool,
+}
+
+impl Default for Dealing {
+    fn default() -> Dealing {
+        Dealing {
+            initial_deal: 12,
+            deal_batch: SET_SIZE,
+            replenish: ReplenishPolicy::IfBelowInitial,
+            max_board: 18,
+            guarantee_sets: true,
+        }
+    }
+}
+
+/// Finds `Set`s that clear `board`, dealing from `deck` (in the order
+/// that cards are dealt) as the game would. Returns None when no such
+/// sequence was found within the search budget. The search is
+/// exhaustive for all but the longest games, so this almost always
+/// means that no full clear exists.
+pub fn solve(board: &[Card], deck: &[Card], dealing: Dealing) -> Option<Vec<[Card; 3]>> {
+    let mut search = Search {
+        deck,
+        dealing,
+        visited: HashSet::new(),
+        budget: SEARCH_BUDGET,
+        path: vec![],
+    };
+
+    let mut board = board.to_owned();
+    let mut dealt = 0;
+    if !search.deal_while_stuck(&mut board, &mut dealt) {
+        return None;
+    }
+
+    if search.clear(board, dealt) {
+        Some(search.path)
+    } else {
+        None
+    }
+}
+
+struct Search<'a> {
+    deck: &'a [Card],
+    dealing: Dealing,
+    /// Positions already known to be dead ends
+    visited: HashSet<(u128, usize)>,
+    budget: usize,
+    path: Vec<[Card; 3]>,
+}
+
+impl<'a> Search<'a> {
+    fn clear(&mut self, board: Vec<Card>, dealt: usize) -> bool {
+        if board.is_empty() && dealt == self.deck.len() {
+            return true;
+        }
+
+        let key = (mask(&board), dealt);
+        if self.budget == 0 || self.visited.contains(&key) {
+            return false;
+        }
+        self.budget -= 1;
+
+        for set in board.find_all_sets() {
+            let (a, b, c) = set.cards();
+            let mut next: Vec<Card> = board.iter()
+                .cloned()
+                .filter(|&card| card != a && card != b && card != c)
+                .collect();
+            let mut next_dealt = dealt;
+
+            if self.replenish(&mut next, &mut next_dealt)
+                && self.deal_while_stuck(&mut next, &mut next_dealt)
+            {
+                self.path.push([a, b, c]);
+                if self.clear(next, next_dealt) {
+                    return true;
+                }
+                self.path.pop();
+            }
+        }
+
+        self.visited.insert(key);
+        false
+    }
+
+    /// Deals `n` cards the way `Deck::deal` does, a set's worth at a
+    /// time for larger batches. Returns false if the guarantee would
+    /// doctor one of those draws.
+    fn deal(&self, board: &mut Vec<Card>, dealt: &mut usize, n: usize) -> bool {
+        let chunk = if n > SET_SIZE && n.is_multiple_of(SET_SIZE) { SET_SIZE } else { n };
+
+        for _ in 0..n / chunk {
+            let remaining = self.deck.len() - *dealt;
+            let doctored = self.dealing.guarantee_sets && chunk == SET_SIZE
+                && board.len() == 15 && remaining >= 6;
+
+            let end = *dealt + chunk.min(remaining);
+            board.extend_from_slice(&self.deck[*dealt..end]);
+            *dealt = end;
+
+            if doctored && !board.contains_set() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Deals replacements for a taken `Set` the way `GameState` does.
+    /// Returns false if the guarantee would doctor the deal.
+    fn replenish(&self, board: &mut Vec<Card>, dealt: &mut usize) -> bool {
+        let shortfall = self.dealing.initial_deal.saturating_sub(board.len());
+        if shortfall == 0 {
+            return true;
+        }
+
+        match self.dealing.replenish {
+            ReplenishPolicy::IfBelowInitial => self.deal(board, dealt, SET_SIZE),
+            ReplenishPolicy::AlwaysToInitial => self.deal(board, dealt, shortfall),
+            ReplenishPolicy::Never => true,
+        }
+    }
+
+    /// The batch dealt to a stuck board, which like the game's is
+    /// rounded down to fit the room that's left.
+    fn batch(&self, board: &[Card]) -> usize {
+        let batch = (self.dealing.deal_batch / SET_SIZE).max(1) * SET_SIZE;
+        let room = self.dealing.max_board.saturating_sub(board.len()) / SET_SIZE * SET_SIZE;
+        batch.min(room.max(SET_SIZE))
+    }
+
+    /// Deals more cards until the board has a `Set`, returning false
+    /// if the game would leave this line (see the module docs). An
+    /// empty board is stuck too, since some policies never replenish.
+    fn deal_while_stuck(&self, board: &mut Vec<Card>, dealt: &mut usize) -> bool {
+        while !board.contains_set() && *dealt < self.deck.len() {
+            if board.len() >= self.dealing.max_board {
+                return false;
+            }
+
+            let n = self.batch(board);
+            if !self.deal(board, dealt, n) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Packs a board into a bit per card, since the order doesn't matter.
+fn mask(board: &[Card]) -> u128 {
+    board.iter().fold(0, |mask, card| mask | (1 << card.index()))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::cards;
+    use crate::shuffle::Shuffle;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Plays the solution, checking every step against the rules.
+    fn replay(board: &[Card], deck: &[Card], dealing: Dealing, steps: &[[Card; 3]]) {
+        let search = Search { deck, dealing, visited: HashSet::new(), budget: 0, path: vec![] };
+        let mut board = board.to_owned();
+        let mut dealt = 0;
+        assert!(search.deal_while_stuck(&mut board, &mut dealt));
+
+        for step in steps {
+            assert!(step.iter().all(|card| board.contains(card)));
+            assert!(board.find_all_sets().iter().any(|set| {
+                let (a, b, c) = set.cards();
+                [a, b, c].iter().all(|card| step.contains(card))
+            }));
+
+            board.retain(|card| !step.contains(card));
+            assert!(search.replenish(&mut board, &mut dealt));
+            assert!(search.deal_while_stuck(&mut board, &mut dealt));
+            assert!(board.len() <= dealing.max_board);
+        }
+
+        assert!(board.is_empty());
+        assert_eq!(dealt, deck.len());
+    }
+
+    #[test]
+    fn check_trivial_boards() {
+        let dealing = Dealing::default();
+        assert_eq!(solve(&[], &[], dealing), Some(vec![]));
+
+        // cards that only differ in shading are a set
+        let set: Vec<Card> = (0..3).map(Card::new).collect();
+        let steps = solve(&set, &[], dealing).unwrap();
+        assert_eq!(steps.len(), 1);
+        replay(&set, &[], dealing, &steps);
+
+        // no set and nothing left to deal
+        assert_eq!(solve(&set[..2], &[], dealing), None);
+    }
+
+    /// Solves a few shuffled games dealt by `dealing`, replaying each
+    /// solution. Returns how many were solved.
+    fn solve_games(seed: u64, dealing: Dealing) -> usize {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut solved = 0;
+
+        for _ in 0..5 {
+            let mut deck = cards();
+            deck.shuffle_with(&mut rng);
+            let (board, deck) = deck.split_at(dealing.initial_deal);
+
+            if let Some(steps) = solve(board, deck, dealing) {
+                assert_eq!(steps.len(), 27);
+                replay(board, deck, dealing, &steps);
+                solved += 1;
+            }
+        }
+
+        solved
+    }
+
+    #[test]
+    fn check_solutions_follow_the_rules() {
+        // nearly every full game can be cleared
+        assert!(solve_games(931, Dealing::default()) > 0);
+    }
+
+    #[test]
+    fn check_larger_batches() {
+        let dealing = Dealing { deal_batch: 6, ..Dealing::default() };
+        assert!(solve_games(942, dealing) > 0);
+    }
+
+    #[test]
+    fn check_smaller_grid() {
+        // room for 15 cards means a stuck 15 is a dead end
+        let dealing = Dealing { max_board: 15, ..Dealing::default() };
+        assert!(solve_games(1028, dealing) > 0);
+    }
+
+    #[test]
+    fn check_replenish_policies() {
+        for &replenish in &[ReplenishPolicy::Never, ReplenishPolicy::AlwaysToInitial] {
+            let dealing = Dealing { replenish, ..Dealing::default() };
+            assert!(solve_games(950, dealing) > 0);
+        }
+    }
+}
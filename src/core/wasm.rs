@@ -0,0 +1,63 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! JavaScript bindings for `GameSession`.
+//!
+//! Cards cross the boundary as indices (0-80), and the full state is
+//! available as JSON. The browser does all of the drawing. See
+//! `examples/wasm.js` for usage.
+
+use crate::session::{GameSession, Pick};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Game { session: GameSession }
+
+#[wasm_bindgen]
+impl Game {
+    /// Starts a game whose deck order is determined by `seed`.
+    pub fn new_game(seed: u32) -> Game {
+        Game { session: GameSession::new(seed as u64) }
+    }
+
+    /// Toggles the card at `index` in the tableau. Returns "selected",
+    /// "deselected", "set", or "not_set", or undefined if there's no
+    /// card at `index`.
+    pub fn select(&mut self, index: usize) -> Option<String> {
+        let name = match self.session.select(index)? {
+            Pick::Selected => "selected",
+            Pick::Deselected => "deselected",
+            Pick::Set => "set",
+            Pick::NotSet => "not_set",
+        };
+        Some(name.to_string())
+    }
+
+    /// Deals 3 more cards. Returns false if the deck is empty.
+    pub fn deal(&mut self) -> bool {
+        self.session.deal()
+    }
+
+    /// Returns the index of a card from a `Set` on the table.
+    pub fn hint(&self) -> Option<u8> {
+        self.session.hint().map(|card| card.index() as u8)
+    }
+
+    /// Returns the tableau, selection, score, and game status as JSON.
+    pub fn state_json(&self) -> String {
+        serde_json::to_string(&self.session.snapshot())
+            .expect("snapshot should always serialize")
+    }
+}
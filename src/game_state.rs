@@ -14,67 +14,171 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::cell::{Cell, RenderData};
-use crate::config::{self, Config};
+use crate::config::{self, Config, ReplenishPolicy};
 use core::card::Card;
 use core::deck::Deck;
 use core::shuffle::Shuffle;
 use crate::rules::Rules;
+use rand::{thread_rng, Rng};
 
-pub const COLUMNS: usize = 5;
-pub const ROWS: usize = 4;
+/// Width of the tableau in games saved before the grid came from the rules.
+fn default_columns() -> usize { 5 }
 
-#[derive(Clone)]
+/// Games saved before the guarantee could be turned off always had it.
+fn default_guarantee() -> bool { true }
+
+/// The guarantee in games saved before it knew about supersets always
+/// dealt in threes.
+fn default_set_size() -> usize { 3 }
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameState {
     pub deck: Deck,
     pub score: usize,
     pub tableau: Vec<Cell>,
+    /// Cells in each row of the tableau
+    #[serde(default = "default_columns")]
+    columns: usize,
+    /// Whether deals may doctor the deck to give a stuck table a set
+    #[serde(default = "default_guarantee")]
+    guarantee_sets: bool,
+    /// Cards in a set under the rules being played
+    #[serde(default = "default_set_size")]
+    set_size: usize,
     refill: Vec<usize>,
     hotkeys: Vec<char>,
+    /// Determines the initial deck order
+    #[serde(default)]
+    pub seed: u64,
+    /// Whether the deck was cut short for a quick game
+    #[serde(default)]
+    quick: bool,
 }
 
 impl GameState {
     pub fn with_config(config: Config) -> GameState {
+	GameState::with_config_seeded(config, thread_rng().gen())
+    }
+
+    /// Starts a game whose deck order is determined by `seed`.
+    pub fn with_config_seeded(config: Config, seed: u64) -> GameState {
+	let rules = config.rules();
+	let mut deck = Deck::new_seeded(seed);
+	if config.deck == config::Deck::Simplified { deck.simplify() }
+	if let Some(size) = config.quick_game { deck.subset(size) }
+
+	let mut game_state = GameState::undealt(config, &*rules, deck, seed);
+	game_state.quick = config.quick_game.is_some();
+	game_state.deal(rules.initial_deal_size());
+	game_state
+    }
+
+    /// Starts a game on exactly `cards`, with the rest of the deck
+    /// shuffled. No seed can recreate the board, so the seed is 0.
+    pub fn with_opening(config: Config, cards: &[Card]) -> GameState {
 	let rules = config.rules();
+	let mut deck = Deck::excluding(cards);
+	if config.deck == config::Deck::Simplified { deck.simplify() }
+
+	let mut game_state = GameState::undealt(config, &*rules, deck, 0);
+	game_state.place(cards.to_owned());
+	game_state
+    }
+
+    /// An empty tableau waiting for the first deal from `deck`.
+    fn undealt(config: Config, rules: &dyn Rules, deck: Deck, seed: u64) -> GameState {
+	let (columns, rows) = rules.grid_size();
 	let mut game_state = GameState {
-	    deck: Deck::new(),
+	    deck,
 	    score: 0,
-	    tableau: vec!(Cell::Placeholder; ROWS * COLUMNS),
+	    tableau: vec!(Cell::Placeholder; columns * rows),
+	    columns,
+	    guarantee_sets: config.guarantee_sets && rules.guarantee_set_on_deal(),
+	    set_size: rules.set_size(),
 	    refill: rules.deal_order(),
 	    hotkeys: "abcdefghijklmnopqrstuvwxyz".chars().collect(),
+	    seed,
+	    quick: false,
 	};
 
-	if config.deck == config::Deck::Simplified { game_state.deck.simplify() }
+	let timer = game_state.timer_cell();
 	game_state.tableau[0] = Cell::Deck;
-	game_state.tableau[4] = Cell::Score;
+	game_state.tableau[columns - 1] = Cell::Score;
+	game_state.tableau[timer] = Cell::Timer;
 	game_state.hotkeys.shuffle();
-
-	game_state.deal(rules.initial_deal_size());
 	game_state
     }
 
+    /// Whether this is a quick game on part of the deck.
+    pub fn is_quick(&self) -> bool {
+	self.quick
+    }
+
+    /// Tableau size as (columns, rows).
+    pub fn grid_size(&self) -> (usize, usize) {
+	(self.columns, self.tableau.len() / self.columns)
+    }
+
+    /// The bottom right corner, which is the last cell Set deals into
+    /// and is never used by SuperSet.
+    pub fn timer_cell(&self) -> usize {
+	self.tableau.len() - 1
+    }
+
+    /// Iterate through the tableau cells along with their indices.
+    pub fn cells_with_index(&self) -> impl Iterator<Item = (usize, &Cell)> {
+	self.tableau.iter().enumerate()
+    }
+
+    /// Iterate through the cards in play along with their tableau indices.
+    pub fn cards_with_index(&self) -> impl Iterator<Item = (usize, &RenderData)> {
+	self.cells_with_index().filter_map(|(ix, cell)| match cell {
+	    Cell::Card(data) => Some((ix, data)),
+	    _ => None,
+	})
+    }
+
     /// Generate a list of `Card`s from the tableau.
     pub fn cards(&self) -> Vec<Card> {
-	self.tableau.iter().filter_map(Cell::card).collect()
+	self.cards_with_index().map(|(_, data)| data.card).collect()
     }
 
     /// Finds the `Card` that matches a hotkey (if any)
     pub fn card_for_key(&self, key: char) -> Option<Card> {
-	self.tableau.iter()
-	    .filter_map(|cell| cell.card_for_key(key))
-	    .nth(0)
+	self.cells_with_index().find_map(|(_, cell)| cell.card_for_key(key))
+    }
+
+    /// Finds the hotkey assigned to a `Card` (if it's in play)
+    pub fn hotkey_for_card(&self, card: Card) -> Option<char> {
+	self.cards_with_index()
+	    .find(|(_, data)| data.card == card)
+	    .map(|(_, data)| data.hotkey)
+    }
+
+    /// Number of empty cells that can still be dealt into.
+    pub fn open_cells(&self) -> usize {
+	self.refill.len()
+    }
+
+    /// Whether stuck deals are doctored to contain a set.
+    pub fn guarantees_sets(&self) -> bool {
+	self.guarantee_sets
     }
 
     pub fn card_count(&self) -> usize {
-	self.tableau.iter().filter_map(Cell::card).count()
+	self.cards_with_index().count()
     }
 
     pub fn index_of_card(&self, card: Card) -> Option<usize> {
-	self.tableau.iter().position(|cell| cell.card() == Some(card))
+	self.cards_with_index()
+	    .find(|(_, data)| data.card == card)
+	    .map(|(ix, _)| ix)
     }
 
-    pub fn take_cards(&mut self, cards: &[Card], rules: &dyn Rules) {
+    pub fn take_cards(&mut self, cards: &[Card], rules: &dyn Rules, policy: ReplenishPolicy) {
 	self.score += 1; // woot!
 
+	let timer = self.timer_cell();
 	for (ix, cell) in self.tableau.iter_mut().enumerate().rev() {
 	    if let Cell::Card(data) = *cell {
 		if cards.contains(&data.card) {
@@ -82,34 +186,455 @@ impl GameState {
 		    self.hotkeys.push(data.hotkey);
 		    self.refill.push(ix);
 		    // remove the card
-		    *cell = Cell::Placeholder;
+		    *cell = if ix == timer { Cell::Timer } else { Cell::Placeholder };
 		}
 	    }
 	}
 
 	self.hotkeys.shuffle();
+	self.replenish(rules, policy);
+    }
+
+    /// Deals replacements for taken cards, as far as `policy` allows.
+    fn replenish(&mut self, rules: &dyn Rules, policy: ReplenishPolicy) {
+	let shortfall = rules.initial_deal_size().saturating_sub(self.card_count());
+	if shortfall == 0 {
+	    return;
+	}
 
-	// replenish cards if we dropped below the initial deal size
-	if self.card_count() < rules.initial_deal_size() {
-	    self.deal(rules.set_size());
+	match policy {
+	    ReplenishPolicy::IfBelowInitial => { self.deal(rules.set_size()); }
+	    ReplenishPolicy::AlwaysToInitial => { self.deal(shortfall); }
+	    ReplenishPolicy::Never => {}
 	}
     }
 
-    pub fn deal(&mut self, n: usize) {
+    /// Picks new random angles for all the cards on the tableau.
+    pub fn reroll_angles(&mut self) {
+	for data in self.tableau.iter_mut().filter_map(Cell::card_data_mut) {
+	    data.reroll_angle();
+	}
+    }
+
+    /// Moves the cards in play around the cells they occupy, with new
+    /// hotkeys and angles. Which cards are in play, the deck, and the
+    /// empty cells are all left alone.
+    pub fn shuffle_tableau(&mut self) {
+	let cells: Vec<usize> = self.cards_with_index().map(|(ix, _)| ix).collect();
+	let mut in_play: Vec<RenderData> = self.cards_with_index().map(|(_, &data)| data).collect();
+	let mut hotkeys: Vec<char> = in_play.iter().map(|data| data.hotkey).collect();
+	in_play.shuffle();
+	hotkeys.shuffle();
+
+	for ((ix, mut data), hotkey) in cells.into_iter().zip(in_play).zip(hotkeys) {
+	    data.hotkey = hotkey;
+	    data.reroll_angle();
+	    self.tableau[ix] = Cell::Card(data);
+	}
+    }
+
+    /// Deals up to `n` cards, returning the tableau indices they fill.
+    pub fn deal(&mut self, n: usize) -> Vec<usize> {
 	let cards = self.cards();
-	let guarantee_set = n == 3 // this should probably be encoded in `Rules`
-	    && self.card_count() == 15 && self.deck.remainder() >= 6;
+	let new_cards = self.deck.deal(&cards, n, self.set_size, self.guarantee_sets);
+	self.place(new_cards)
+    }
 
-	let new_cards = if guarantee_set {
-	    self.deck.draw_guaranteeing_set(&cards).unwrap()
-	} else {
-	    self.deck.draw(n)
-	};
+    /// The cards `deal(n)` would place and the cells they'd fill, in
+    /// dealing order. This is the naive draw, so when the next cards
+    /// would leave the table stuck, the guarantee doctors the deck and
+    /// the actual deal differs.
+    pub fn peek_deal(&self, n: usize) -> Vec<(usize, Card)> {
+	// mirror the batching in `Deck::deal`, which draws a set's worth at a time
+	let set_size = self.set_size;
+	let chunk = if n > set_size && n.is_multiple_of(set_size) { set_size } else { n.max(1) };
+	let cards = self.deck.peek(n);
+	let cards = cards.rchunks(chunk).flat_map(|draw| draw.iter().copied());
+
+	self.refill.iter().rev().copied().zip(cards).collect()
+    }
+
+    /// Deals a card into every empty playable cell, as far as the
+    /// deck allows. This is a plain top-up, so no set is guaranteed.
+    pub fn fill_empty(&mut self) -> Vec<usize> {
+	let new_cards = self.deck.draw(self.refill.len());
+	self.place(new_cards)
+    }
 
+    /// Puts cards into the next refill cells, returning their indices.
+    fn place(&mut self, new_cards: Vec<Card>) -> Vec<usize> {
+	// the cards already in play have survived another deal
+	if !new_cards.is_empty() {
+	    for data in self.tableau.iter_mut().filter_map(Cell::card_data_mut) {
+		data.deals_survived += 1;
+	    }
+	}
+
+	let mut dealt = Vec::with_capacity(new_cards.len());
 	for card in new_cards {
 	    let i = self.refill.pop().unwrap();
 	    let hotkey = self.hotkeys.pop().unwrap();
 	    self.tableau[i] = Cell::Card(RenderData::with_card_and_hotkey(card, hotkey));
+	    dealt.push(i);
+	}
+
+	dealt
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::HintMode;
+    use core::find::FindSets;
+
+    #[test]
+    fn check_card_iterators() {
+	let mut state = GameState::with_config(Config::new());
+	state.deal(3);
+
+	// compare against straightforward scans of the tableau
+	let cards: Vec<Card> = state.tableau.iter().filter_map(Cell::card).collect();
+	assert_eq!(state.cards(), cards);
+	assert_eq!(state.card_count(), cards.len());
+	let (columns, rows) = state.grid_size();
+	assert_eq!(state.cells_with_index().count(), columns * rows);
+
+	for (ix, cell) in state.tableau.iter().enumerate() {
+	    match cell.card() {
+		Some(card) => assert_eq!(state.index_of_card(card), Some(ix)),
+		None => assert!(state.cards_with_index().all(|(i, _)| i != ix)),
+	    }
+	}
+
+	for key in "abcdefghijklmnopqrstuvwxyz".chars() {
+	    let expected = state.tableau.iter()
+		.filter_map(|cell| cell.card_for_key(key))
+		.next();
+	    assert_eq!(state.card_for_key(key), expected);
+	}
+    }
+
+    #[test]
+    fn check_seeded_games_match() {
+	let config = Config::new();
+	let mut a = GameState::with_config_seeded(config, 1234);
+	let mut b = GameState::with_config_seeded(config, 1234);
+	assert_eq!(a.cards(), b.cards());
+	assert_eq!(a.deck.remaining_cards(), b.deck.remaining_cards());
+
+	assert_eq!(a.deal(3), b.deal(3));
+	assert_eq!(a.cards(), b.cards());
+
+	let c = GameState::with_config_seeded(config, 4321);
+	assert_ne!(a.deck.remaining_cards(), c.deck.remaining_cards());
+
+	// 15 cards without a set, over a stock whose next 3 can't fix them
+	let cap: Vec<Card> = [0,1,3,4,9,13,14,15,19,34,38,39,40,44,49,50,52,53,60,74]
+	    .iter().map(|&ix| Card::new(ix)).collect();
+	let (hand, top) = cap.split_at(15);
+	let mut stock: Vec<Card> = core::deck::all_cards().filter(|card| !cap.contains(card)).take(20).collect();
+	stock.extend(top);
+
+	// doctored deals shuffle too, and the seed has to cover that
+	let doctored = || {
+	    let rules = config.rules();
+	    let deck = Deck::from_cards_seeded(stock.clone(), 1234);
+	    let mut state = GameState::undealt(config, &*rules, deck, 1234);
+	    state.place(hand.to_vec());
+	    state.deal(3);
+	    state
+	};
+	let (a, b) = (doctored(), doctored());
+	assert!(a.cards().contains_set());
+	assert_eq!(a.cards(), b.cards());
+	assert_eq!(a.deck.remaining_cards(), b.deck.remaining_cards());
+    }
+
+    #[test]
+    fn check_shuffle_tableau() {
+	let mut state = GameState::with_config(Config::new());
+	let deck = state.deck.remaining_cards();
+	let occupied = |state: &GameState| -> Vec<usize> {
+	    state.cards_with_index().map(|(ix, _)| ix).collect()
+	};
+	let sorted = |mut items: Vec<Card>| { items.sort_by_key(|card| card.index()); items };
+	let hotkeys = |state: &GameState| -> Vec<char> {
+	    let mut keys: Vec<char> = state.cards_with_index().map(|(_, data)| data.hotkey).collect();
+	    keys.sort();
+	    keys
+	};
+
+	let (cells, cards, keys) = (occupied(&state), sorted(state.cards()), hotkeys(&state));
+	state.shuffle_tableau();
+
+	assert_eq!(occupied(&state), cells);
+	assert_eq!(sorted(state.cards()), cards);
+	assert_eq!(hotkeys(&state), keys);
+	assert_eq!(state.deck.remaining_cards(), deck);
+    }
+
+    #[test]
+    fn check_grid_size() {
+	let config = Config::new();
+	let state = GameState::with_config(config);
+	assert_eq!(state.grid_size(), config.rules().grid_size());
+	assert!(matches!(state.tableau[4], Cell::Score));
+
+	// games saved before the grid was recorded get the original 5x4
+	let yaml = serde_yaml::to_string(&state).unwrap();
+	let old: String = yaml.lines()
+	    .filter(|line| !line.starts_with("columns:"))
+	    .map(|line| format!("{}\n", line))
+	    .collect();
+	assert_ne!(old, yaml);
+	let restored: GameState = serde_yaml::from_str(&old).unwrap();
+	assert_eq!(restored.grid_size(), (5, 4));
+    }
+
+    #[test]
+    fn check_timer_cell() {
+	let config = Config::new();
+	let rules = config.rules();
+	let mut state = GameState::with_config(config);
+	let timer = state.timer_cell();
+	assert_eq!(timer, 19);
+	assert!(matches!(state.tableau[timer], Cell::Timer));
+
+	// Set deals into the corner last, and it becomes a timer again once emptied
+	state.deal(6);
+	let card = state.tableau[timer].card().unwrap();
+	state.take_cards(&[card], &*rules, ReplenishPolicy::Never);
+	assert!(matches!(state.tableau[timer], Cell::Timer));
+    }
+
+    #[test]
+    fn check_deal_reports_cells() {
+	let mut state = GameState::with_config(Config::new());
+	let before: Vec<usize> = state.cards_with_index().map(|(ix, _)| ix).collect();
+
+	let dealt = state.deal(3);
+	assert_eq!(dealt.len(), 3);
+
+	for ix in dealt {
+	    assert!(!before.contains(&ix));
+	    assert!(state.tableau[ix].card().is_some());
+	}
+    }
+
+    #[test]
+    fn check_deal_batch() {
+	let capacity = Config::new().rules().deal_order().len();
+	let mut state = GameState::with_config(Config::new());
+
+	let dealt = state.deal(6);
+	assert_eq!(dealt.len(), 6);
+	assert_eq!(state.card_count(), capacity);
+	assert_eq!(state.open_cells(), 0);
+
+	// every dealt cell is distinct and filled
+	let mut cells = dealt.clone();
+	cells.sort();
+	cells.dedup();
+	assert_eq!(cells.len(), 6);
+	assert!(dealt.iter().all(|&ix| state.tableau[ix].card().is_some()));
+    }
+
+    #[test]
+    fn check_peek_deal() {
+	let mut state = GameState::with_config(Config::new());
+	let preview = state.peek_deal(3);
+
+	// batches preview (and deal) 3 cards at a time
+	assert_eq!(state.peek_deal(6)[..3], preview[..]);
+	assert_eq!(state.peek_deal(6).len(), 6);
+
+	// no guarantee applies to a 12 card table, so the preview is exact
+	let dealt = state.deal(3);
+	let cells: Vec<usize> = preview.iter().map(|&(ix, _)| ix).collect();
+	assert_eq!(cells, dealt);
+	for (ix, card) in preview {
+	    assert_eq!(state.tableau[ix].card(), Some(card));
+	}
+    }
+
+    #[test]
+    fn check_hotkey_for_card() {
+	let state = GameState::with_config(Config::new());
+	let mut hotkeys: Vec<char> = state.cards().into_iter()
+	    .map(|card| state.hotkey_for_card(card).unwrap())
+	    .collect();
+
+	for &key in &hotkeys {
+	    let card = state.card_for_key(key).unwrap();
+	    assert_eq!(state.hotkey_for_card(card), Some(key));
 	}
+
+	// every card in play has its own hotkey
+	hotkeys.sort();
+	hotkeys.dedup();
+	assert_eq!(hotkeys.len(), state.card_count());
+
+	let missing = state.deck.remaining_cards()[0];
+	assert_eq!(state.hotkey_for_card(missing), None);
+    }
+
+    #[test]
+    fn check_fill_empty() {
+	let capacity = Config::new().rules().deal_order().len();
+
+	// a full fill occupies every playable cell
+	let mut state = GameState::with_config(Config::new());
+	let dealt = state.fill_empty();
+	assert_eq!(state.card_count(), capacity);
+	assert_eq!(dealt.len(), capacity - 12);
+	assert!(state.fill_empty().is_empty());
+	assert!(state.cards_with_index().all(|(ix, data)| state.card_for_key(data.hotkey) == state.tableau[ix].card()));
+
+	// a partial fill is limited by the deck
+	let mut state = GameState::with_config(Config::new());
+	let remainder = state.deck.remainder();
+	state.deck.draw(remainder - 2);
+	let dealt = state.fill_empty();
+	assert_eq!(dealt.len(), 2);
+	assert!(state.deck.is_empty());
+	assert_eq!(state.card_count(), 14);
+	assert!(dealt.iter().all(|&ix| state.tableau[ix].card().is_some()));
+    }
+
+    #[test]
+    fn check_deals_survived() {
+	let rules = Config::new().rules();
+	let mut state = GameState::with_config(Config::new());
+	let age = |state: &GameState, ix: usize| match state.tableau[ix] {
+	    Cell::Card(data) => data.deals_survived,
+	    _ => panic!("no card in cell {}", ix),
+	};
+
+	let cells: Vec<usize> = state.cards_with_index().map(|(ix, _)| ix).collect();
+	let (veteran, replaced) = (cells[0], &cells[1..4]);
+	assert_eq!(age(&state, veteran), 0);
+
+	// taking cards deals replacements into the same cells
+	let taken: Vec<Card> = replaced.iter().filter_map(|&ix| state.tableau[ix].card()).collect();
+	state.take_cards(&taken, &*rules, ReplenishPolicy::IfBelowInitial);
+	assert_eq!(age(&state, veteran), 1);
+	assert!(replaced.iter().all(|&ix| age(&state, ix) == 0));
+
+	state.deal(3);
+	assert_eq!(age(&state, veteran), 2);
+	assert!(replaced.iter().all(|&ix| age(&state, ix) == 1));
+    }
+
+    #[test]
+    fn check_quick_game() {
+	let config = Config { quick_game: Some(30), ..Config::new() };
+	let rules = config.rules();
+	let mut state = GameState::with_config(config);
+	assert_eq!(state.card_count() + state.deck.remainder(), 30);
+	assert!(state.is_quick());
+	assert!(!GameState::with_config(Config::new()).is_quick());
+
+	// play the whole game through
+	while let Some(hint) = rules.hint(&state.cards(), HintMode::Random) {
+	    let cards = state.cards();
+	    let set = rules.find_all(&cards).into_iter()
+		.find(|set| hint.iter().all(|card| set.contains(card)))
+		.unwrap();
+	    state.take_cards(&set, &*rules, ReplenishPolicy::IfBelowInitial);
+
+	    while rules.stuck(&state.cards()) && !state.deck.is_empty() {
+		state.deal(rules.set_size());
+	    }
+	}
+
+	assert!(state.deck.is_empty());
+	assert_eq!(state.card_count() + state.score * 3, 30);
+    }
+
+    #[test]
+    fn check_optional_guarantee() {
+	// a 15 card board without a set
+	let cap = loop {
+	    let mut cap: Vec<Card> = Vec::new();
+	    for card in Deck::new().draw(81) {
+		cap.push(card);
+		if cap.contains_set() { cap.pop(); }
+		if cap.len() == 15 { break; }
+	    }
+	    if cap.len() == 15 { break cap; }
+	};
+
+	let mut guaranteed = GameState::with_opening(Config::new(), &cap);
+	guaranteed.deal(3);
+	assert!(guaranteed.cards().contains_set());
+
+	// without the guarantee, the deal is just the top of the deck
+	let config = Config { guarantee_sets: false, ..Config::new() };
+	let mut naive = GameState::with_opening(config, &cap);
+	let expected = naive.deck.peek(3);
+	naive.deal(3);
+	assert!(expected.iter().all(|card| naive.cards().contains(card)));
+    }
+
+    #[test]
+    fn check_superset_guarantee() {
+	use core::find::FindSuperSets;
+
+	// 5 cards without a superset, which a naive draw of 4 may not fix
+	let hand = loop {
+	    let hand = Deck::new().draw(5);
+	    if !hand.contains_superset() { break hand; }
+	};
+
+	let config = Config { variant: config::Variant::SuperSet, ..Config::new() };
+	for _ in 0..20 {
+	    let mut state = GameState::with_opening(config, &hand);
+	    state.deal(4);
+	    assert!(state.cards().contains_superset());
+	}
+    }
+
+    /// Takes the first set on the board under `policy`, returning the
+    /// number of cards left afterward.
+    fn take_first_set(state: &mut GameState, policy: ReplenishPolicy) -> usize {
+	let rules = Config::new().rules();
+	let set = rules.find_all(&state.cards()).remove(0);
+	state.take_cards(&set, &*rules, policy);
+	state.card_count()
+    }
+
+    #[test]
+    fn check_replenish_policies() {
+	let config = Config::new();
+	let board_with_set = |size: usize| loop {
+	    let mut opening = Deck::new().draw(size);
+	    if opening.contains_set() {
+		opening.sort();
+		break GameState::with_opening(config, &opening);
+	    }
+	};
+
+	// from the standard board
+	let standard = board_with_set(12);
+	assert_eq!(take_first_set(&mut standard.clone(), ReplenishPolicy::IfBelowInitial), 12);
+	assert_eq!(take_first_set(&mut standard.clone(), ReplenishPolicy::AlwaysToInitial), 12);
+	assert_eq!(take_first_set(&mut standard.clone(), ReplenishPolicy::Never), 9);
+
+	// from an expanded board, nothing needs replacing
+	let expanded = board_with_set(15);
+	assert_eq!(take_first_set(&mut expanded.clone(), ReplenishPolicy::IfBelowInitial), 12);
+	assert_eq!(take_first_set(&mut expanded.clone(), ReplenishPolicy::AlwaysToInitial), 12);
+	assert_eq!(take_first_set(&mut expanded.clone(), ReplenishPolicy::Never), 12);
+
+	// from a short board, only one policy gets all the way back
+	let short = board_with_set(9);
+	assert_eq!(take_first_set(&mut short.clone(), ReplenishPolicy::IfBelowInitial), 9);
+	assert_eq!(take_first_set(&mut short.clone(), ReplenishPolicy::AlwaysToInitial), 12);
+	assert_eq!(take_first_set(&mut short.clone(), ReplenishPolicy::Never), 6);
     }
 }
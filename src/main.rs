@@ -27,11 +27,16 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_yaml;
 
+pub mod audio;
 pub mod cell;
+pub mod clock;
 pub mod config;
 pub mod controller;
 pub mod game_state;
 pub mod rules;
+pub mod scores;
+pub mod session;
+pub mod share;
 
 use gdk::prelude::*;
 use gdk::ModifierType;
@@ -39,12 +44,20 @@ use gdk_pixbuf::{Pixbuf, PixbufLoader};
 use glib::{clone, Error};
 use gtk::prelude::*;
 use gtk::{AccelGroup, Application, ApplicationWindow, MenuItem};
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
-
-use crate::config::{Config, Deck, Variant};
-use crate::controller::Controller;
+use std::time::Duration;
+
+use crate::audio::{Player, Sound};
+use crate::config::{Config, Deck, HoverStyle, RenderQuality, ReplenishPolicy, Theme, Variant};
+use crate::controller::{default_window_size, Controller, DemoAction, GameEvent, GameStats};
+use crate::rules::HintLevel;
+use crate::scores::Scores;
+use crate::session::Session;
+use core::graphics::clock_text;
 use core::graphics::ColorScheme::{Classic, CMYK};
+use core::{log_debug, log_warn};
 
 /// A convenience type for passing data to menu building functions
 type MenuData<'a> = (
@@ -67,6 +80,8 @@ fn init(app: &Application) {
     // create controller and drawing area
     let controller = Controller::shared_with_config(config);
     let drawing_area = controller.borrow().get_drawing_area();
+    // resuming may change the variant, so do it before building the UI
+    offer_to_resume(&controller);
     // create window
     let window = build_window(app, &controller);
 
@@ -78,31 +93,114 @@ fn init(app: &Application) {
     window.add_accel_group(&accel_group);
     menu_bar.append(&build_game_menu(menu_data));
     menu_bar.append(&build_control_menu(menu_data));
-    menu_bar.append(&build_help_menu(&window));
+    menu_bar.append(&build_help_menu(menu_data));
 
     // add the widgets to the window
     let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
     v_box.pack_start(&menu_bar, false, false, 0);
     v_box.pack_start(&drawing_area, true, true, 0);
+    v_box.pack_start(&build_status_line(&controller), false, false, 0);
     window.add(&v_box);
 
+    // show the controller's messages in the status line or a dialog
+    controller.borrow_mut().add_message_observer(
+        clone!(@weak controller, @weak window => move |message: &str| {
+            let message = message.to_string();
+            // wait until the controller is no longer borrowed
+            glib::idle_add_local_once(move || show_game_message(&controller, message, &window));
+        }),
+    );
+
+    controller.borrow_mut().add_event_observer(|_, event| log_debug!("Game event: {:?}", event));
+
+    // the player is only looked for once sounds are turned on
+    let player: OnceCell<Option<Player>> = OnceCell::new();
+    controller.borrow_mut().add_event_observer(move |controller, event| {
+        let sound = match event {
+            GameEvent::SetFound => Sound::Set,
+            GameEvent::InvalidAttempt => Sound::Invalid,
+            GameEvent::Dealt => Sound::Deal,
+            _ => return,
+        };
+        if controller.config.sound_enabled {
+            if let Some(player) = player.get_or_init(Player::new) {
+                player.play(sound);
+            }
+        }
+    });
+
+    // offer a new game once the current one is over
+    controller.borrow_mut().add_finish_observer(
+        clone!(@weak controller, @weak window => move |_| {
+            // wait until the controller is no longer borrowed
+            glib::idle_add_local_once(move || show_game_over_dialog(&controller, &window));
+        }),
+    );
+
     window.show_all();
 }
 
+/// A label for game messages, shown only when the status line is enabled.
+fn build_status_line(controller: &Rc<RefCell<Controller>>) -> gtk::Label {
+    let status_line = gtk::Label::new(None);
+    status_line.set_xalign(0.);
+    status_line.set_margin_start(8);
+    status_line.set_margin_end(8);
+    status_line.set_margin_top(4);
+    status_line.set_margin_bottom(4);
+    status_line.set_no_show_all(true);
+    status_line.set_visible(controller.borrow().config.use_status_line);
+
+    controller.borrow_mut().add_status_observer(clone!(@weak status_line => move |controller| {
+        status_line.set_visible(controller.config.use_status_line);
+        status_line.set_text(controller.status().unwrap_or(""));
+    }));
+
+    status_line
+}
+
+/// The variant being played, and optionally how many sets are on the table.
+fn window_title(controller: &Controller) -> String {
+    let name = controller.config.rules().name();
+    if controller.config.show_set_count {
+        format!("{} ({} on the table)", name, controller.available_set_count())
+    } else {
+        name.to_string()
+    }
+}
+
 fn build_window(app: &Application, controller: &Rc<RefCell<Controller>>) -> ApplicationWindow {
     let config = controller.borrow().config;
     let window = ApplicationWindow::new(app);
-    let (width, height) = config.window_size;
+    let (width, height) = config.window_size.unwrap_or_else(|| default_window_size(&config));
 
-    window.set_title(config.rules().name());
+    window.set_title(&window_title(&controller.borrow()));
     window.set_default_size(width, height);
 
+    // keep the count in the title current as the cards change
+    controller.borrow_mut().add_event_observer(clone!(@weak window => move |controller, event| {
+        if event == GameEvent::BoardChanged && controller.config.show_set_count {
+            window.set_title(&window_title(controller));
+        }
+    }));
+
     // quit if the window is closed
     window.connect_delete_event(
         clone!(@strong controller, @weak window => @default-return Inhibit(false), move |_, _| {
-            // save the current window size in the config
-            let config = &mut controller.borrow_mut().config;
-            config.set_window_size(window.size());
+            let confirm = controller.borrow().config.confirm_close_on_pace;
+            if confirm && controller.borrow().on_pace_for_best() && !confirm_close(&window) {
+                return Inhibit(true);
+            }
+
+            // save the current window size in the config, along with
+            // any other changes that are still deferred
+            let mut controller = controller.borrow_mut();
+            controller.config.set_window_size(Some(window.size()));
+            controller.config.flush();
+
+            if controller.config.auto_save {
+                controller.save_session();
+            }
             Inhibit(false)
         }),
     );
@@ -110,6 +208,64 @@ fn build_window(app: &Application, controller: &Rc<RefCell<Controller>>) -> Appl
     window
 }
 
+fn offer_to_resume(controller: &Rc<RefCell<Controller>>) {
+    if !controller.borrow().config.auto_save {
+        return;
+    }
+
+    let session = Session::load()
+        .map_err(|err| log_debug!("No saved game to resume: {}", err))
+        .ok();
+
+    if let Some(session) = session {
+        let md = gtk::MessageDialog::new(
+            None::<&gtk::Window>,
+            gtk::DialogFlags::empty(),
+            gtk::MessageType::Question,
+            gtk::ButtonsType::YesNo,
+            "Resume your last game?",
+        );
+        md.set_markup("<big>Resume your last game?</big>");
+
+        let variant = session.config.variant;
+        if variant != controller.borrow().config.variant {
+            let name = session.config.rules().name();
+            md.set_secondary_text(Some(&format!("Your last game was {}.", name)));
+        }
+
+        let response = md.run();
+        unsafe {
+            md.destroy();
+        }
+
+        if response == gtk::ResponseType::Yes {
+            controller.borrow_mut().load_session(session);
+        }
+    }
+
+    // a saved game is only offered once
+    Session::discard();
+}
+
+fn confirm_close(window: &ApplicationWindow) -> bool {
+    let md = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Question,
+        gtk::ButtonsType::YesNo,
+        "Quit anyway?",
+    );
+    md.set_markup("<big>Quit anyway?</big>");
+    md.set_secondary_text(Some("You're on pace to beat your best."));
+
+    let response = md.run();
+    unsafe {
+        md.destroy();
+    }
+
+    response == gtk::ResponseType::Yes
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Menu Helpers
 ////////////////////////////////////////////////////////////////////////////////
@@ -153,7 +309,24 @@ fn build_game_menu(menu_data: MenuData) -> MenuItem {
     // create menu items
     let new_game = make_menu_item("_New Game", accel_group, ModifierType::CONTROL_MASK, &['N']);
     let restart = MenuItem::with_mnemonic("_Restart Game");
+    let challenge = MenuItem::with_mnemonic("Start _Challenge");
+    let high_scores = MenuItem::with_mnemonic("Hi_gh Scores…");
+    let statistics = MenuItem::with_mnemonic("Statis_tics…");
+    let export_demo = MenuItem::with_mnemonic("_Export Hint Demo…");
+    let share_board = MenuItem::with_mnemonic("S_hare Board…");
+    let load_share_code = MenuItem::with_mnemonic("_Load Share Code…");
+    let auto_save = gtk::CheckMenuItem::with_mnemonic("_Save Game on Exit");
+    let confirm_close = gtk::CheckMenuItem::with_mnemonic("_Warn Before Quitting a Record Run");
+    let unhinted_records = gtk::CheckMenuItem::with_mnemonic("_Ignore Hinted Games in High Scores");
     let close = make_menu_item("_Close", accel_group, ModifierType::CONTROL_MASK, &['W']);
+    let (save_as, open_saved) = build_slot_submenus(menu_data);
+    let save_file = MenuItem::with_mnemonic("Save Game to _File…");
+    let open_file = MenuItem::with_mnemonic("Open Game from F_ile…");
+
+    // reflect config settings
+    auto_save.set_active(controller.borrow().config.auto_save);
+    confirm_close.set_active(controller.borrow().config.confirm_close_on_pace);
+    unhinted_records.set_active(controller.borrow().config.unhinted_records);
 
     new_game.connect_activate(
         clone!(@strong controller => move |_| controller.borrow_mut().new_game()),
@@ -162,13 +335,76 @@ fn build_game_menu(menu_data: MenuData) -> MenuItem {
     restart
         .connect_activate(clone!(@strong controller => move |_| controller.borrow_mut().restart()));
 
+    challenge.connect_activate(
+        clone!(@strong controller => move |_| controller.borrow_mut().start_challenge()),
+    );
+
+    high_scores.connect_activate(clone!(@weak window => move |_| show_high_scores_dialog(&window)));
+
+    statistics.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        let stats = controller.borrow().stats();
+        show_statistics_dialog(&window, stats);
+    }));
+
+    save_file.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        if let Some(path) = choose_game_file(&window, gtk::FileChooserAction::Save) {
+            if let Err(err) = controller.borrow().save_game(&path) {
+                let message = format!("Could not save the game: {}", err);
+                show_message_dialog(Some(glib::markup_escape_text(&message).to_string()), &window);
+            }
+        }
+    }));
+
+    open_file.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        if let Some(path) = choose_game_file(&window, gtk::FileChooserAction::Open) {
+            if let Err(err) = controller.borrow_mut().load_game(&path) {
+                let message = format!("Could not open {}: {}", path.display(), err);
+                show_message_dialog(Some(glib::markup_escape_text(&message).to_string()), &window);
+            }
+        }
+    }));
+
+    export_demo.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        if let Some(dir) = choose_folder(&window, "Export Hint Demo") {
+            let actions = [DemoAction::Hint];
+            if let Err(err) = controller.borrow().export_frames(&dir, &actions) {
+                show_message_dialog(Some(format!("Could not export frames: {}", err)), &window);
+            }
+        }
+    }));
+
+    share_board.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        let code = controller.borrow().board_share_code();
+        gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&code);
+        let message = format!("Share code (copied to the clipboard):\n\n<tt>{}</tt>", code);
+        show_message_dialog(Some(message), &window);
+    }));
+
+    load_share_code.connect_activate(clone!(@strong controller, @weak window => move |_| {
+        if let Some(code) = prompt_for_text(&window, "Enter a share code:") {
+            if let Err(err) = controller.borrow_mut().load_share_code(&code) {
+                let message = glib::markup_escape_text(&err.to_string()).to_string();
+                show_message_dialog(Some(message), &window);
+            }
+        }
+    }));
+
+    auto_save.connect_toggled(clone!(@strong controller => move |w|
+               controller.borrow_mut().set_auto_save(w.is_active())));
+
+    confirm_close.connect_toggled(clone!(@strong controller => move |w|
+               controller.borrow_mut().set_confirm_close_on_pace(w.is_active())));
+
+    unhinted_records.connect_toggled(clone!(@strong controller => move |w|
+               controller.borrow_mut().set_unhinted_records(w.is_active())));
+
     close.connect_activate(clone!(@weak window => move |_| window.close()));
 
     // disable restart menu by default
     restart.set_sensitive(false);
     // update restart status based on undo stack changes
     controller.borrow_mut().add_undo_observer(
-        clone!(@weak restart => move |controller| restart.set_sensitive(controller.can_undo())),
+        clone!(@weak restart => move |controller| restart.set_sensitive(controller.can_restart())),
     );
 
     build_menu!(
@@ -176,15 +412,118 @@ fn build_game_menu(menu_data: MenuData) -> MenuItem {
         [
             new_game,
             restart,
+            challenge,
+            high_scores,
+            statistics,
+            gtk::SeparatorMenuItem::new(),
+            save_as,
+            open_saved,
+            save_file,
+            open_file,
             gtk::SeparatorMenuItem::new(),
             build_variant_submenu(menu_data),
             build_deck_submenu(menu_data),
+            auto_save,
+            confirm_close,
+            unhinted_records,
+            gtk::SeparatorMenuItem::new(),
+            share_board,
+            load_share_code,
+            export_demo,
             gtk::SeparatorMenuItem::new(),
             close
         ]
     )
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Saved Game Submenus
+////////////////////////////////////////////////////////////////////////////////
+
+/// Submenus for saving to and opening named slots. The slots are
+/// listed afresh whenever one of the submenus is about to open.
+fn build_slot_submenus(menu_data: MenuData) -> (MenuItem, MenuItem) {
+    let (window, _accel_group, controller) = menu_data;
+
+    let save_as = MenuItem::with_mnemonic("Save _As");
+    let save_menu = gtk::Menu::new();
+    save_as.set_submenu(Some(&save_menu));
+
+    let open = MenuItem::with_mnemonic("_Open Saved Game");
+    let open_menu = gtk::Menu::new();
+    open.set_submenu(Some(&open_menu));
+
+    save_as.connect_select(clone!(@strong controller, @weak window, @weak save_menu => move |_| {
+        clear_menu(&save_menu);
+
+        let new_slot = MenuItem::with_mnemonic("_New Slot…");
+        new_slot.connect_activate(clone!(@strong controller, @weak window => move |_| {
+            if let Some(name) = prompt_for_text(&window, "Name the saved game:") {
+                save_to_slot(&controller, &name, false, &window);
+            }
+        }));
+        save_menu.append(&new_slot);
+
+        // choosing an existing slot replaces it
+        let slots = Controller::list_slots();
+        if !slots.is_empty() {
+            save_menu.append(&gtk::SeparatorMenuItem::new());
+        }
+        for slot in slots {
+            let item = MenuItem::with_label(&slot);
+            item.connect_activate(clone!(@strong controller, @weak window => move |_| {
+                save_to_slot(&controller, &slot, true, &window);
+            }));
+            save_menu.append(&item);
+        }
+
+        save_menu.show_all();
+    }));
+
+    open.connect_select(clone!(@strong controller, @weak window, @weak open_menu => move |_| {
+        clear_menu(&open_menu);
+
+        let slots = Controller::list_slots();
+        if slots.is_empty() {
+            let none = MenuItem::with_label("No Saved Games");
+            none.set_sensitive(false);
+            open_menu.append(&none);
+        }
+        for slot in slots {
+            let item = MenuItem::with_label(&slot);
+            item.connect_activate(clone!(@strong controller, @weak window => move |_| {
+                if let Err(err) = controller.borrow_mut().load_slot(&slot) {
+                    let message = format!("Could not open {}: {}", slot, err);
+                    show_message_dialog(Some(glib::markup_escape_text(&message).to_string()), &window);
+                }
+            }));
+            open_menu.append(&item);
+        }
+
+        open_menu.show_all();
+    }));
+
+    (save_as, open)
+}
+
+fn clear_menu(menu: &gtk::Menu) {
+    for child in menu.children() {
+        menu.remove(&child);
+    }
+}
+
+fn save_to_slot(
+    controller: &Rc<RefCell<Controller>>,
+    name: &str,
+    overwrite: bool,
+    window: &ApplicationWindow,
+) {
+    if let Err(err) = controller.borrow().save_slot(name, overwrite) {
+        let message = format!("Could not save the game: {}", err);
+        show_message_dialog(Some(glib::markup_escape_text(&message).to_string()), window);
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Variant Submenu
 ////////////////////////////////////////////////////////////////////////////////
@@ -195,25 +534,48 @@ fn build_variant_submenu(menu_data: MenuData) -> MenuItem {
     // create menu items
     let set_variant = gtk::RadioMenuItem::with_mnemonic("_Set");
     let superset_variant = gtk::RadioMenuItem::with_mnemonic("S_uperSet");
+    let ultraset_variant = gtk::RadioMenuItem::with_mnemonic("U_ltraSet");
     superset_variant.join_group(Some(&set_variant));
+    ultraset_variant.join_group(Some(&set_variant));
 
     // reflect config settings
     match controller.borrow().config.variant {
         Variant::Set => set_variant.set_active(true),
         Variant::SuperSet => superset_variant.set_active(true),
+        Variant::UltraSet => ultraset_variant.set_active(true),
     }
 
-    set_variant.connect_toggled(clone!(@strong controller, @weak window => move |_| {
-        controller.borrow_mut().set_variant(Variant::Set);
-        window.set_title("Set");
-    }));
+    let variants = [
+        (&set_variant, Variant::Set),
+        (&superset_variant, Variant::SuperSet),
+        (&ultraset_variant, Variant::UltraSet),
+    ];
+    for &(item, variant) in &variants {
+        item.connect_toggled(clone!(@strong controller, @weak window => move |w| {
+            if w.is_active() {
+                // the controller may have already switched on its own
+                if controller.borrow().config.variant != variant {
+                    controller.borrow_mut().set_variant(variant);
+                }
+                window.set_title(&window_title(&controller.borrow()));
+            }
+        }));
+    }
 
-    superset_variant.connect_toggled(clone!(@strong controller, @weak window => move |_| {
-        controller.borrow_mut().set_variant(Variant::SuperSet);
-        window.set_title("SuperSet");
-    }));
+    // follow variant changes made by loading a game
+    controller.borrow_mut().add_settings_observer(
+        clone!(@weak set_variant, @weak superset_variant, @weak ultraset_variant => move |controller| {
+            let item = match controller.config.variant {
+                Variant::Set => set_variant.clone(),
+                Variant::SuperSet => superset_variant.clone(),
+                Variant::UltraSet => ultraset_variant.clone(),
+            };
+            // wait until the controller is no longer borrowed
+            glib::idle_add_local_once(move || item.set_active(true));
+        }),
+    );
 
-    build_menu!("_Variant", [set_variant, superset_variant])
+    build_menu!("_Variant", [set_variant, superset_variant, ultraset_variant])
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -234,13 +596,61 @@ fn build_deck_submenu(menu_data: MenuData) -> MenuItem {
         Deck::Full => full_deck.set_active(true),
     }
 
-    beginner_deck.connect_toggled(clone!(@strong controller => move |_|
-	       controller.borrow_mut().set_deck(Deck::Simplified)));
+    for &(item, deck) in &[(&beginner_deck, Deck::Simplified), (&full_deck, Deck::Full)] {
+        item.connect_toggled(clone!(@strong controller => move |w| {
+            // the controller may have already switched on its own
+            if w.is_active() && controller.borrow().config.deck != deck {
+                controller.borrow_mut().set_deck(deck);
+            }
+        }));
+    }
+
+    // follow deck changes made by loading a game
+    controller.borrow_mut().add_settings_observer(
+        clone!(@weak beginner_deck, @weak full_deck => move |controller| {
+            let item = match controller.config.deck {
+                Deck::Simplified => beginner_deck.clone(),
+                Deck::Full => full_deck.clone(),
+            };
+            // wait until the controller is no longer borrowed
+            glib::idle_add_local_once(move || item.set_active(true));
+        }),
+    );
+
+    // quick games use part of either deck
+    let whole_deck = gtk::RadioMenuItem::with_mnemonic("_Whole Deck");
+    let quick_45 = gtk::RadioMenuItem::with_mnemonic("Quick Game: _45 Cards");
+    let quick_30 = gtk::RadioMenuItem::with_mnemonic("Quick Game: _30 Cards");
+    quick_45.join_group(Some(&whole_deck));
+    quick_30.join_group(Some(&whole_deck));
 
-    full_deck.connect_toggled(clone!(@strong controller => move |_|
-	       controller.borrow_mut().set_deck(Deck::Full)));
+    let quick_games = [(&whole_deck, None), (&quick_45, Some(45)), (&quick_30, Some(30))];
+    let quick_game = controller.borrow().config.quick_game;
 
-    build_menu!("_Deck", [beginner_deck, full_deck])
+    for &(item, size) in &quick_games {
+        // reflect config settings
+        if quick_game == size {
+            item.set_active(true);
+        }
+
+        item.connect_toggled(clone!(@strong controller => move |w| {
+            if w.is_active() && controller.borrow().config.quick_game != size {
+                controller.borrow_mut().set_quick_game(size);
+            }
+        }));
+    }
+
+    build_menu!(
+        "_Deck",
+        [
+            beginner_deck,
+            full_deck,
+            gtk::SeparatorMenuItem::new(),
+            whole_deck,
+            quick_45,
+            quick_30
+        ]
+    )
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -281,6 +691,9 @@ fn connect_undo_redo(controller: &Rc<RefCell<Controller>>, undo: &MenuItem, redo
 // Control Menu
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Clickable margin around cards when click targets are expanded.
+const EXPANDED_HIT_PADDING: f64 = 0.2;
+
 fn build_control_menu(menu_data: MenuData) -> MenuItem {
     let (window, accel_group, controller) = menu_data;
     let ctrl_shift = ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK;
@@ -292,34 +705,162 @@ fn build_control_menu(menu_data: MenuData) -> MenuItem {
     let redo = make_menu_item("_Redo", accel_group, ctrl_shift, &['Z']);
     let hint = make_menu_item("_Hint", accel_group, no_modifier, &['?', '/']);
     let deal_more = make_menu_item("_Deal More Cards", accel_group, no_modifier, &['+', '=']);
-    let tidy_layout = gtk::CheckMenuItem::with_mnemonic("_Tidy Layout");
+    let force_deal = make_menu_item("Deal _Anyway", accel_group, ModifierType::CONTROL_MASK, &['+', '=']);
+    let show_solution = MenuItem::with_mnemonic("Show _Solution");
+    let shuffle_tableau = MenuItem::with_mnemonic("Shu_ffle Cards on the Table");
     let classic_colors = gtk::CheckMenuItem::with_mnemonic("_Classic Colors");
+    let show_hotkeys = gtk::CheckMenuItem::with_mnemonic("Show Hot_keys");
+    let show_grid = gtk::CheckMenuItem::with_mnemonic("Show Gr_id");
+    let card_shadows = gtk::CheckMenuItem::with_mnemonic("Card Shado_ws");
+    let cosmetic_undoable = gtk::CheckMenuItem::with_mnemonic("Undoable _Rearranging");
+    let explain_sets = gtk::CheckMenuItem::with_mnemonic("_Explain Found Sets");
+    let tint_stale_cards = gtk::CheckMenuItem::with_mnemonic("_Tint Long-Lived Cards");
+    let escalating_hints = gtk::CheckMenuItem::with_mnemonic("_Gradual Hints");
+    let hint_deterministic = gtk::CheckMenuItem::with_mnemonic("C_onsistent Hints");
+    let use_status_line = gtk::CheckMenuItem::with_mnemonic("Messages in Status Li_ne");
+    let larger_targets = gtk::CheckMenuItem::with_mnemonic("E_xpanded Click Targets");
+    let show_efficiency = gtk::CheckMenuItem::with_mnemonic("Rate E_fficiency");
+    let click_empty_deselects = gtk::CheckMenuItem::with_mnemonic("Deselect on E_mpty Clicks");
+    let preview_deals = gtk::CheckMenuItem::with_mnemonic("Pre_view Next Deal");
+    let preserve_selection = gtk::CheckMenuItem::with_mnemonic("Keep Se_lection on Undo");
+    let deck_button = gtk::CheckMenuItem::with_mnemonic("Clic_kable Deck Badge");
+    let show_timer = gtk::CheckMenuItem::with_mnemonic("Show Ela_psed Time");
+    let show_set_count = gtk::CheckMenuItem::with_mnemonic("Count Sets in Title _Bar");
+    let auto_deal = gtk::CheckMenuItem::with_mnemonic("A_uto-Deal When Stuck");
+    let guarantee_sets = gtk::CheckMenuItem::with_mnemonic("Guarantee a Set on _Deals");
+    let sound_enabled = gtk::CheckMenuItem::with_mnemonic("Sound Effec_ts");
 
     // reflect config settings
-    tidy_layout.set_active(config.tidy_layout);
     classic_colors.set_active(config.color_scheme == Classic);
+    show_hotkeys.set_active(config.show_hotkeys);
+    show_grid.set_active(config.show_grid);
+    card_shadows.set_active(config.card_shadows);
+    cosmetic_undoable.set_active(config.cosmetic_undoable);
+    explain_sets.set_active(config.explain_sets);
+    tint_stale_cards.set_active(config.tint_stale_cards);
+    escalating_hints.set_active(config.escalating_hints);
+    hint_deterministic.set_active(config.hint_deterministic);
+    use_status_line.set_active(config.use_status_line);
+    larger_targets.set_active(config.hit_padding > 0.);
+    show_efficiency.set_active(config.show_efficiency);
+    click_empty_deselects.set_active(config.click_empty_deselects);
+    preview_deals.set_active(config.preview_deals);
+    preserve_selection.set_active(config.preserve_selection_on_undo);
+    deck_button.set_active(config.deck_button);
+    show_timer.set_active(config.show_timer);
+    show_set_count.set_active(config.show_set_count);
+    auto_deal.set_active(config.auto_deal);
+    guarantee_sets.set_active(config.guarantee_sets);
+    sound_enabled.set_active(config.sound_enabled);
 
     // undo and redo require a bit more setup than other menu items
     connect_undo_redo(controller, &undo, &redo);
 
-    hint.connect_activate(clone!(@strong controller, @weak window => move |_| {
-        let message = controller.borrow_mut().show_hint();
-        show_message_dialog(message, &window);
-    }));
+    hint.connect_activate(clone!(@strong controller => move |_| controller.borrow_mut().show_hint()));
 
-    deal_more.connect_activate(clone!(@strong controller, @weak window => move |_| {
-        let message = controller.borrow_mut().deal_more_cards();
-        show_message_dialog(message, &window);
-    }));
+    // disable hints while the hint cooldown is running
+    controller.borrow_mut().add_hint_observer(
+        clone!(@weak hint => move |controller| hint.set_sensitive(controller.can_hint())),
+    );
 
-    tidy_layout.connect_toggled(clone!(@strong controller => move |w|
-	       controller.borrow_mut().set_tidy_layout(w.is_active())));
+    deal_more.connect_activate(clone!(@strong controller => move |_| controller.borrow_mut().deal_more_cards()));
+
+    force_deal.connect_activate(clone!(@strong controller => move |_| controller.borrow_mut().force_deal()));
+
+    show_solution.connect_activate(clone!(@strong controller => move |_| controller.borrow_mut().show_solution()));
+
+    shuffle_tableau.connect_activate(
+        clone!(@strong controller => move |_| controller.borrow_mut().shuffle_tableau()),
+    );
 
     classic_colors.connect_toggled(clone!(@strong controller => move |w|  {
         let scheme = if w.is_active() { Classic } else { CMYK };
-        controller.borrow_mut().set_color_scheme(scheme);
+        // the controller may have already switched on its own
+        if controller.borrow().config.color_scheme != scheme {
+            controller.borrow_mut().set_color_scheme(scheme);
+        }
     }));
 
+    show_hotkeys.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_show_hotkeys(w.is_active())));
+
+    show_grid.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_show_grid(w.is_active())));
+
+    card_shadows.connect_toggled(clone!(@strong controller => move |w| {
+        if controller.borrow().config.card_shadows != w.is_active() {
+            controller.borrow_mut().set_card_shadows(w.is_active());
+        }
+    }));
+
+    // follow changes made by applying a theme
+    controller.borrow_mut().add_settings_observer(
+        clone!(@weak classic_colors, @weak card_shadows => move |controller| {
+            let classic = controller.config.color_scheme == Classic;
+            let shadows = controller.config.card_shadows;
+            // wait until the controller is no longer borrowed
+            glib::idle_add_local_once(move || {
+                classic_colors.set_active(classic);
+                card_shadows.set_active(shadows);
+            });
+        }),
+    );
+
+    cosmetic_undoable.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_cosmetic_undoable(w.is_active())));
+
+    explain_sets.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_explain_sets(w.is_active())));
+
+    tint_stale_cards.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_tint_stale_cards(w.is_active())));
+
+    escalating_hints.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_escalating_hints(w.is_active())));
+
+    hint_deterministic.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_hint_deterministic(w.is_active())));
+
+    use_status_line.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_use_status_line(w.is_active())));
+
+    larger_targets.connect_toggled(clone!(@strong controller => move |w| {
+        let padding = if w.is_active() { EXPANDED_HIT_PADDING } else { 0. };
+        controller.borrow_mut().set_hit_padding(padding);
+    }));
+
+    show_efficiency.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_show_efficiency(w.is_active())));
+
+    click_empty_deselects.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_click_empty_deselects(w.is_active())));
+
+    preview_deals.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_preview_deals(w.is_active())));
+
+    show_timer.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_show_timer(w.is_active())));
+
+    show_set_count.connect_toggled(clone!(@strong controller, @weak window => move |w| {
+        controller.borrow_mut().set_show_set_count(w.is_active());
+        window.set_title(&window_title(&controller.borrow()));
+    }));
+
+    preserve_selection.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_preserve_selection_on_undo(w.is_active())));
+
+    deck_button.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_deck_button(w.is_active())));
+
+    auto_deal.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_auto_deal(w.is_active())));
+
+    guarantee_sets.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_guarantee_sets(w.is_active())));
+
+    sound_enabled.connect_toggled(clone!(@strong controller => move |w|
+	       controller.borrow_mut().set_sound_enabled(w.is_active())));
+
     build_menu!(
         "_Control",
         [
@@ -328,13 +869,331 @@ fn build_control_menu(menu_data: MenuData) -> MenuItem {
             gtk::SeparatorMenuItem::new(),
             hint,
             deal_more,
+            force_deal,
+            show_solution,
+            shuffle_tableau,
             gtk::SeparatorMenuItem::new(),
-            tidy_layout,
-            classic_colors
+            build_theme_submenu(menu_data),
+            build_layout_submenu(menu_data),
+            auto_deal,
+            guarantee_sets,
+            build_undo_depth_submenu(menu_data),
+            build_deal_batch_submenu(menu_data),
+            build_replenish_submenu(menu_data),
+            build_render_quality_submenu(menu_data),
+            build_hover_style_submenu(menu_data),
+            build_nudge_submenu(menu_data),
+            build_hint_level_submenu(menu_data),
+            classic_colors,
+            show_hotkeys,
+            show_grid,
+            card_shadows,
+            cosmetic_undoable,
+            explain_sets,
+            tint_stale_cards,
+            escalating_hints,
+            hint_deterministic,
+            use_status_line,
+            larger_targets,
+            show_efficiency,
+            click_empty_deselects,
+            preview_deals,
+            preserve_selection,
+            deck_button,
+            show_timer,
+            show_set_count,
+            sound_enabled
         ]
     )
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Theme Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const THEME_PRESETS: [(&str, Theme); 3] = [
+    ("_Standard", Theme {
+        color_scheme: CMYK,
+        background: (0.8, 0.8, 0.8),
+        sloppiness: 1.0,
+        card_shadows: false,
+        hover_style: HoverStyle::Explode,
+    }),
+    ("_Classic", Theme {
+        color_scheme: Classic,
+        background: (0.8, 0.8, 0.8),
+        sloppiness: 0.0,
+        card_shadows: false,
+        hover_style: HoverStyle::Explode,
+    }),
+    ("_Tabletop", Theme {
+        color_scheme: CMYK,
+        background: (0.2, 0.42, 0.3),
+        sloppiness: 0.5,
+        card_shadows: true,
+        hover_style: HoverStyle::Highlight,
+    }),
+];
+
+/// Themes set several options at once, so they're plain items rather
+/// than radio items. The individual options reflect the result.
+fn build_theme_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+
+    let menu = MenuItem::with_mnemonic("T_hemes");
+    let submenu = gtk::Menu::new();
+
+    for &(mnemonic, theme) in THEME_PRESETS.iter() {
+        let item = MenuItem::with_mnemonic(mnemonic);
+        item.connect_activate(clone!(@strong controller => move |_| {
+            controller.borrow_mut().apply_theme(theme);
+        }));
+        submenu.append(&item);
+    }
+
+    menu.set_submenu(Some(&submenu));
+    menu
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Layout Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const SLOPPINESS_PRESETS: [(&str, f64); 3] = [("_Tidy", 0.0), ("_Relaxed", 0.5), ("_Sloppy", 1.0)];
+
+fn build_layout_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let sloppiness = controller.borrow().config.effective_sloppiness();
+
+    let on_select = clone!(@strong controller => move |amount: f64| {
+        let current = controller.borrow().config.effective_sloppiness();
+        if (current - amount).abs() >= f64::EPSILON {
+            controller.borrow_mut().set_sloppiness(amount);
+        }
+    });
+    let (menu, items) = build_radio_submenu("_Layout", &SLOPPINESS_PRESETS, sloppiness, on_select);
+
+    // follow changes made by applying a theme
+    for (item, &(_, amount)) in items.into_iter().zip(SLOPPINESS_PRESETS.iter()) {
+        controller.borrow_mut().add_settings_observer(clone!(@weak item => move |controller| {
+            if (controller.config.effective_sloppiness() - amount).abs() < f64::EPSILON {
+                glib::idle_add_local_once(move || item.set_active(true));
+            }
+        }));
+    }
+
+    menu
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Undo Depth Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const UNDO_DEPTH_PRESETS: [(&str, Option<usize>); 4] = [
+    ("_10", Some(10)),
+    ("_25", Some(25)),
+    ("_100", Some(100)),
+    ("_Unlimited", None),
+];
+
+fn build_undo_depth_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let max_undo_depth = controller.borrow().config.max_undo_depth;
+
+    let on_select = clone!(@strong controller => move |depth: Option<usize>| {
+        controller.borrow_mut().set_max_undo_depth(depth);
+    });
+    build_radio_submenu("Undo Le_vels", &UNDO_DEPTH_PRESETS, max_undo_depth, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Deal Batch Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const DEAL_BATCH_PRESETS: [(&str, Option<usize>); 3] = [
+    ("_One Set", None),
+    ("_Two Sets", Some(6)),
+    ("T_hree Sets", Some(9)),
+];
+
+fn build_deal_batch_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let deal_batch = controller.borrow().config.deal_batch;
+
+    let on_select = clone!(@strong controller => move |batch: Option<usize>| {
+        controller.borrow_mut().set_deal_batch(batch);
+    });
+    build_radio_submenu("Deal _Batch Size", &DEAL_BATCH_PRESETS, deal_batch, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Replenish Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const REPLENISH_PRESETS: [(&str, ReplenishPolicy); 3] = [
+    ("_Below the Initial Deal", ReplenishPolicy::IfBelowInitial),
+    ("_Up to the Initial Deal", ReplenishPolicy::AlwaysToInitial),
+    ("_Never", ReplenishPolicy::Never),
+];
+
+fn build_replenish_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let replenish_policy = controller.borrow().config.replenish_policy;
+
+    let on_select = clone!(@strong controller => move |policy: ReplenishPolicy| {
+        controller.borrow_mut().set_replenish_policy(policy);
+    });
+    build_radio_submenu("Re_plenish After a Set", &REPLENISH_PRESETS, replenish_policy, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Render Quality Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const RENDER_QUALITY_PRESETS: [(&str, RenderQuality); 3] = [
+    ("_High", RenderQuality::High),
+    ("_Balanced", RenderQuality::Balanced),
+    ("_Fast", RenderQuality::Fast),
+];
+
+fn build_render_quality_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let render_quality = controller.borrow().config.render_quality;
+
+    let on_select = clone!(@strong controller => move |quality: RenderQuality| {
+        controller.borrow_mut().set_render_quality(quality);
+    });
+    build_radio_submenu("Rendering _Quality", &RENDER_QUALITY_PRESETS, render_quality, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Hover Style Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const HOVER_STYLE_PRESETS: [(&str, HoverStyle); 3] = [
+    ("_Enlarge", HoverStyle::Explode),
+    ("_Highlight", HoverStyle::Highlight),
+    ("_None", HoverStyle::None),
+];
+
+fn build_hover_style_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let hover_style = controller.borrow().config.hover_style;
+
+    let on_select = clone!(@strong controller => move |style: HoverStyle| {
+        if controller.borrow().config.hover_style != style {
+            controller.borrow_mut().set_hover_style(style);
+        }
+    });
+    let (menu, items) = build_radio_submenu("Hover St_yle", &HOVER_STYLE_PRESETS, hover_style, on_select);
+
+    // follow changes made by applying a theme
+    for (item, &(_, style)) in items.into_iter().zip(HOVER_STYLE_PRESETS.iter()) {
+        controller.borrow_mut().add_settings_observer(clone!(@weak item => move |controller| {
+            if controller.config.hover_style == style {
+                glib::idle_add_local_once(move || item.set_active(true));
+            }
+        }));
+    }
+
+    menu
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Nudge Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const NUDGE_PRESETS: [(&str, u32); 4] = [
+    ("_Never", 0),
+    ("After _15 Seconds", 15),
+    ("After _30 Seconds", 30),
+    ("After _60 Seconds", 60),
+];
+
+fn build_nudge_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let nudge_seconds = controller.borrow().config.nudge_seconds;
+
+    let on_select = clone!(@strong controller => move |seconds: u32| {
+        controller.borrow_mut().set_nudge_seconds(seconds);
+    });
+    build_radio_submenu("Nud_ge When Idle", &NUDGE_PRESETS, nudge_seconds, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Hint Level Submenu
+////////////////////////////////////////////////////////////////////////////////
+
+const HINT_LEVEL_PRESETS: [(&str, HintLevel); 3] = [
+    ("_One Card", HintLevel::Single),
+    ("_Two Cards", HintLevel::Pair),
+    ("The _Whole Set", HintLevel::Full),
+];
+
+fn build_hint_level_submenu(menu_data: MenuData) -> MenuItem {
+    let (_window, _accel_group, controller) = menu_data;
+    let hint_level = controller.borrow().config.hint_level;
+
+    let on_select = clone!(@strong controller => move |level: HintLevel| {
+        controller.borrow_mut().set_hint_level(level);
+    });
+    build_radio_submenu("Hint _Size", &HINT_LEVEL_PRESETS, hint_level, on_select).0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Radio Submenus
+////////////////////////////////////////////////////////////////////////////////
+
+/// Builds a submenu with a radio item for each of `choices`, marking
+/// the one for `current`, and calls `on_select` with the choice when
+/// an item is picked. A setting that isn't one of the choices (from an
+/// edited config file, say) is marked by a disabled "Custom" item. The
+/// choice items are returned in order, so that callers can follow
+/// changes made elsewhere.
+fn build_radio_submenu<T, F>(label: &str, choices: &[(&str, T)], current: T, on_select: F)
+    -> (MenuItem, Vec<gtk::RadioMenuItem>)
+where
+    T: Copy + PartialEq + 'static,
+    F: Fn(T) + 'static,
+{
+    let menu = MenuItem::with_mnemonic(label);
+    let submenu = gtk::Menu::new();
+    let on_select = Rc::new(on_select);
+    let mut items: Vec<gtk::RadioMenuItem> = vec![];
+
+    // a radio group always has an active item, so this one holds the
+    // mark until (unless) one of the choices is picked
+    let custom = gtk::RadioMenuItem::with_mnemonic("_Custom");
+    custom.set_sensitive(false);
+
+    for &(mnemonic, choice) in choices {
+        let item = gtk::RadioMenuItem::with_mnemonic(mnemonic);
+        item.join_group(Some(&custom));
+
+        // reflect config settings
+        if choice == current {
+            item.set_active(true);
+        }
+
+        item.connect_toggled(clone!(@strong on_select => move |w| {
+            if w.is_active() {
+                on_select(choice);
+            }
+        }));
+
+        submenu.append(&item);
+        items.push(item);
+    }
+
+    if !choices.iter().any(|&(_, choice)| choice == current) {
+        submenu.append(&gtk::SeparatorMenuItem::new());
+        submenu.append(&custom);
+    }
+
+    menu.set_submenu(Some(&submenu));
+    (menu, items)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Help Menu
 ////////////////////////////////////////////////////////////////////////////////
@@ -353,15 +1212,38 @@ fn logo_loader() -> Result<PixbufLoader, Error> {
     Ok(loader)
 }
 
-fn logo() -> Option<Pixbuf> {
-    match logo_loader() {
-        Ok(loader) => loader.pixbuf(),
-        Err(_) => None,
+thread_local! {
+    // decoded on first use; pixbufs can't be shared across threads
+    static LOGO: OnceCell<Option<Pixbuf>> = OnceCell::new();
+}
+
+fn decode_logo() -> Option<Pixbuf> {
+    match logo_loader().map(|loader| loader.pixbuf()) {
+        Ok(Some(pixbuf)) => Some(pixbuf),
+        Ok(None) => {
+            log_warn!("Could not decode the logo.");
+            None
+        }
+        Err(err) => {
+            log_warn!("Could not decode the logo: {}", err);
+            None
+        }
     }
 }
 
-fn build_help_menu(window: &ApplicationWindow) -> MenuItem {
+fn logo() -> Option<Pixbuf> {
+    LOGO.with(|logo| logo.get_or_init(decode_logo).clone())
+}
+
+fn build_help_menu(menu_data: MenuData) -> MenuItem {
+    let (window, _accel_group, controller) = menu_data;
+    let first_lesson = MenuItem::with_mnemonic("_First Lesson");
     let about = MenuItem::with_mnemonic("_About");
+
+    first_lesson.connect_activate(clone!(@strong controller => move |_| {
+        controller.borrow_mut().load_first_lesson();
+    }));
+
     about.connect_activate(clone!(@weak window => move |_| {
         let a = gtk::AboutDialog::new();
         a.set_program_name("Marmoset");
@@ -380,13 +1262,23 @@ fn build_help_menu(window: &ApplicationWindow) -> MenuItem {
         }
     }));
 
-    build_menu!("_Help", [about])
+    build_menu!("_Help", [first_lesson, gtk::SeparatorMenuItem::new(), about])
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // Game Messages
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Shows the result of a game action in the status line or a dialog,
+/// depending on the settings.
+fn show_game_message(controller: &Rc<RefCell<Controller>>, message: String, window: &ApplicationWindow) {
+    if controller.borrow().config.use_status_line {
+        controller.borrow_mut().set_status(Some(message));
+    } else {
+        show_message_dialog(Some(message), window);
+    }
+}
+
 fn show_message_dialog(message: Option<String>, window: &ApplicationWindow) {
     if let Some(string) = message {
         let md = gtk::MessageDialog::new(
@@ -403,3 +1295,184 @@ fn show_message_dialog(message: Option<String>, window: &ApplicationWindow) {
         }
     }
 }
+
+fn show_game_over_dialog(controller: &Rc<RefCell<Controller>>, window: &ApplicationWindow) {
+    // the game may have been undone or restarted in the meantime
+    if !controller.borrow().is_finished() {
+        return;
+    }
+
+    let summary = controller.borrow().result_summary();
+    let md = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Info,
+        gtk::ButtonsType::None,
+        "Game Over",
+    );
+    md.set_markup("<big>Game Over</big>");
+    md.set_secondary_text(Some(&summary));
+    md.add_button("_Review Board", gtk::ResponseType::Close);
+    md.add_button("_New Game", gtk::ResponseType::Accept);
+    md.set_default_response(gtk::ResponseType::Accept);
+
+    let response = md.run();
+    unsafe {
+        md.destroy();
+    }
+
+    if response == gtk::ResponseType::Accept {
+        controller.borrow_mut().new_game();
+    }
+}
+
+/// Lists the fastest finished game for each variant and deck.
+fn show_high_scores_dialog(window: &ApplicationWindow) {
+    let lines: Vec<String> = Scores::load().best_times().iter()
+        .map(|best| {
+            let rules = Config { variant: best.variant, ..Config::new() }.rules();
+            let deck = match best.deck {
+                Deck::Simplified => "beginner deck",
+                Deck::Full => "full deck",
+            };
+            let date = glib::DateTime::from_unix_local(best.finished as i64)
+                .and_then(|date| date.format("%Y-%m-%d"))
+                .map(|date| date.to_string())
+                .unwrap_or_default();
+
+            format!("{}, {}: {} on {}", rules.name(), deck, clock_text(Duration::from_secs(best.seconds)), date)
+        })
+        .collect();
+
+    let text = if lines.is_empty() {
+        "Finish a game to set a best time.".to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    let md = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Info,
+        gtk::ButtonsType::Ok,
+        "High Scores",
+    );
+    md.set_markup("<big>High Scores</big>");
+    md.set_secondary_text(Some(&text));
+    md.run();
+    unsafe {
+        md.destroy();
+    }
+}
+
+fn show_statistics_dialog(window: &ApplicationWindow, stats: GameStats) {
+    let text = format!(
+        "Sets found: {}\nInvalid selections: {}\nHints used: {}\nTimes dealt more cards: {}",
+        stats.sets_found, stats.invalid_selections, stats.hints, stats.deals
+    );
+
+    let md = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Info,
+        gtk::ButtonsType::Ok,
+        "Statistics",
+    );
+    md.set_markup("<big>Statistics</big>");
+    md.set_secondary_text(Some(&text));
+    md.run();
+    unsafe {
+        md.destroy();
+    }
+}
+
+fn prompt_for_text(window: &ApplicationWindow, prompt: &str) -> Option<String> {
+    let md = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Question,
+        gtk::ButtonsType::OkCancel,
+        prompt,
+    );
+    let entry = gtk::Entry::new();
+    entry.set_activates_default(true);
+    md.set_default_response(gtk::ResponseType::Ok);
+    md.content_area().pack_end(&entry, false, false, 0);
+    md.show_all();
+
+    let response = md.run();
+    let text = entry.text().to_string();
+    unsafe {
+        md.destroy();
+    }
+
+    if response == gtk::ResponseType::Ok {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// File Dialogs
+////////////////////////////////////////////////////////////////////////////////
+
+/// Asks for a saved game file to open, or where to save one.
+fn choose_game_file(window: &ApplicationWindow, action: gtk::FileChooserAction) -> Option<PathBuf> {
+    let (title, accept) = match action {
+        gtk::FileChooserAction::Save => ("Save Game", "_Save"),
+        _ => ("Open Game", "_Open"),
+    };
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some(title),
+        Some(window),
+        action,
+        &[
+            ("_Cancel", gtk::ResponseType::Cancel),
+            (accept, gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_do_overwrite_confirmation(true);
+
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some("Saved Games"));
+    filter.add_pattern("*.yml");
+    dialog.add_filter(&filter);
+    if action == gtk::FileChooserAction::Save {
+        dialog.set_current_name("marmoset.yml");
+    }
+
+    let path = if dialog.run() == gtk::ResponseType::Accept {
+        dialog.filename()
+    } else {
+        None
+    };
+
+    unsafe {
+        dialog.destroy();
+    }
+    path
+}
+
+fn choose_folder(window: &ApplicationWindow, title: &str) -> Option<PathBuf> {
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some(title),
+        Some(window),
+        gtk::FileChooserAction::SelectFolder,
+        &[
+            ("_Cancel", gtk::ResponseType::Cancel),
+            ("_Select", gtk::ResponseType::Accept),
+        ],
+    );
+
+    let path = if dialog.run() == gtk::ResponseType::Accept {
+        dialog.filename()
+    } else {
+        None
+    };
+
+    unsafe {
+        dialog.destroy();
+    }
+    path
+}
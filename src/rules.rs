@@ -18,7 +18,36 @@ use core::find::{FindSets, FindSuperSets};
 use core::shuffle::Shuffle;
 
 pub struct Set;
+/// Four cards that split into two pairs completed by the same card.
 pub struct SuperSet;
+/// SuperSet on a fuller table. The groupings, hints, and counts are
+/// exactly SuperSet's; only the deal differs, with 12 cards to start on
+/// the full Set table instead of SuperSet's sparse 10.
+pub struct UltraSet;
+
+/// Random hints avoid favoring any part of the layout. Deterministic
+/// hints always pick the same grouping for the same cards, no matter
+/// where they sit on the tableau.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HintMode { Random, Deterministic }
+
+/// How much of a grouping a hint gives away: one card, two cards, or
+/// the whole grouping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HintLevel { Single, Pair, Full }
+
+/// Orders the cards to search for a hint.
+fn hint_order(cards: &[Card], mode: HintMode) -> Vec<Card> {
+    let mut ordered = cards.to_owned();
+    match mode {
+        // By shuffling here, we randomize both the order of the discovered
+        // sets, as well as the order of the cards within the returned hint.
+        // Otherwise we favor sets and cards earlier in the layout.
+        HintMode::Random => ordered.shuffle(),
+        HintMode::Deterministic => ordered.sort(),
+    }
+    ordered
+}
 
 pub trait Rules {
     fn name(&self) -> &'static str;
@@ -26,10 +55,67 @@ pub trait Rules {
     fn deal_order(&self) -> Vec<usize>;
     fn initial_deal_size(&self) -> usize;
     fn set_size(&self) -> usize;
+    /// Tableau size as (columns, rows). Cells are numbered across each
+    /// row from the top left, with the deck and score badges in the top
+    /// corners and the clock in the bottom right.
+    fn grid_size(&self) -> (usize, usize);
+    /// Whether a stuck table is dealt a set when possible, rather than
+    /// whatever the deck has next.
+    fn guarantee_set_on_deal(&self) -> bool;
     fn valid_set(&self, selection: &[Card]) -> bool;
-    fn hint(&self, cards: &[Card]) -> Option<Vec<Card>>;
+    /// Like `valid_set`, but rejects selections of the wrong size or
+    /// with repeated cards instead of asserting.
+    fn is_valid_selection(&self, selection: &[Card]) -> bool {
+        let distinct = selection.iter().enumerate()
+            .all(|(ix, card)| !selection[ix + 1..].contains(card));
+
+        selection.len() == self.set_size() && distinct && self.valid_set(selection)
+    }
+    fn hint(&self, cards: &[Card], mode: HintMode) -> Option<Vec<Card>>;
+    /// Like `hint`, but trimmed to one card or completed to the whole
+    /// grouping as `level` asks. The cards of a `Pair` hint come first.
+    fn hint_with_level(&self, cards: &[Card], mode: HintMode, level: HintLevel) -> Option<Vec<Card>> {
+        let mut hint = self.hint(cards, mode)?;
+        match level {
+            HintLevel::Single => hint.truncate(1),
+            HintLevel::Pair => (),
+            HintLevel::Full => {
+                let group = self.find_all(cards)
+                    .into_iter()
+                    .find(|group| hint.iter().all(|card| group.contains(card)))?;
+                let rest: Vec<Card> = group.into_iter().filter(|card| !hint.contains(card)).collect();
+                hint.extend(rest);
+            }
+        }
+        Some(hint)
+    }
     fn stuck(&self, cards: &[Card]) -> bool;
     fn count_sets(&self, cards: &[Card]) -> usize;
+    /// Every valid grouping in `cards`, regardless of variant.
+    fn find_all(&self, cards: &[Card]) -> Vec<Vec<Card>>;
+    /// Spells out why a valid grouping works, for teaching.
+    fn explain(&self, cards: &[Card]) -> String;
+}
+
+/// Describes each feature as all the same or all different.
+fn describe_features(cards: &[Card]) -> String {
+    let features: [(&str, &str, fn(&Card) -> u8); 4] = [
+        ("number", "numbers", |card| card.count()),
+        ("shape", "shapes", |card| card.shape() as u8),
+        ("color", "colors", |card| card.color() as u8),
+        ("shading", "shadings", |card| card.shading() as u8),
+    ];
+
+    features.iter()
+        .map(|&(singular, plural, value)| {
+            if cards.iter().all(|card| value(card) == value(&cards[0])) {
+                format!("same {}", singular)
+            } else {
+                format!("different {}", plural)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl Rules for Set {
@@ -49,6 +135,8 @@ impl Rules for Set {
 
     fn initial_deal_size(&self) -> usize { 12 }
     fn set_size(&self) -> usize { 3 }
+    fn grid_size(&self) -> (usize, usize) { (5, 4) }
+    fn guarantee_set_on_deal(&self) -> bool { true }
 
     fn valid_set(&self, cards: &[Card]) -> bool {
         assert_eq!(cards.len(), self.set_size());
@@ -56,14 +144,8 @@ impl Rules for Set {
         triple.to_set().is_some()
     }
 
-    fn hint(&self, cards: &[Card]) -> Option<Vec<Card>> {
-        let mut shuffled = cards.to_owned();
-        // By shuffling here, we randomize both the order of the discovered
-        // sets, as well as the order of the cards within the returned hint
-        // pair. Otherwise we favor sets and cards earlier in the layout.
-        shuffled.shuffle();
-
-        if let Some(set) = shuffled.find_first_set() {
+    fn hint(&self, cards: &[Card], mode: HintMode) -> Option<Vec<Card>> {
+        if let Some(set) = hint_order(cards, mode).find_first_set() {
             let (a,b,_) = set.cards();
             Some(vec![a, b])
         } else {
@@ -78,6 +160,20 @@ impl Rules for Set {
     fn count_sets(&self, cards: &[Card]) -> usize {
         cards.count_sets()
     }
+
+    fn find_all(&self, cards: &[Card]) -> Vec<Vec<Card>> {
+        cards.find_all_sets()
+            .into_iter()
+            .map(|set| {
+                let (a,b,c) = set.cards();
+                vec![a, b, c]
+            })
+            .collect()
+    }
+
+    fn explain(&self, cards: &[Card]) -> String {
+        describe_features(cards)
+    }
 }
 
 impl Rules for SuperSet {
@@ -97,18 +193,16 @@ impl Rules for SuperSet {
 
     fn initial_deal_size(&self) -> usize { 10 }
     fn set_size(&self) -> usize { 4 }
+    fn grid_size(&self) -> (usize, usize) { (5, 4) }
+    fn guarantee_set_on_deal(&self) -> bool { true }
 
     fn valid_set(&self, cards: &[Card]) -> bool {
         assert_eq!(cards.len(), self.set_size());
         cards.contains_superset()
     }
 
-    fn hint(&self, cards: &[Card]) -> Option<Vec<Card>> {
-        let mut shuffled = cards.to_owned();
-        // Same rationale for randomizing as in rules::Set::hint().
-        shuffled.shuffle();
-
-        if let Some(superset) = shuffled.find_first_superset() {
+    fn hint(&self, cards: &[Card], mode: HintMode) -> Option<Vec<Card>> {
+        if let Some(superset) = hint_order(cards, mode).find_first_superset() {
             let (a,b) = superset.left(); // or right
             Some(vec![a, b])
         } else {
@@ -123,4 +217,191 @@ impl Rules for SuperSet {
     fn count_sets(&self, cards: &[Card]) -> usize {
         cards.count_supersets()
     }
+
+    fn find_all(&self, cards: &[Card]) -> Vec<Vec<Card>> {
+        cards.find_all_supersets()
+            .into_iter()
+            .map(|superset| {
+                let (a,b) = superset.left();
+                let (c,d) = superset.right();
+                vec![a, b, c, d]
+            })
+            .collect()
+    }
+
+    fn explain(&self, cards: &[Card]) -> String {
+        // a superset is two pairs whose sets share a third card
+        if cards.contains_superset() {
+            "both pairs are completed by the same card".to_string()
+        } else {
+            String::new()
+        }
+    }
+}
+
+impl Rules for UltraSet {
+    fn name(&self) -> &'static str {
+        "UltraSet"
+    }
+
+    fn deal_order(&self) -> Vec<usize> {
+        Set.deal_order()
+    }
+
+    fn initial_deal_size(&self) -> usize { 12 }
+    fn set_size(&self) -> usize { 4 }
+    fn grid_size(&self) -> (usize, usize) { (5, 4) }
+    fn guarantee_set_on_deal(&self) -> bool { true }
+
+    // the groupings themselves are SuperSet's
+
+    fn valid_set(&self, cards: &[Card]) -> bool {
+        SuperSet.valid_set(cards)
+    }
+
+    fn hint(&self, cards: &[Card], mode: HintMode) -> Option<Vec<Card>> {
+        SuperSet.hint(cards, mode)
+    }
+
+    fn stuck(&self, cards: &[Card]) -> bool {
+        SuperSet.stuck(cards)
+    }
+
+    fn count_sets(&self, cards: &[Card]) -> usize {
+        SuperSet.count_sets(cards)
+    }
+
+    fn find_all(&self, cards: &[Card]) -> Vec<Vec<Card>> {
+        SuperSet.find_all(cards)
+    }
+
+    fn explain(&self, cards: &[Card]) -> String {
+        SuperSet.explain(cards)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::deck::cards;
+
+    fn check_find_all(rules: &dyn Rules) {
+        let cards = cards();
+        let all = rules.find_all(&cards);
+        assert_eq!(all.len(), rules.count_sets(&cards));
+
+        for grouping in all.iter().take(500) {
+            assert_eq!(grouping.len(), rules.set_size());
+            assert!(rules.valid_set(grouping));
+        }
+    }
+
+    #[test]
+    fn check_find_all_sets() {
+        check_find_all(&Set);
+    }
+
+    #[test]
+    fn check_find_all_supersets() {
+        check_find_all(&SuperSet);
+    }
+
+    #[test]
+    fn check_find_all_ultrasets() {
+        check_find_all(&UltraSet);
+    }
+
+    #[test]
+    fn check_valid_selection() {
+        let cards = cards();
+        let set = &Set.find_all(&cards)[0];
+        let superset = &SuperSet.find_all(&cards)[0];
+
+        assert!(Set.is_valid_selection(set));
+        assert!(SuperSet.is_valid_selection(superset));
+
+        // wrong sizes are rejected rather than asserted
+        assert!(!Set.is_valid_selection(superset));
+        assert!(!SuperSet.is_valid_selection(set));
+        assert!(!Set.is_valid_selection(&[]));
+
+        // a card can't be used twice
+        assert!(!Set.is_valid_selection(&[set[0], set[0], set[0]]));
+    }
+
+    #[test]
+    fn check_explain() {
+        // these cards only differ in number
+        let set: Vec<_> = (0..3).map(|i| Card::new(i * 27)).collect();
+        assert_eq!(Set.explain(&set), "different numbers, same shape, same color, same shading");
+
+        let cards = cards();
+        let superset = &SuperSet.find_all(&cards)[0];
+        assert!(SuperSet.explain(superset).starts_with("both pairs"));
+        assert!(SuperSet.explain(&set).is_empty());
+    }
+
+    #[test]
+    fn check_deterministic_hint() {
+        let rules: [&dyn Rules; 3] = [&Set, &SuperSet, &UltraSet];
+        for rules in rules.iter() {
+            let mut cards = cards();
+            cards.truncate(20);
+            let hint = rules.hint(&cards, HintMode::Deterministic).unwrap();
+
+            // the layout order doesn't matter
+            for _ in 0..10 {
+                cards.shuffle();
+                assert_eq!(rules.hint(&cards, HintMode::Deterministic), Some(hint.clone()));
+            }
+
+            // and the hint still leads to a valid grouping
+            assert!(rules.find_all(&cards).iter()
+                    .any(|group| hint.iter().all(|card| group.contains(card))));
+        }
+    }
+
+    #[test]
+    fn check_deal_order_fits_grid() {
+        let rules: [&dyn Rules; 3] = [&Set, &SuperSet, &UltraSet];
+        for rules in rules.iter() {
+            let (columns, rows) = rules.grid_size();
+            let order = rules.deal_order();
+            assert!(order.len() >= rules.initial_deal_size(), "{}", rules.name());
+
+            for (ix, &cell) in order.iter().enumerate() {
+                assert!(cell < columns * rows, "{} deals off the grid", rules.name());
+                // the top corners hold the deck and score badges
+                assert!(cell != 0 && cell != columns - 1, "{} deals onto a badge", rules.name());
+                assert!(!order[ix + 1..].contains(&cell), "{} deals twice into {}", rules.name(), cell);
+            }
+        }
+    }
+
+    #[test]
+    fn check_hint_levels() {
+        let rules: [&dyn Rules; 3] = [&Set, &SuperSet, &UltraSet];
+        for rules in rules.iter() {
+            let mut cards = cards();
+            cards.truncate(20);
+            let mode = HintMode::Deterministic;
+            let pair = rules.hint(&cards, mode).unwrap();
+
+            assert_eq!(rules.hint_with_level(&cards, mode, HintLevel::Pair), Some(pair.clone()));
+            assert_eq!(rules.hint_with_level(&cards, mode, HintLevel::Single), Some(pair[..1].to_vec()));
+
+            // the full hint extends the pair into a valid grouping
+            let full = rules.hint_with_level(&cards, mode, HintLevel::Full).unwrap();
+            assert_eq!(full[..2], pair[..]);
+            assert!(rules.is_valid_selection(&full));
+        }
+
+        // no hint at all on a stuck board
+        let stuck: Vec<_> = (0..2).map(Card::new).collect();
+        assert_eq!(Set.hint_with_level(&stuck, HintMode::Random, HintLevel::Full), None);
+    }
 }
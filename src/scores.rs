@@ -0,0 +1,196 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persistent best scores.
+
+use serde_yaml;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use crate::config::{Config, ConfigError, ConfigResult, Deck, Variant};
+use core::log_error;
+
+/// Most sets found in a timed challenge of a given length.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct ChallengeBest {
+    variant: Variant,
+    seconds: u32,
+    sets: usize,
+}
+
+/// Fastest finish of a whole game with a given variant and deck.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BestTime {
+    pub variant: Variant,
+    pub deck: Deck,
+    pub seconds: u64,
+    /// When the game was finished, in seconds since the Unix epoch
+    pub finished: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scores {
+    challenges: Vec<ChallengeBest>,
+    times: Vec<BestTime>,
+}
+
+impl Scores {
+    pub fn path() -> ConfigResult<PathBuf> {
+        Config::config_dir().map(|dir| dir.join("scores.yml"))
+    }
+
+    pub fn load() -> Scores {
+        let mut serialized = String::new();
+
+        Scores::path()
+            .and_then(|path| File::open(&path)
+                      .map_err(ConfigError::Io))
+            .and_then(|mut file| file.read_to_string(&mut serialized)
+                      .map_err(ConfigError::Io))
+            .and_then(|_| serde_yaml::from_str(&serialized)
+                      .map_err(ConfigError::Yaml))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let serialized = serde_yaml::to_string(&self).unwrap();
+
+        Scores::path()
+            .and_then(|path| File::create(&path)
+                      .map_err(ConfigError::Io))
+            .and_then(|mut file| file.write_all(serialized.as_bytes())
+                      .map_err(ConfigError::Io))
+            .unwrap_or_else(|err| {
+                log_error!("Could not save scores: {}", err);
+            });
+    }
+
+    pub fn best_challenge(&self, variant: Variant, seconds: u32) -> Option<usize> {
+        self.challenges.iter()
+            .find(|best| best.variant == variant && best.seconds == seconds)
+            .map(|best| best.sets)
+    }
+
+    /// Records the result of a challenge. Returns true if it's a new best.
+    pub fn record_challenge(&mut self, variant: Variant, seconds: u32, sets: usize) -> bool {
+        let entry = self.challenges.iter_mut()
+            .find(|best| best.variant == variant && best.seconds == seconds);
+
+        match entry {
+            Some(best) if best.sets >= sets => false,
+            Some(best) => {
+                best.sets = sets;
+                true
+            }
+            None => {
+                self.challenges.push(ChallengeBest { variant, seconds, sets });
+                true
+            }
+        }
+    }
+
+    pub fn best_times(&self) -> &[BestTime] {
+        &self.times
+    }
+
+    /// Records the time of a finished game. Returns true if it's the
+    /// fastest for its variant and deck.
+    pub fn record_time(&mut self, time: BestTime) -> bool {
+        let entry = self.times.iter_mut()
+            .find(|best| best.variant == time.variant && best.deck == time.deck);
+
+        match entry {
+            Some(best) if best.seconds <= time.seconds => false,
+            Some(best) => {
+                *best = time;
+                true
+            }
+            None => {
+                self.times.push(time);
+                true
+            }
+        }
+    }
+}
+
+/// Where a controller keeps its records. Headless controllers keep
+/// them in memory, so tests never touch the player's scores file.
+pub enum ScoreStore {
+    File,
+    Memory(RefCell<Scores>),
+}
+
+impl ScoreStore {
+    pub fn load(&self) -> Scores {
+        match self {
+            ScoreStore::File => Scores::load(),
+            ScoreStore::Memory(scores) => scores.borrow().clone(),
+        }
+    }
+
+    pub fn save(&self, scores: &Scores) {
+        match self {
+            ScoreStore::File => scores.save(),
+            ScoreStore::Memory(kept) => *kept.borrow_mut() = scores.clone(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_challenge_bests() {
+        let mut scores = Scores::default();
+        assert_eq!(scores.best_challenge(Variant::Set, 60), None);
+
+        assert!(scores.record_challenge(Variant::Set, 60, 5));
+        assert!(!scores.record_challenge(Variant::Set, 60, 4));
+        assert!(!scores.record_challenge(Variant::Set, 60, 5));
+        assert!(scores.record_challenge(Variant::Set, 60, 7));
+
+        // bests are tracked per variant and duration
+        assert!(scores.record_challenge(Variant::SuperSet, 60, 2));
+        assert!(scores.record_challenge(Variant::Set, 120, 3));
+
+        assert_eq!(scores.best_challenge(Variant::Set, 60), Some(7));
+        assert_eq!(scores.best_challenge(Variant::SuperSet, 60), Some(2));
+        assert_eq!(scores.best_challenge(Variant::Set, 120), Some(3));
+    }
+
+    #[test]
+    fn check_best_times() {
+        let time = |deck, seconds, finished| BestTime { variant: Variant::Set, deck, seconds, finished };
+        let mut scores = Scores::default();
+        assert!(scores.best_times().is_empty());
+
+        assert!(scores.record_time(time(Deck::Full, 300, 1)));
+        assert!(!scores.record_time(time(Deck::Full, 300, 2)));
+        assert!(!scores.record_time(time(Deck::Full, 400, 3)));
+        assert!(scores.record_time(time(Deck::Full, 250, 4)));
+
+        // times are tracked per deck, and keep the date of the best
+        assert!(scores.record_time(time(Deck::Simplified, 90, 5)));
+        assert_eq!(scores.best_times(), &[time(Deck::Full, 250, 4), time(Deck::Simplified, 90, 5)]);
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Saved games that can be resumed on a later launch, either the
+//! automatic save or any number of named slots.
+
+use serde_yaml;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::{error, fmt};
+
+use crate::config::{Config, ConfigError, ConfigResult};
+use crate::controller::UndoItem;
+use crate::game_state::GameState;
+use core::log_error;
+
+/// Everything needed to pick a game back up where it was left off.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub config: Config,
+    pub state: GameState,
+    pub undo_stack: Vec<UndoItem>,
+    pub redo_stack: Vec<UndoItem>,
+}
+
+impl Session {
+    pub fn path() -> ConfigResult<PathBuf> {
+        Config::config_dir().map(|dir| dir.join("session.yml"))
+    }
+
+    pub fn load() -> ConfigResult<Session> {
+        Session::load_from(&Session::path()?)
+    }
+
+    pub fn save(&self) -> ConfigResult<()> {
+        self.save_to(&Session::path()?)
+    }
+
+    pub fn load_slot(slot: &str) -> ConfigResult<Session> {
+        Session::load_from(&slot_path(slot)?)
+    }
+
+    pub fn save_slot(&self, slot: &str) -> ConfigResult<()> {
+        self.save_to(&slot_path(slot)?)
+    }
+
+    /// Reads a session from any file, such as one the player chose.
+    pub fn load_from(path: &Path) -> ConfigResult<Session> {
+        let mut serialized = String::new();
+        let mut file = File::open(path)?;
+        file.read_to_string(&mut serialized)?;
+
+        serde_yaml::from_str(&serialized).map_err(ConfigError::Yaml)
+    }
+
+    pub fn save_to(&self, path: &Path) -> ConfigResult<()> {
+        let serialized = serde_yaml::to_string(self)?;
+        let mut file = File::create(path)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+
+    /// Removes any saved session so it isn't offered again.
+    pub fn discard() {
+        if let Ok(path) = Session::path() {
+            if path.exists() {
+                fs::remove_file(&path).unwrap_or_else(|err| {
+                    log_error!("Could not remove saved game: {}", err);
+                });
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Slots
+////////////////////////////////////////////////////////////////////////////////
+
+/// Longest slot name kept, in characters.
+const MAX_SLOT_NAME: usize = 64;
+
+/// Turns a player's name for a saved game into a slot name that is
+/// safe to use as a file name. Letters, digits, '-', and '_' are kept,
+/// and anything else becomes '_'. Returns None if nothing usable is left.
+pub fn slot_name(name: &str) -> Option<String> {
+    let slot: String = name
+        .trim()
+        .chars()
+        .take(MAX_SLOT_NAME)
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    if slot.chars().all(|c| c == '_') {
+        None
+    } else {
+        Some(slot)
+    }
+}
+
+/// A slot name for `name` that's not among `existing`, numbered if
+/// needed. Names differing only in case collide, since some file
+/// systems can't tell them apart.
+pub fn unused_slot_name(name: &str, existing: &[String]) -> Option<String> {
+    let base = slot_name(name)?;
+    let taken = |slot: &str| existing.iter().any(|s| s.to_lowercase() == slot.to_lowercase());
+
+    let mut slot = base.clone();
+    let mut n = 2;
+    while taken(&slot) {
+        slot = format!("{}-{}", base, n);
+        n += 1;
+    }
+    Some(slot)
+}
+
+fn saves_dir() -> ConfigResult<PathBuf> {
+    let dir = Config::config_dir()?.join("saves");
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+fn slot_path(slot: &str) -> ConfigResult<PathBuf> {
+    saves_dir().map(|dir| dir.join(format!("{}.yml", slot)))
+}
+
+/// Names of the saved game slots, in alphabetical order.
+pub fn list_slots() -> ConfigResult<Vec<String>> {
+    let mut slots = vec![];
+    for entry in fs::read_dir(saves_dir()?)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "yml") {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                slots.push(stem.to_string());
+            }
+        }
+    }
+
+    slots.sort();
+    Ok(slots)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// SlotError
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum SlotError {
+    /// The name has nothing that can go in a file name
+    BadName,
+    Config(ConfigError),
+}
+
+impl fmt::Display for SlotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SlotError::BadName => write!(f, "Saved games need a name with letters or digits."),
+            SlotError::Config(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for SlotError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            SlotError::BadName => None,
+            SlotError::Config(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<ConfigError> for SlotError {
+    fn from(err: ConfigError) -> SlotError {
+        SlotError::Config(err)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_slot_name() {
+        assert_eq!(slot_name("  Lunch break "), Some("Lunch_break".to_string()));
+        assert_eq!(slot_name("../../etc/passwd"), Some("______etc_passwd".to_string()));
+        assert_eq!(slot_name("café-2"), Some("café-2".to_string()));
+        assert_eq!(slot_name(""), None);
+        assert_eq!(slot_name("/.?"), None);
+        assert_eq!(slot_name(&"x".repeat(100)).unwrap().len(), MAX_SLOT_NAME);
+    }
+
+    #[test]
+    fn check_unused_slot_name() {
+        let existing = vec!["game".to_string(), "game-2".to_string()];
+        assert_eq!(unused_slot_name("new", &existing), Some("new".to_string()));
+        assert_eq!(unused_slot_name("game", &existing), Some("game-3".to_string()));
+        assert_eq!(unused_slot_name("Game", &existing), Some("Game-3".to_string()));
+        assert_eq!(unused_slot_name("?", &existing), None);
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright (C) 2017 Steve Sprang
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compact codes for sharing a starting board.
+//!
+//! A share code packs the deck seed, variant, and deck type along with
+//! an 8-bit checksum into 75 bits, which are written as 15 characters
+//! of Crockford's base32 in groups of five: `XXXXX-XXXXX-XXXXX`. The
+//! alphabet avoids easily confused letters, and parsing is forgiving
+//! about case, separators, and the usual look-alikes (O/0, I/L/1).
+
+use std::str::FromStr;
+use std::{error, fmt};
+
+use crate::config::{Deck, Variant};
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LENGTH: usize = 15;
+const GROUP_LENGTH: usize = 5;
+const PAYLOAD_BITS: u32 = 66;
+/// UltraSet came after the layout above was settled, so its flag rides
+/// in the spare top bit, past the checksum. Older codes leave it clear.
+const ULTRA_BIT: u32 = 74;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShareCode {
+    pub seed: u64,
+    pub variant: Variant,
+    pub deck: Deck,
+}
+
+impl ShareCode {
+    fn payload(&self) -> u128 {
+        let variant: u128 = match self.variant {
+            Variant::Set => 0,
+            Variant::SuperSet => 1,
+            Variant::UltraSet => 0b100,
+        };
+        let deck: u128 = match self.deck {
+            Deck::Full => 0,
+            Deck::Simplified => 1,
+        };
+
+        u128::from(self.seed) | (variant << 64) | (deck << 65)
+    }
+
+    pub fn encode(&self) -> String {
+        let payload = self.payload();
+        let ultra = payload >> PAYLOAD_BITS;
+        let bits = (payload & payload_mask())
+            | (u128::from(checksum(payload)) << PAYLOAD_BITS)
+            | (ultra << ULTRA_BIT);
+        let mut code = String::new();
+
+        for ix in 0..CODE_LENGTH {
+            if ix > 0 && ix % GROUP_LENGTH == 0 {
+                code.push('-');
+            }
+            // most significant digit first
+            let shift = 5 * (CODE_LENGTH - 1 - ix);
+            let digit = (bits >> shift) & 0x1f;
+            code.push(ALPHABET[digit as usize] as char);
+        }
+
+        code
+    }
+}
+
+fn payload_mask() -> u128 {
+    (1u128 << PAYLOAD_BITS) - 1
+}
+
+/// An 8-bit FNV-1a hash of the payload, to catch typos.
+fn checksum(payload: u128) -> u8 {
+    let hash = payload.to_le_bytes()[..9]
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    (hash >> 56) as u8
+}
+
+fn digit_value(c: char) -> Option<u128> {
+    let c = match c.to_ascii_uppercase() {
+        'O' => '0',
+        'I' | 'L' => '1',
+        c => c,
+    };
+
+    ALPHABET.iter().position(|&a| a as char == c).map(|ix| ix as u128)
+}
+
+impl fmt::Display for ShareCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+impl FromStr for ShareCode {
+    type Err = ShareError;
+
+    fn from_str(s: &str) -> Result<ShareCode, ShareError> {
+        let mut bits = 0u128;
+        let mut length = 0;
+
+        for c in s.chars().filter(|&c| c != '-' && !c.is_whitespace()) {
+            let value = digit_value(c).ok_or(ShareError::Character(c))?;
+            if length < CODE_LENGTH {
+                bits = (bits << 5) | value;
+            }
+            length += 1;
+        }
+
+        if length != CODE_LENGTH {
+            return Err(ShareError::Length(length));
+        }
+
+        let ultra = bits >> ULTRA_BIT;
+        let payload = (bits & payload_mask()) | (ultra << PAYLOAD_BITS);
+        if (bits >> PAYLOAD_BITS) & 0xff != u128::from(checksum(payload)) {
+            return Err(ShareError::Checksum);
+        }
+
+        let variant = match ((payload >> 64) & 1, ultra) {
+            (0, 0) => Variant::Set,
+            (1, 0) => Variant::SuperSet,
+            (0, _) => Variant::UltraSet,
+            _ => return Err(ShareError::Checksum),
+        };
+
+        Ok(ShareCode {
+            seed: payload as u64,
+            variant,
+            deck: if (payload >> 65) & 1 == 0 { Deck::Full } else { Deck::Simplified },
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ShareError
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShareError {
+    /// The code didn't have the right number of characters.
+    Length(usize),
+    /// The code contained a character outside the alphabet.
+    Character(char),
+    /// The code was well formed but doesn't check out (probably a typo).
+    Checksum,
+}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShareError::Length(n) =>
+                write!(f, "A share code has {} characters, but {} were given.", CODE_LENGTH, n),
+            ShareError::Character(c) =>
+                write!(f, "'{}' can't appear in a share code.", c),
+            ShareError::Checksum =>
+                write!(f, "That share code isn't valid. Check it for typos."),
+        }
+    }
+}
+
+impl error::Error for ShareError {}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let seeds = [0, 1, 0xdead_beef, u64::MAX];
+        let variants = [Variant::Set, Variant::SuperSet, Variant::UltraSet];
+        let decks = [Deck::Full, Deck::Simplified];
+
+        for &seed in &seeds {
+            for &variant in &variants {
+                for &deck in &decks {
+                    let share = ShareCode { seed, variant, deck };
+                    let code = share.encode();
+                    assert_eq!(code.len(), CODE_LENGTH + 2);
+                    assert_eq!(code.parse(), Ok(share));
+
+                    // parsing is forgiving about case and separators
+                    let sloppy = code.to_lowercase().replace('-', " ");
+                    assert_eq!(sloppy.parse(), Ok(share));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_codes_from_before_ultraset() {
+        let set = ShareCode { seed: 42, variant: Variant::Set, deck: Deck::Full };
+        let superset = ShareCode { seed: 42, variant: Variant::SuperSet, deck: Deck::Simplified };
+        assert_eq!("3M000-00000-0001A".parse(), Ok(set));
+        assert_eq!("3NG00-00000-0001A".parse(), Ok(superset));
+    }
+
+    #[test]
+    fn check_malformed_codes() {
+        let share = ShareCode { seed: 42, variant: Variant::Set, deck: Deck::Full };
+        let code = share.encode();
+
+        assert_eq!("".parse::<ShareCode>(), Err(ShareError::Length(0)));
+        assert_eq!(code[..10].parse::<ShareCode>(), Err(ShareError::Length(9)));
+        assert_eq!(format!("{}0", code).parse::<ShareCode>(), Err(ShareError::Length(16)));
+        assert_eq!(code.replacen('-', "U", 1).parse::<ShareCode>(), Err(ShareError::Character('U')));
+
+        // change a single digit
+        let mut typo: Vec<char> = code.chars().collect();
+        typo[3] = if typo[3] == '7' { '8' } else { '7' };
+        let typo: String = typo.into_iter().collect();
+        assert_eq!(typo.parse::<ShareCode>(), Err(ShareError::Checksum));
+    }
+}